@@ -0,0 +1,458 @@
+// Constant-product pool math and the `SwapCurve` trait every curve implements
+pub use crate::error::{AmmError, AmmResult};
+use crate::full_math::{mul_div_wide, Q64};
+
+pub const BPS_DENOMINATOR: u128 = 10_000;
+// LP tokens the first deposit into a pool locks for good. The supply then
+// never drops back to a few units, where a single LP token would be worth
+// so much that later deposits round down to nothing.
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+// Pricing rules of a pool. Reserves are always passed as (a, b) with the
+// swap direction, so curves that treat the two sides differently can tell
+// them apart. New curves implement this trait and get picked up by
+// `quote` without changes to the instruction handlers.
+pub trait SwapCurve {
+    // Output and fee for an exact-input swap. Returns (amount_out, fee).
+    fn swap_exact_in(
+        &self,
+        amount_in: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+        fee_bps: u16,
+    ) -> AmmResult<(u64, u64)>;
+
+    // Input and fee to receive exactly `amount_out`. Returns (amount_in, fee).
+    fn swap_exact_out(
+        &self,
+        amount_out: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+        fee_bps: u16,
+    ) -> AmmResult<(u64, u64)>;
+
+    // Deposit taken within the maximums and LP tokens minted for it.
+    // Returns (amount_a, amount_b, lp_tokens).
+    fn deposit(
+        &self,
+        max_amount_a: u64,
+        max_amount_b: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        lp_supply: u64,
+    ) -> AmmResult<(u64, u64, u64)>;
+
+    // Amounts returned for burning `lp_amount`. Returns (amount_a, amount_b).
+    fn withdraw(
+        &self,
+        lp_amount: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        lp_supply: u64,
+    ) -> AmmResult<(u64, u64)> {
+        calculate_withdraw_amounts(lp_amount, reserve_a, reserve_b, lp_supply)
+    }
+
+    // Output `amount_in` would get at the current marginal price, i.e.
+    // without any price impact
+    fn spot_price(
+        &self,
+        amount_in: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+    ) -> AmmResult<u128>;
+
+    // Marginal price of token A in token B as a Q64.64 number, used by the
+    // price oracle
+    fn marginal_price(&self, reserve_a: u64, reserve_b: u64) -> AmmResult<u128>;
+}
+
+// (reserve_in, reserve_out) for a swap in the given direction
+pub fn directional_reserves(reserve_a: u64, reserve_b: u64, a_to_b: bool) -> (u64, u64) {
+    if a_to_b {
+        (reserve_a, reserve_b)
+    } else {
+        (reserve_b, reserve_a)
+    }
+}
+
+// x * y = k
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConstantProductCurve;
+
+impl SwapCurve for ConstantProductCurve {
+    fn swap_exact_in(
+        &self,
+        amount_in: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+        fee_bps: u16,
+    ) -> AmmResult<(u64, u64)> {
+        let (reserve_in, reserve_out) = directional_reserves(reserve_a, reserve_b, a_to_b);
+
+        calculate_swap_output(amount_in, reserve_in, reserve_out, fee_bps)
+    }
+
+    fn swap_exact_out(
+        &self,
+        amount_out: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+        fee_bps: u16,
+    ) -> AmmResult<(u64, u64)> {
+        let (reserve_in, reserve_out) = directional_reserves(reserve_a, reserve_b, a_to_b);
+
+        calculate_swap_input(amount_out, reserve_in, reserve_out, fee_bps)
+    }
+
+    fn deposit(
+        &self,
+        max_amount_a: u64,
+        max_amount_b: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        lp_supply: u64,
+    ) -> AmmResult<(u64, u64, u64)> {
+        let (amount_a, amount_b) =
+            calculate_deposit_amounts(max_amount_a, max_amount_b, reserve_a, reserve_b)?;
+
+        let lp_tokens =
+            calculate_lp_tokens_to_mint(amount_a, amount_b, reserve_a, reserve_b, lp_supply)?;
+
+        Ok((amount_a, amount_b, lp_tokens))
+    }
+
+    fn spot_price(
+        &self,
+        amount_in: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+    ) -> AmmResult<u128> {
+        let (reserve_in, reserve_out) = directional_reserves(reserve_a, reserve_b, a_to_b);
+
+        mul_div(amount_in as u128, reserve_out as u128, reserve_in as u128)
+    }
+
+    fn marginal_price(&self, reserve_a: u64, reserve_b: u64) -> AmmResult<u128> {
+        mul_div_wide(reserve_b as u128, Q64, reserve_a as u128)
+    }
+}
+
+// a * b / c rounded down
+pub fn mul_div(a: u128, b: u128, c: u128) -> AmmResult<u128> {
+    a.checked_mul(b)
+        .ok_or(AmmError::Overflow)?
+        .checked_div(c)
+        .ok_or(AmmError::Overflow)
+}
+
+// a * b / c rounded up
+pub fn mul_div_ceil(a: u128, b: u128, c: u128) -> AmmResult<u128> {
+    if c == 0 {
+        return Err(AmmError::Overflow);
+    }
+
+    Ok(a.checked_mul(b).ok_or(AmmError::Overflow)?.div_ceil(c))
+}
+
+pub fn to_u64(value: u128) -> AmmResult<u64> {
+    value.try_into().map_err(|_| AmmError::Overflow)
+}
+
+// Swap fee charged on `amount` for a pool fee of `fee_bps`
+pub fn calculate_fee(amount: u128, fee_bps: u16) -> AmmResult<u128> {
+    mul_div(amount, fee_bps as u128, BPS_DENOMINATOR)
+}
+
+// Constant-product output for an exact-input swap. The fee is taken from
+// `amount_in` before applying x * y = k and stays in the reserves, so k can
+// only grow. Returns (amount_out, fee).
+pub fn calculate_swap_output(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_bps: u16,
+) -> AmmResult<(u64, u64)> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(AmmError::EmptyReserves);
+    }
+
+    let fee = calculate_fee(amount_in as u128, fee_bps)?;
+    let amount_in_after_fee = amount_in as u128 - fee;
+
+    // Rounded down in the pool's favour and always below reserve_out
+    let amount_out = mul_div(
+        amount_in_after_fee,
+        reserve_out as u128,
+        reserve_in as u128 + amount_in_after_fee,
+    )?;
+
+    Ok((amount_out as u64, fee as u64))
+}
+
+// Constant-product input needed to receive exactly `amount_out`. Both
+// roundings go up so the pool never gives out more than x * y = k allows.
+// Returns (amount_in, fee).
+pub fn calculate_swap_input(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_bps: u16,
+) -> AmmResult<(u64, u64)> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(AmmError::EmptyReserves);
+    }
+    if amount_out >= reserve_out {
+        return Err(AmmError::InsufficientReserves);
+    }
+
+    let amount_in_after_fee = mul_div_ceil(
+        reserve_in as u128,
+        amount_out as u128,
+        (reserve_out - amount_out) as u128,
+    )?;
+
+    let amount_in = mul_div_ceil(
+        amount_in_after_fee,
+        BPS_DENOMINATOR,
+        BPS_DENOMINATOR - fee_bps as u128,
+    )?;
+
+    // Same fee an exact-input swap of `amount_in` would be charged
+    let fee = calculate_fee(amount_in, fee_bps)?;
+
+    Ok((to_u64(amount_in)?, fee as u64))
+}
+
+// Part of a swap fee that goes to the protocol instead of the LPs
+pub fn calculate_protocol_fee(fee: u64, protocol_fee_bps: u16) -> AmmResult<u64> {
+    let protocol_fee = calculate_fee(fee as u128, protocol_fee_bps)?;
+
+    Ok(protocol_fee as u64)
+}
+
+// Integer square root rounded down (Babylonian method)
+pub fn integer_sqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+
+    let mut x = value;
+    let mut y = x.div_ceil(2);
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+
+    x
+}
+
+// Largest deposit within `max_amount_a` and `max_amount_b` that keeps the
+// current reserve ratio. An empty pool accepts both amounts as given.
+pub fn calculate_deposit_amounts(
+    max_amount_a: u64,
+    max_amount_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+) -> AmmResult<(u64, u64)> {
+    if reserve_a == 0 || reserve_b == 0 {
+        return Ok((max_amount_a, max_amount_b));
+    }
+
+    let amount_b_optimal = mul_div(max_amount_a as u128, reserve_b as u128, reserve_a as u128)?;
+
+    if amount_b_optimal <= max_amount_b as u128 {
+        return Ok((max_amount_a, amount_b_optimal as u64));
+    }
+
+    let amount_a_optimal = mul_div(max_amount_b as u128, reserve_a as u128, reserve_b as u128)?;
+
+    Ok((to_u64(amount_a_optimal)?, max_amount_b))
+}
+
+// LP tokens minted for a deposit of `amount_a` and `amount_b`.
+// The first deposit mints sqrt(a * b), later deposits mint
+// min(a / reserve_a, b / reserve_b) * lp_supply.
+pub fn calculate_lp_tokens_to_mint(
+    amount_a: u64,
+    amount_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    lp_supply: u64,
+) -> AmmResult<u64> {
+    let lp_tokens = if lp_supply == 0 {
+        integer_sqrt(
+            (amount_a as u128)
+                .checked_mul(amount_b as u128)
+                .ok_or(AmmError::Overflow)?,
+        )
+    } else {
+        if reserve_a == 0 || reserve_b == 0 {
+            return Err(AmmError::EmptyReserves);
+        }
+
+        let lp_from_a = mul_div(amount_a as u128, lp_supply as u128, reserve_a as u128)?;
+        let lp_from_b = mul_div(amount_b as u128, lp_supply as u128, reserve_b as u128)?;
+
+        lp_from_a.min(lp_from_b)
+    };
+
+    to_u64(lp_tokens)
+}
+
+// Share of each reserve returned for burning `lp_amount` out of `lp_supply`
+pub fn calculate_withdraw_amounts(
+    lp_amount: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    lp_supply: u64,
+) -> AmmResult<(u64, u64)> {
+    if lp_amount == 0 || lp_amount > lp_supply {
+        return Err(AmmError::InsufficientLpSupply);
+    }
+
+    let amount_a = mul_div(lp_amount as u128, reserve_a as u128, lp_supply as u128)?;
+    let amount_b = mul_div(lp_amount as u128, reserve_b as u128, lp_supply as u128)?;
+
+    // Both are bounded by their reserve, so the casts cannot truncate
+    Ok((amount_a as u64, amount_b as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_sqrt_rounds_down_across_the_whole_range() {
+        assert_eq!(integer_sqrt(0), 0);
+        assert_eq!(integer_sqrt(1), 1);
+        assert_eq!(integer_sqrt(2), 1);
+        assert_eq!(integer_sqrt(3), 1);
+
+        for root in [2u128, 3, 10, 1_000_000_007, 1 << 32, u64::MAX as u128] {
+            assert_eq!(integer_sqrt(root * root), root);
+            assert_eq!(integer_sqrt(root * root - 1), root - 1);
+            assert_eq!(integer_sqrt(root * root + 1), root);
+        }
+
+        // (2^64 - 1)^2 is the largest square below u128::MAX
+        assert_eq!(integer_sqrt(u128::MAX), u64::MAX as u128);
+    }
+
+    #[test]
+    fn empty_pool_takes_the_whole_deposit() {
+        assert_eq!(
+            calculate_deposit_amounts(1_000, 3_000, 0, 0),
+            Ok((1_000, 3_000))
+        );
+    }
+
+    #[test]
+    fn deposit_is_clamped_by_whichever_side_runs_short() {
+        // B at the ratio fits, A goes in whole
+        assert_eq!(
+            calculate_deposit_amounts(100, 500, 1_000, 2_000),
+            Ok((100, 200))
+        );
+        // B would need 200 but only 150 is offered, A is scaled down to match
+        assert_eq!(
+            calculate_deposit_amounts(100, 150, 1_000, 2_000),
+            Ok((75, 150))
+        );
+    }
+
+    #[test]
+    fn first_deposit_mints_the_geometric_mean() {
+        assert_eq!(
+            calculate_lp_tokens_to_mint(4_000_000, 1_000_000, 0, 0, 0),
+            Ok(2_000_000)
+        );
+        // sqrt(6) rounded down
+        assert_eq!(calculate_lp_tokens_to_mint(2, 3, 0, 0, 0), Ok(2));
+        assert_eq!(
+            calculate_lp_tokens_to_mint(u64::MAX, u64::MAX, 0, 0, 0),
+            Ok(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn later_deposits_mint_the_smaller_share() {
+        // A tenth of the pool mints a tenth of the supply
+        assert_eq!(
+            calculate_lp_tokens_to_mint(100, 200, 1_000, 2_000, 5_000),
+            Ok(500)
+        );
+        // The extra B is a donation to the pool, not more LP tokens
+        assert_eq!(
+            calculate_lp_tokens_to_mint(100, 300, 1_000, 2_000, 5_000),
+            Ok(500)
+        );
+        assert_eq!(
+            calculate_lp_tokens_to_mint(100, 200, 0, 2_000, 5_000),
+            Err(AmmError::EmptyReserves)
+        );
+    }
+
+    #[test]
+    fn deposit_and_withdrawal_round_in_the_pools_favour() {
+        for &(max_amount_a, max_amount_b, reserve_a, reserve_b, lp_supply) in &[
+            (10u64, 100u64, 3u64, 7u64, 5u64),
+            (999, 1_001, 1_000, 1_003, 1_001),
+            (1_234_567, 7_654_321, 98_765_431, 12_345_677, 33_333_333),
+        ] {
+            let (amount_a, amount_b) =
+                calculate_deposit_amounts(max_amount_a, max_amount_b, reserve_a, reserve_b)
+                    .unwrap();
+            let lp_tokens =
+                calculate_lp_tokens_to_mint(amount_a, amount_b, reserve_a, reserve_b, lp_supply)
+                    .unwrap();
+            if lp_tokens == 0 {
+                continue;
+            }
+
+            // Withdrawing the fresh LP tokens straight away returns no more
+            // than was deposited
+            let (back_a, back_b) = calculate_withdraw_amounts(
+                lp_tokens,
+                reserve_a + amount_a,
+                reserve_b + amount_b,
+                lp_supply + lp_tokens,
+            )
+            .unwrap();
+            assert!(back_a <= amount_a && back_b <= amount_b);
+        }
+
+        // A third of 10 is rounded down on both sides
+        assert_eq!(calculate_withdraw_amounts(1, 10, 11, 3), Ok((3, 3)));
+    }
+
+    #[test]
+    fn full_withdrawal_returns_both_reserves() {
+        assert_eq!(
+            calculate_withdraw_amounts(5_000, 1_000, 2_000, 5_000),
+            Ok((1_000, 2_000))
+        );
+        assert_eq!(
+            calculate_withdraw_amounts(u64::MAX, u64::MAX, 1, u64::MAX),
+            Ok((u64::MAX, 1))
+        );
+    }
+
+    #[test]
+    fn withdrawal_needs_lp_tokens_within_the_supply() {
+        assert_eq!(
+            calculate_withdraw_amounts(0, 1_000, 2_000, 5_000),
+            Err(AmmError::InsufficientLpSupply)
+        );
+        assert_eq!(
+            calculate_withdraw_amounts(5_001, 1_000, 2_000, 5_000),
+            Err(AmmError::InsufficientLpSupply)
+        );
+    }
+}
@@ -0,0 +1,357 @@
+// Math for concentrated liquidity pools, following Uniswap v3. Sqrt prices
+// and fee growth are Q64.64 fixed-point numbers and liquidity is a u128.
+// Products of two Q64.64 values go through the 256-bit `full_math` helpers.
+use crate::amm::{mul_div, mul_div_ceil, to_u64, AmmError, AmmResult, BPS_DENOMINATOR};
+use crate::full_math::{mul_div_wide, mul_div_wide_ceil, Q64};
+
+// Liquidity plus a signed change
+pub fn add_liquidity_delta(liquidity: u128, delta: i128) -> AmmResult<u128> {
+    if delta >= 0 {
+        liquidity.checked_add(delta as u128)
+    } else {
+        liquidity.checked_sub(delta.unsigned_abs())
+    }
+    .ok_or(AmmError::Overflow)
+}
+
+// Token A held by `liquidity` between two sqrt prices:
+// L * (upper - lower) / (lower * upper)
+pub fn amount_a_delta(
+    sqrt_price_lower: u128,
+    sqrt_price_upper: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> AmmResult<u128> {
+    let difference = sqrt_price_upper - sqrt_price_lower;
+
+    if round_up {
+        let scaled = mul_div_wide_ceil(liquidity, difference, sqrt_price_upper)?;
+        mul_div_wide_ceil(scaled, Q64, sqrt_price_lower)
+    } else {
+        let scaled = mul_div_wide(liquidity, difference, sqrt_price_upper)?;
+        mul_div_wide(scaled, Q64, sqrt_price_lower)
+    }
+}
+
+// Token B held by `liquidity` between two sqrt prices: L * (upper - lower)
+pub fn amount_b_delta(
+    sqrt_price_lower: u128,
+    sqrt_price_upper: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> AmmResult<u128> {
+    let difference = sqrt_price_upper - sqrt_price_lower;
+
+    if round_up {
+        mul_div_wide_ceil(liquidity, difference, Q64)
+    } else {
+        mul_div_wide(liquidity, difference, Q64)
+    }
+}
+
+// Sqrt price after adding `amount_in` to the pool, rounded so the pool never
+// pays out more than the exact price allows
+pub fn next_sqrt_price_from_input(
+    sqrt_price: u128,
+    liquidity: u128,
+    amount_in: u128,
+    a_to_b: bool,
+) -> AmmResult<u128> {
+    if a_to_b {
+        // L * p / (L + amount * p), rounded up
+        let product = mul_div_wide(amount_in, sqrt_price, Q64)?;
+        let denominator = liquidity.checked_add(product).ok_or(AmmError::Overflow)?;
+        mul_div_wide_ceil(liquidity, sqrt_price, denominator)
+    } else {
+        // p + amount / L, rounded down
+        let delta = mul_div_wide(amount_in, Q64, liquidity)?;
+        sqrt_price.checked_add(delta).ok_or(AmmError::Overflow)
+    }
+}
+
+// Token amounts backing `liquidity` in [sqrt_price_lower, sqrt_price_upper)
+// at the current price. Rounded up when depositing and down when withdrawing.
+pub fn position_amounts(
+    sqrt_price: u128,
+    sqrt_price_lower: u128,
+    sqrt_price_upper: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> AmmResult<(u128, u128)> {
+    if sqrt_price < sqrt_price_lower {
+        let amount_a = amount_a_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?;
+        Ok((amount_a, 0))
+    } else if sqrt_price >= sqrt_price_upper {
+        let amount_b = amount_b_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?;
+        Ok((0, amount_b))
+    } else {
+        let amount_a = amount_a_delta(sqrt_price, sqrt_price_upper, liquidity, round_up)?;
+        let amount_b = amount_b_delta(sqrt_price_lower, sqrt_price, liquidity, round_up)?;
+        Ok((amount_a, amount_b))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_price_next: u128,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+}
+
+// One exact-input swap step within a range of constant liquidity, moving the
+// price from `sqrt_price_current` towards `sqrt_price_target`. The fee is
+// taken from the input like on the other curves.
+pub fn compute_swap_step(
+    sqrt_price_current: u128,
+    sqrt_price_target: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee_bps: u16,
+    a_to_b: bool,
+) -> AmmResult<SwapStep> {
+    let fee_bps = fee_bps as u128;
+    let amount_remaining_less_fee = mul_div(
+        amount_remaining as u128,
+        BPS_DENOMINATOR - fee_bps,
+        BPS_DENOMINATOR,
+    )?;
+
+    let amount_in_to_target = if a_to_b {
+        amount_a_delta(sqrt_price_target, sqrt_price_current, liquidity, true)?
+    } else {
+        amount_b_delta(sqrt_price_current, sqrt_price_target, liquidity, true)?
+    };
+
+    let sqrt_price_next = if amount_remaining_less_fee >= amount_in_to_target {
+        sqrt_price_target
+    } else {
+        next_sqrt_price_from_input(
+            sqrt_price_current,
+            liquidity,
+            amount_remaining_less_fee,
+            a_to_b,
+        )?
+    };
+    let reached_target = sqrt_price_next == sqrt_price_target;
+
+    let amount_in = if reached_target {
+        amount_in_to_target
+    } else if a_to_b {
+        amount_a_delta(sqrt_price_next, sqrt_price_current, liquidity, true)?
+    } else {
+        amount_b_delta(sqrt_price_current, sqrt_price_next, liquidity, true)?
+    };
+
+    let amount_out = if a_to_b {
+        amount_b_delta(sqrt_price_next, sqrt_price_current, liquidity, false)?
+    } else {
+        amount_a_delta(sqrt_price_current, sqrt_price_next, liquidity, false)?
+    };
+
+    // A partial step keeps the whole remainder as fee, a full one charges the
+    // fee on what it actually used
+    let fee = if reached_target {
+        mul_div_ceil(amount_in, fee_bps, BPS_DENOMINATOR - fee_bps)?
+    } else {
+        (amount_remaining as u128).saturating_sub(amount_in)
+    };
+
+    Ok(SwapStep {
+        sqrt_price_next,
+        amount_in: to_u64(amount_in)?,
+        amount_out: to_u64(amount_out)?,
+        fee: to_u64(fee)?,
+    })
+}
+
+// Fee growth per unit of liquidity inside [tick_lower, tick_upper), from the
+// global growth and the growth recorded outside each boundary tick. All
+// fee growth values wrap, only their differences are meaningful.
+pub fn fee_growth_inside(
+    tick_current: i32,
+    tick_lower: i32,
+    tick_upper: i32,
+    fee_growth_global: u128,
+    fee_growth_outside_lower: u128,
+    fee_growth_outside_upper: u128,
+) -> u128 {
+    let below = if tick_current >= tick_lower {
+        fee_growth_outside_lower
+    } else {
+        fee_growth_global.wrapping_sub(fee_growth_outside_lower)
+    };
+    let above = if tick_current < tick_upper {
+        fee_growth_outside_upper
+    } else {
+        fee_growth_global.wrapping_sub(fee_growth_outside_upper)
+    };
+
+    fee_growth_global.wrapping_sub(below).wrapping_sub(above)
+}
+
+// Fees earned by `liquidity` since the inside growth was last `checkpoint`
+pub fn fees_earned(fee_growth_inside: u128, checkpoint: u128, liquidity: u128) -> AmmResult<u64> {
+    let growth = fee_growth_inside.wrapping_sub(checkpoint);
+    let fees = mul_div_wide(growth, liquidity, Q64)?;
+
+    fees.try_into().map_err(|_| AmmError::Overflow)
+}
+
+// Fee growth per unit of liquidity for `fee` earned by `liquidity`, zero
+// when no liquidity is in range
+pub fn fee_growth_delta(fee: u64, liquidity: u128) -> u128 {
+    ((fee as u128) << 64).checked_div(liquidity).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tick_math::sqrt_price_from_tick;
+
+    const LIQUIDITY: u128 = 1_000_000_000_000;
+    const FEE_BPS: u16 = 30;
+
+    fn sqrt_price(tick: i32) -> u128 {
+        sqrt_price_from_tick(tick).unwrap()
+    }
+
+    #[test]
+    fn amount_deltas_match_the_range_formulas() {
+        // Between sqrt prices 1 and 1.0001^10: L * (1 - 1.0001^-10) of A
+        // and L * (1.0001^10 - 1) of B
+        let (lower, upper) = (sqrt_price(0), sqrt_price(20));
+        let amount_a = amount_a_delta(lower, upper, LIQUIDITY, false).unwrap();
+        let amount_b = amount_b_delta(lower, upper, LIQUIDITY, false).unwrap();
+        assert!(amount_a.abs_diff(999_450_219) <= 1, "{}", amount_a);
+        assert!(amount_b.abs_diff(1_000_450_120) <= 1, "{}", amount_b);
+
+        for &(lower, upper) in &[(-100, 100), (0, 1), (-400_000, 400_000)] {
+            let (lower, upper) = (sqrt_price(lower), sqrt_price(upper));
+            let a_down = amount_a_delta(lower, upper, LIQUIDITY, false).unwrap();
+            let a_up = amount_a_delta(lower, upper, LIQUIDITY, true).unwrap();
+            let b_down = amount_b_delta(lower, upper, LIQUIDITY, false).unwrap();
+            let b_up = amount_b_delta(lower, upper, LIQUIDITY, true).unwrap();
+
+            // A rounds twice, the first time before scaling by 1 / lower
+            assert!(a_up >= a_down && a_up - a_down <= 2 + Q64 / lower);
+            assert!(b_up >= b_down && b_up - b_down <= 1);
+        }
+    }
+
+    #[test]
+    fn positions_hold_one_token_outside_their_range() {
+        let (lower, upper) = (sqrt_price(-100), sqrt_price(100));
+
+        let below = position_amounts(sqrt_price(-200), lower, upper, LIQUIDITY, true).unwrap();
+        assert!(below.0 > 0);
+        assert_eq!(below.1, 0);
+
+        let above = position_amounts(upper, lower, upper, LIQUIDITY, true).unwrap();
+        assert_eq!(above.0, 0);
+        assert!(above.1 > 0);
+
+        let inside = position_amounts(Q64, lower, upper, LIQUIDITY, true).unwrap();
+        assert!(inside.0 > 0 && inside.1 > 0);
+        assert!(inside.0 < below.0 && inside.1 < above.1);
+    }
+
+    #[test]
+    fn next_price_never_undercharges_the_input() {
+        let current = sqrt_price(0);
+
+        for &amount_in in &[1u128, 1_000, 1_000_000, 1_000_000_000] {
+            // Selling A lowers the price, rounded up so it never moves further
+            // than the input pays for
+            let next = next_sqrt_price_from_input(current, LIQUIDITY, amount_in, true).unwrap();
+            assert!(next <= current);
+            assert!(amount_a_delta(next, current, LIQUIDITY, false).unwrap() <= amount_in);
+
+            // Selling B raises it, rounded down
+            let next = next_sqrt_price_from_input(current, LIQUIDITY, amount_in, false).unwrap();
+            assert!(next >= current);
+            assert!(amount_b_delta(current, next, LIQUIDITY, false).unwrap() <= amount_in);
+        }
+    }
+
+    #[test]
+    fn swap_step_exactly_reaches_its_target() {
+        for a_to_b in [true, false] {
+            let current = sqrt_price(0);
+            let target = sqrt_price(if a_to_b { -60 } else { 60 });
+            let amount_to_target = if a_to_b {
+                amount_a_delta(target, current, LIQUIDITY, true).unwrap()
+            } else {
+                amount_b_delta(current, target, LIQUIDITY, true).unwrap()
+            } as u64;
+
+            // Without a fee the input to the target is just enough
+            let step =
+                compute_swap_step(current, target, LIQUIDITY, amount_to_target, 0, a_to_b).unwrap();
+            assert_eq!(step.sqrt_price_next, target);
+            assert_eq!(step.amount_in, amount_to_target);
+            assert_eq!(step.fee, 0);
+
+            let short =
+                compute_swap_step(current, target, LIQUIDITY, amount_to_target - 1, 0, a_to_b)
+                    .unwrap();
+            assert_ne!(short.sqrt_price_next, target);
+            assert!(short.amount_out <= step.amount_out);
+
+            // With a fee the input grossed up by it is too
+            let amount_remaining = mul_div_ceil(
+                amount_to_target as u128,
+                BPS_DENOMINATOR,
+                BPS_DENOMINATOR - FEE_BPS as u128,
+            )
+            .unwrap() as u64;
+            let step = compute_swap_step(
+                current,
+                target,
+                LIQUIDITY,
+                amount_remaining,
+                FEE_BPS,
+                a_to_b,
+            )
+            .unwrap();
+            assert_eq!(step.sqrt_price_next, target);
+            assert_eq!(step.amount_in, amount_to_target);
+            assert!(step.amount_in + step.fee <= amount_remaining);
+        }
+    }
+
+    #[test]
+    fn partial_swap_steps_use_up_the_whole_input() {
+        let current = sqrt_price(0);
+        let target = sqrt_price(-6_000);
+
+        for &amount_remaining in &[1u64, 997, 1_000_000, 50_000_000] {
+            let step =
+                compute_swap_step(current, target, LIQUIDITY, amount_remaining, FEE_BPS, true)
+                    .unwrap();
+
+            assert!(step.sqrt_price_next > target && step.sqrt_price_next <= current);
+            assert_eq!(step.amount_in + step.fee, amount_remaining);
+            // The pool pays no more than the input is worth at the start price
+            assert!(step.amount_out <= step.amount_in);
+        }
+    }
+
+    #[test]
+    fn fee_growth_inside_survives_wrapping() {
+        // 100 units of growth accrued on each side of a position that has
+        // seen 50 itself, with the global value wrapped past zero
+        let global = 249u128.wrapping_sub(1_000);
+        let outside_lower = 99u128.wrapping_sub(1_000);
+        let outside_upper = 100;
+        assert_eq!(
+            fee_growth_inside(0, -10, 10, global, outside_lower, outside_upper),
+            50
+        );
+
+        let growth = fee_growth_delta(1_000, LIQUIDITY);
+        assert_eq!(fees_earned(growth, 0, LIQUIDITY), Ok(999));
+        assert_eq!(fees_earned(growth.wrapping_add(7), 7, LIQUIDITY), Ok(999));
+        assert_eq!(fee_growth_delta(1_000, 0), 0);
+    }
+}
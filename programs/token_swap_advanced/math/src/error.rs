@@ -0,0 +1,16 @@
+// Errors of the pool math. The program maps them to its own error codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmmError {
+    // An intermediate value does not fit its integer type
+    Overflow,
+    // One side of the pool has no liquidity
+    EmptyReserves,
+    // The pool cannot pay out the requested amount
+    InsufficientReserves,
+    // More LP tokens were requested than exist
+    InsufficientLpSupply,
+    // A tick or sqrt price is outside the supported range
+    InvalidTick,
+}
+
+pub type AmmResult<T> = core::result::Result<T, AmmError>;
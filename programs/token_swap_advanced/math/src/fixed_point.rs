@@ -0,0 +1,249 @@
+// Unsigned 18-decimal fixed-point arithmetic with `ln`, `exp` and `pow`, used
+// by the weighted curve. `pow` is accurate to about 1e-16; `pow_up` pads that
+// error so callers that need to round in the pool's favour can rely on it.
+use crate::amm::{mul_div, mul_div_ceil, AmmError, AmmResult};
+
+pub const ONE: u128 = 1_000_000_000_000_000_000;
+// ln(2) * 1e18
+const LN_2: i128 = 693_147_180_559_945_309;
+// Relative error bound of `pow`, 1e-14
+const MAX_POW_RELATIVE_ERROR: u128 = 10_000;
+
+pub fn mul_down(a: u128, b: u128) -> AmmResult<u128> {
+    mul_div(a, b, ONE)
+}
+
+pub fn mul_up(a: u128, b: u128) -> AmmResult<u128> {
+    mul_div_ceil(a, b, ONE)
+}
+
+pub fn div_down(a: u128, b: u128) -> AmmResult<u128> {
+    mul_div(a, ONE, b)
+}
+
+pub fn div_up(a: u128, b: u128) -> AmmResult<u128> {
+    mul_div_ceil(a, ONE, b)
+}
+
+// Natural logarithm of the fixed-point `x`, itself in fixed point
+pub fn ln(x: u128) -> AmmResult<i128> {
+    if x == 0 {
+        return Err(AmmError::Overflow);
+    }
+    if x < ONE {
+        // ln(x) = -ln(1 / x)
+        return Ok(-ln(ONE * ONE / x)?);
+    }
+
+    // x = 2^k * m with m in [1, 2)
+    let k = 127 - (x / ONE).leading_zeros();
+    let m = x >> k;
+
+    // ln(m) = 2 * atanh(z) = 2 * (z + z^3 / 3 + z^5 / 5 + ...), z <= 1/3
+    let z = mul_div(m - ONE, ONE, m + ONE)?;
+    let z_squared = z * z / ONE;
+
+    let mut term = z;
+    let mut sum = z;
+    let mut n = 1;
+    loop {
+        term = term * z_squared / ONE;
+        if term == 0 {
+            break;
+        }
+        n += 2;
+        sum += term / n;
+    }
+
+    Ok(k as i128 * LN_2 + 2 * sum as i128)
+}
+
+// e to the power of the fixed-point `x`, in fixed point
+pub fn exp(x: i128) -> AmmResult<u128> {
+    if x < 0 {
+        // e^-x = 1 / e^x, which rounds to zero once e^x no longer fits
+        return Ok(exp(-x).map_or(0, |value| ONE * ONE / value));
+    }
+
+    // x = k * ln(2) + r with r in [0, ln(2))
+    let k = x / LN_2;
+    if k >= 128 {
+        return Err(AmmError::Overflow);
+    }
+    let k = k as u32;
+    let r = (x % LN_2) as u128;
+
+    // e^r = 1 + r + r^2 / 2! + ..., below 2 since r < ln(2)
+    let mut term = ONE;
+    let mut sum = ONE;
+    let mut n = 1;
+    loop {
+        term = term * r / ONE / n;
+        if term == 0 {
+            break;
+        }
+        sum += term;
+        n += 1;
+    }
+
+    if k >= sum.leading_zeros() {
+        return Err(AmmError::Overflow);
+    }
+
+    Ok(sum << k)
+}
+
+// `value` times the fixed-point `factor`, with the integer and fractional
+// parts of the factor applied separately so large exponents do not overflow
+fn mul_signed(value: i128, factor: u128) -> AmmResult<i128> {
+    let whole = (factor / ONE) as i128;
+    let fraction = (factor % ONE) as i128;
+
+    value
+        .checked_mul(whole)
+        .and_then(|product| product.checked_add(value * fraction / ONE as i128))
+        .ok_or(AmmError::Overflow)
+}
+
+// base ^ exponent, both in fixed point
+pub fn pow(base: u128, exponent: u128) -> AmmResult<u128> {
+    if exponent == 0 {
+        return Ok(ONE);
+    }
+    if base == 0 {
+        return Ok(0);
+    }
+
+    exp(mul_signed(ln(base)?, exponent)?)
+}
+
+// `pow` rounded up by its maximum error
+pub fn pow_up(base: u128, exponent: u128) -> AmmResult<u128> {
+    let raw = pow(base, exponent)?;
+
+    Ok(raw + mul_up(raw, MAX_POW_RELATIVE_ERROR)? + 1)
+}
+
+// e ^ (sum of weight * ln(value)) for plain integer values and fixed-point
+// weights, i.e. the weighted geometric mean when the weights add up to one
+pub fn weighted_product(values: &[(u64, u128)]) -> AmmResult<u128> {
+    let mut exponent: i128 = 0;
+    for (value, weight) in values {
+        if *value == 0 {
+            return Err(AmmError::EmptyReserves);
+        }
+
+        let ln_value = ln(*value as u128 * ONE)?;
+        exponent = exponent
+            .checked_add(mul_signed(ln_value, *weight)?)
+            .ok_or(AmmError::Overflow)?;
+    }
+
+    Ok(exp(exponent)? / ONE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // e * 1e18
+    const E: u128 = 2_718_281_828_459_045_235;
+
+    // Within 1e-16 of `expected`, relative to it or to one if it is smaller
+    fn assert_close(actual: u128, expected: u128) {
+        let tolerance = expected.max(ONE) / 10_u128.pow(16);
+        assert!(
+            actual.abs_diff(expected) <= tolerance,
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn ln_matches_known_values() {
+        assert_eq!(ln(ONE).unwrap(), 0);
+        assert_close(ln(2 * ONE).unwrap() as u128, LN_2 as u128);
+        assert_close(ln(E).unwrap() as u128, ONE);
+        assert_close((-ln(ONE / 2).unwrap()) as u128, LN_2 as u128);
+        // ln(1e6) = 13.815510557964274104
+        assert_close(
+            ln(1_000_000 * ONE).unwrap() as u128,
+            13_815_510_557_964_274_104,
+        );
+        assert_eq!(ln(0), Err(AmmError::Overflow));
+    }
+
+    #[test]
+    fn exp_matches_known_values() {
+        assert_eq!(exp(0).unwrap(), ONE);
+        assert_close(exp(ONE as i128).unwrap(), E);
+        assert_close(exp(LN_2).unwrap(), 2 * ONE);
+        // e^-1 = 0.367879441171442321
+        assert_close(exp(-(ONE as i128)).unwrap(), 367_879_441_171_442_321);
+        // e^20 = 485165195.409790277969106830
+        assert_close(
+            exp(20 * ONE as i128).unwrap(),
+            485_165_195_409_790_277_969_106_830,
+        );
+        assert_eq!(exp(100 * ONE as i128), Err(AmmError::Overflow));
+        assert_eq!(exp(-100 * ONE as i128).unwrap(), 0);
+    }
+
+    #[test]
+    fn pow_matches_known_values() {
+        for &(base, exponent, expected) in &[
+            (4 * ONE, ONE / 2, 2 * ONE),
+            (2 * ONE, 10 * ONE, 1_024 * ONE),
+            (ONE / 4, ONE / 2, ONE / 2),
+            (9 * ONE, 3 * ONE / 2, 27 * ONE),
+            // 0.7^4 = 0.2401
+            (7 * ONE / 10, 4 * ONE, 2_401 * ONE / 10_000),
+            (123 * ONE, ONE, 123 * ONE),
+        ] {
+            assert_close(pow(base, exponent).unwrap(), expected);
+            assert!(pow_up(base, exponent).unwrap() >= expected);
+        }
+
+        assert_eq!(pow(5 * ONE, 0).unwrap(), ONE);
+        assert_eq!(pow(0, ONE).unwrap(), 0);
+    }
+
+    #[test]
+    fn pow_up_is_never_below_pow() {
+        let bases = [
+            1,
+            ONE / 1_000,
+            ONE / 2,
+            ONE - 1,
+            ONE,
+            ONE + 1,
+            3 * ONE,
+            1_000 * ONE,
+        ];
+        let exponents = [1, ONE / 100, ONE / 3, ONE, 4 * ONE, 99 * ONE];
+
+        for &base in &bases {
+            for &exponent in &exponents {
+                if let Ok(down) = pow(base, exponent) {
+                    let up = pow_up(base, exponent).unwrap();
+                    assert!(up > down, "pow_up({}, {})", base, exponent);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_product_of_equal_weights_is_the_geometric_mean() {
+        let half = ONE / 2;
+
+        // Truncating the fixed-point result can lose a unit
+        let mean = weighted_product(&[(1_000_000, half), (4_000_000, half)]).unwrap();
+        assert!(mean.abs_diff(2_000_000) <= 1);
+        assert_eq!(
+            weighted_product(&[(0, half), (4_000_000, half)]),
+            Err(AmmError::EmptyReserves)
+        );
+    }
+}
@@ -0,0 +1,184 @@
+// 256-bit intermediates for a * b / c on u128 operands. Q64.64 prices and
+// liquidity overflow a u128 product, so every curve that works with them
+// divides through these instead of `amm::mul_div`.
+use crate::error::{AmmError, AmmResult};
+
+pub const Q64: u128 = 1 << 64;
+
+const LOW_64: u128 = u64::MAX as u128;
+
+// 256-bit product of `a` and `b` as (high, low) halves
+pub fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    let (a_high, a_low) = (a >> 64, a & LOW_64);
+    let (b_high, b_low) = (b >> 64, b & LOW_64);
+
+    let low_low = a_low * b_low;
+    let low_high = a_low * b_high;
+    let high_low = a_high * b_low;
+    let high_high = a_high * b_high;
+
+    let middle = (low_low >> 64) + (low_high & LOW_64) + (high_low & LOW_64);
+
+    let low = (low_low & LOW_64) | (middle << 64);
+    let high = high_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+
+    (high, low)
+}
+
+// a * b / c with a 256-bit intermediate, returns (quotient, remainder)
+pub fn wide_div_rem(a: u128, b: u128, c: u128) -> AmmResult<(u128, u128)> {
+    if c == 0 {
+        return Err(AmmError::Overflow);
+    }
+
+    let (high, low) = full_mul(a, b);
+    if high == 0 {
+        return Ok((low / c, low % c));
+    }
+    // The quotient would not fit in 128 bits
+    if high >= c {
+        return Err(AmmError::Overflow);
+    }
+
+    // Shift the low half in bit by bit, `high < c` keeps the quotient in range
+    let mut remainder = high;
+    let mut quotient: u128 = 0;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= c {
+            remainder = remainder.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+
+    Ok((quotient, remainder))
+}
+
+// a * b / c rounded down, without overflowing on the product
+pub fn mul_div_wide(a: u128, b: u128, c: u128) -> AmmResult<u128> {
+    Ok(wide_div_rem(a, b, c)?.0)
+}
+
+// a * b / c rounded up, without overflowing on the product
+pub fn mul_div_wide_ceil(a: u128, b: u128, c: u128) -> AmmResult<u128> {
+    let (quotient, remainder) = wide_div_rem(a, b, c)?;
+    if remainder == 0 {
+        Ok(quotient)
+    } else {
+        quotient.checked_add(1).ok_or(AmmError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic operands of every size from one bit up to 128
+    fn operands() -> impl Iterator<Item = u128> {
+        let mut state: u128 = 0x2545_f491_4f6c_dd1d;
+        (0..200).map(move |index| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state >> (index % 128)
+        })
+    }
+
+    // 256-bit q * c + r as (high, low)
+    fn recombine(quotient: u128, divisor: u128, remainder: u128) -> (u128, u128) {
+        let (high, low) = full_mul(quotient, divisor);
+        let (low, carry) = low.overflowing_add(remainder);
+        (high + carry as u128, low)
+    }
+
+    #[test]
+    fn full_mul_keeps_the_high_half() {
+        assert_eq!(full_mul(0, u128::MAX), (0, 0));
+        assert_eq!(full_mul(3, 5), (0, 15));
+        assert_eq!(full_mul(Q64, Q64), (1, 0));
+        assert_eq!(full_mul(u128::MAX, 2), (1, u128::MAX - 1));
+        // (2^128 - 1)^2 = 2^256 - 2^129 + 1
+        assert_eq!(full_mul(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+
+        for a in operands() {
+            for b in operands().take(20) {
+                if let Some(product) = a.checked_mul(b) {
+                    assert_eq!(full_mul(a, b), (0, product));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wide_div_rem_matches_u128_division() {
+        for a in operands() {
+            for b in operands().take(20) {
+                let Some(product) = a.checked_mul(b) else {
+                    continue;
+                };
+                for c in operands().take(20).filter(|&c| c != 0) {
+                    assert_eq!(wide_div_rem(a, b, c), Ok((product / c, product % c)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wide_div_rem_divides_256_bit_products() {
+        assert_eq!(
+            wide_div_rem(u128::MAX, u128::MAX, u128::MAX),
+            Ok((u128::MAX, 0))
+        );
+        assert_eq!(mul_div_wide(1 << 100, 1 << 100, 1 << 80), Ok(1 << 120));
+        // (2^128 - 2^64) * (2^64 + 1) = (2^128 - 1) * 2^64
+        assert_eq!(
+            wide_div_rem(u128::MAX, Q64, Q64 + 1),
+            Ok((u128::MAX - Q64 + 1, 0))
+        );
+
+        for a in operands() {
+            for b in operands().take(20) {
+                for c in operands().take(20).filter(|&c| c != 0) {
+                    match wide_div_rem(a, b, c) {
+                        Ok((quotient, remainder)) => {
+                            assert!(remainder < c);
+                            assert_eq!(recombine(quotient, c, remainder), full_mul(a, b));
+                        }
+                        // Only when the quotient needs more than 128 bits
+                        Err(_) => assert!(full_mul(a, b).0 >= c),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wide_division_rejects_zero_and_oversized_quotients() {
+        assert_eq!(wide_div_rem(1, 1, 0), Err(AmmError::Overflow));
+        assert_eq!(mul_div_wide(u128::MAX, 2, 1), Err(AmmError::Overflow));
+        assert_eq!(
+            mul_div_wide_ceil(u128::MAX, u128::MAX, u128::MAX - 1),
+            Err(AmmError::Overflow)
+        );
+    }
+
+    #[test]
+    fn ceil_rounds_up_only_with_a_remainder() {
+        assert_eq!(mul_div_wide_ceil(10, 10, 5), Ok(20));
+        assert_eq!(mul_div_wide_ceil(10, 10, 3), Ok(34));
+        assert_eq!(mul_div_wide(10, 10, 3), Ok(33));
+
+        for a in operands() {
+            for b in operands().take(20) {
+                for c in operands().take(20).filter(|&c| c != 0) {
+                    if let Ok((quotient, remainder)) = wide_div_rem(a, b, c) {
+                        let expected = quotient.checked_add((remainder != 0) as u128);
+                        assert_eq!(mul_div_wide_ceil(a, b, c).ok(), expected);
+                    }
+                }
+            }
+        }
+    }
+}
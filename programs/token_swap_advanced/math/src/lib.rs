@@ -0,0 +1,15 @@
+// Pool math shared by the on-chain program and off-chain clients. It only
+// uses `core` and integer arithmetic, so quotes computed anywhere are
+// bit-exact with the instruction handlers and clients do not need Anchor.
+#![cfg_attr(not(test), no_std)]
+
+pub mod amm;
+pub mod clmm;
+pub mod error;
+pub mod fixed_point;
+pub mod full_math;
+pub mod oracle;
+pub mod quote;
+pub mod stable_swap;
+pub mod tick_math;
+pub mod weighted;
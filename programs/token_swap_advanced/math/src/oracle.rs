@@ -0,0 +1,164 @@
+// Price accumulators for time-weighted average prices, as in Uniswap v2.
+// A pool adds its Q64.64 marginal price times the seconds it was in effect
+// to a running sum; the average between two readings of the sum is their
+// difference over the time between them. Sums wrap on overflow, only
+// differences are meaningful.
+use crate::amm::{AmmError, AmmResult};
+use crate::full_math::{mul_div_wide, Q64};
+
+// Running sums of a pool at one point in time: the price of A in B, the
+// price of B in A and the liquidity, each times the seconds it held
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cumulatives {
+    pub price_a: u128,
+    pub price_b: u128,
+    pub liquidity: u128,
+}
+
+// Sums after `elapsed` more seconds at `price_a`, the Q64.64 price of A in
+// B, and `liquidity`
+pub fn accumulate(
+    cumulatives: Cumulatives,
+    price_a: u128,
+    liquidity: u128,
+    elapsed: u64,
+) -> Cumulatives {
+    // A price too small to invert leaves the B sum where it was
+    let price_b = mul_div_wide(Q64, Q64, price_a).unwrap_or(0);
+
+    Cumulatives {
+        price_a: cumulatives
+            .price_a
+            .wrapping_add(price_a.wrapping_mul(elapsed as u128)),
+        price_b: cumulatives
+            .price_b
+            .wrapping_add(price_b.wrapping_mul(elapsed as u128)),
+        liquidity: cumulatives
+            .liquidity
+            .wrapping_add(liquidity.wrapping_mul(elapsed as u128)),
+    }
+}
+
+// Sums `elapsed` seconds into the `duration` seconds between the readings
+// `start` and `end`, assuming every rate held steady in between
+pub fn interpolate(
+    start: Cumulatives,
+    end: Cumulatives,
+    elapsed: u64,
+    duration: u64,
+) -> AmmResult<Cumulatives> {
+    if duration == 0 || elapsed > duration {
+        return Err(AmmError::Overflow);
+    }
+
+    let between = |start: u128, end: u128| -> AmmResult<u128> {
+        let delta = mul_div_wide(end.wrapping_sub(start), elapsed as u128, duration as u128)?;
+        Ok(start.wrapping_add(delta))
+    };
+
+    Ok(Cumulatives {
+        price_a: between(start.price_a, end.price_a)?,
+        price_b: between(start.price_b, end.price_b)?,
+        liquidity: between(start.liquidity, end.liquidity)?,
+    })
+}
+
+// Average Q64.64 price between two readings of a cumulative price taken
+// `elapsed` seconds apart
+pub fn twap(cumulative_start: u128, cumulative_end: u128, elapsed: u64) -> AmmResult<u128> {
+    cumulative_end
+        .wrapping_sub(cumulative_start)
+        .checked_div(elapsed as u128)
+        .ok_or(AmmError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulate_adds_each_rate_times_the_elapsed_time() {
+        let start = Cumulatives {
+            price_a: 7,
+            price_b: 11,
+            liquidity: 13,
+        };
+
+        // A price of 4 makes the inverse price a quarter
+        let sums = accumulate(start, 4 * Q64, 1_000, 30);
+        assert_eq!(
+            sums,
+            Cumulatives {
+                price_a: 7 + 120 * Q64,
+                price_b: 11 + 30 * Q64 / 4,
+                liquidity: 13 + 30_000,
+            }
+        );
+
+        assert_eq!(accumulate(start, 4 * Q64, 1_000, 0), start);
+    }
+
+    #[test]
+    fn accumulate_wraps_and_skips_prices_it_cannot_invert() {
+        let start = Cumulatives {
+            price_a: u128::MAX - 9,
+            price_b: 5,
+            liquidity: u128::MAX,
+        };
+
+        // The inverse of a price of one unit does not fit in a u128
+        let sums = accumulate(start, 1, 1, 20);
+        assert_eq!(sums.price_a, 10);
+        assert_eq!(sums.price_b, 5);
+        assert_eq!(sums.liquidity, 19);
+    }
+
+    #[test]
+    fn interpolate_splits_the_change_in_proportion() {
+        let start = Cumulatives {
+            price_a: 1_000,
+            price_b: u128::MAX - 99,
+            liquidity: 0,
+        };
+        let end = Cumulatives {
+            price_a: 2_000,
+            // Wrapped past zero: 300 more than the start
+            price_b: 200,
+            liquidity: 90,
+        };
+
+        assert_eq!(
+            interpolate(start, end, 25, 100),
+            Ok(Cumulatives {
+                price_a: 1_250,
+                price_b: u128::MAX - 24,
+                liquidity: 22,
+            })
+        );
+        assert_eq!(interpolate(start, end, 0, 100), Ok(start));
+        assert_eq!(interpolate(start, end, 100, 100), Ok(end));
+    }
+
+    #[test]
+    fn interpolate_rejects_points_outside_the_readings() {
+        let start = Cumulatives::default();
+        let end = Cumulatives {
+            price_a: 10,
+            price_b: 10,
+            liquidity: 10,
+        };
+
+        assert_eq!(interpolate(start, end, 0, 0), Err(AmmError::Overflow));
+        assert_eq!(interpolate(start, end, 11, 10), Err(AmmError::Overflow));
+    }
+
+    #[test]
+    fn twap_averages_across_wrapped_sums() {
+        let price = 3 * Q64 / 2;
+        let start = u128::MAX - price;
+        let end = start.wrapping_add(price * 60);
+
+        assert_eq!(twap(start, end, 60), Ok(price));
+        assert_eq!(twap(start, end, 0), Err(AmmError::Overflow));
+    }
+}
@@ -0,0 +1,331 @@
+// Quotes for swaps, deposits and withdrawals computed from the raw pool
+// state.
+use crate::amm::{
+    calculate_protocol_fee, mul_div, AmmResult, ConstantProductCurve, SwapCurve, BPS_DENOMINATOR,
+    MINIMUM_LIQUIDITY,
+};
+use crate::stable_swap::StableCurve;
+use crate::weighted::WeightedCurve;
+
+// Curve a pool prices against, with its parameters resolved for the current
+// time (e.g. a ramping amplification) and its weights
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    ConstantProduct(ConstantProductCurve),
+    Stable(StableCurve),
+    Weighted(WeightedCurve),
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Curve::ConstantProduct(ConstantProductCurve)
+    }
+}
+
+impl Curve {
+    pub fn swap_curve(&self) -> &dyn SwapCurve {
+        match self {
+            Curve::ConstantProduct(curve) => curve,
+            Curve::Stable(curve) => curve,
+            Curve::Weighted(curve) => curve,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolState {
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+    pub lp_supply: u64,
+    pub fee_bps: u16,
+    pub protocol_fee_bps: u16,
+    pub curve: Curve,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    // Total swap fee, including the protocol share
+    pub fee: u64,
+    pub protocol_fee: u64,
+    // Execution price below the spot price, fee excluded, in basis points
+    pub price_impact_bps: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DepositQuote {
+    pub amount_a: u64,
+    pub amount_b: u64,
+    // LP tokens minted to the depositor
+    pub lp_tokens: u64,
+    // LP tokens added to the supply that nobody receives, only on the
+    // first deposit
+    pub lp_tokens_locked: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WithdrawQuote {
+    pub amount_a: u64,
+    pub amount_b: u64,
+}
+
+pub fn quote_swap_exact_in(
+    state: &PoolState,
+    amount_in: u64,
+    a_to_b: bool,
+) -> AmmResult<SwapQuote> {
+    let (amount_out, fee) = state.curve.swap_curve().swap_exact_in(
+        amount_in,
+        state.reserve_a,
+        state.reserve_b,
+        a_to_b,
+        state.fee_bps,
+    )?;
+
+    build_swap_quote(state, amount_in, amount_out, fee, a_to_b)
+}
+
+pub fn quote_swap_exact_out(
+    state: &PoolState,
+    amount_out: u64,
+    a_to_b: bool,
+) -> AmmResult<SwapQuote> {
+    let (amount_in, fee) = state.curve.swap_curve().swap_exact_out(
+        amount_out,
+        state.reserve_a,
+        state.reserve_b,
+        a_to_b,
+        state.fee_bps,
+    )?;
+
+    build_swap_quote(state, amount_in, amount_out, fee, a_to_b)
+}
+
+pub fn quote_deposit(
+    state: &PoolState,
+    max_amount_a: u64,
+    max_amount_b: u64,
+) -> AmmResult<DepositQuote> {
+    let (amount_a, amount_b, lp_tokens) = state.curve.swap_curve().deposit(
+        max_amount_a,
+        max_amount_b,
+        state.reserve_a,
+        state.reserve_b,
+        state.lp_supply,
+    )?;
+
+    // The first deposit leaves MINIMUM_LIQUIDITY of what it mints in the
+    // pool, a deposit too small to cover it mints nothing
+    let lp_tokens_locked = if state.lp_supply == 0 {
+        MINIMUM_LIQUIDITY
+    } else {
+        0
+    };
+
+    Ok(DepositQuote {
+        amount_a,
+        amount_b,
+        lp_tokens: lp_tokens.saturating_sub(lp_tokens_locked),
+        lp_tokens_locked,
+    })
+}
+
+pub fn quote_withdraw(state: &PoolState, lp_amount: u64) -> AmmResult<WithdrawQuote> {
+    let (amount_a, amount_b) = state.curve.swap_curve().withdraw(
+        lp_amount,
+        state.reserve_a,
+        state.reserve_b,
+        state.lp_supply,
+    )?;
+
+    Ok(WithdrawQuote { amount_a, amount_b })
+}
+
+fn build_swap_quote(
+    state: &PoolState,
+    amount_in: u64,
+    amount_out: u64,
+    fee: u64,
+    a_to_b: bool,
+) -> AmmResult<SwapQuote> {
+    let protocol_fee = calculate_protocol_fee(fee, state.protocol_fee_bps)?;
+
+    // Output the same input after fee would get at the spot price
+    let spot_amount_out = state.curve.swap_curve().spot_price(
+        amount_in - fee,
+        state.reserve_a,
+        state.reserve_b,
+        a_to_b,
+    )?;
+
+    let price_impact_bps = if spot_amount_out == 0 {
+        0
+    } else {
+        mul_div(
+            spot_amount_out.saturating_sub(amount_out as u128),
+            BPS_DENOMINATOR,
+            spot_amount_out,
+        )? as u64
+    };
+
+    Ok(SwapQuote {
+        amount_in,
+        amount_out,
+        fee,
+        protocol_fee,
+        price_impact_bps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constant_product_pool(reserve_a: u64, reserve_b: u64) -> PoolState {
+        PoolState {
+            reserve_a,
+            reserve_b,
+            lp_supply: 1_000_000,
+            fee_bps: 30,
+            protocol_fee_bps: 1_667,
+            curve: Curve::default(),
+        }
+    }
+
+    // What the swap handlers computed before the quote module existed: the
+    // fee comes off the input, the rest trades against x * y = k
+    fn reference_amount_out(
+        amount_in: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+        fee_bps: u16,
+    ) -> u64 {
+        let fee = amount_in as u128 * fee_bps as u128 / 10_000;
+        let amount_in_after_fee = amount_in as u128 - fee;
+        (amount_in_after_fee * reserve_out as u128 / (reserve_in as u128 + amount_in_after_fee))
+            as u64
+    }
+
+    // Reserves after `record_swap` books a quote, as (reserve_in, reserve_out)
+    fn booked_reserves(reserve_in: u64, reserve_out: u64, quote: &SwapQuote) -> (u64, u64) {
+        (
+            reserve_in + quote.amount_in - quote.protocol_fee,
+            reserve_out - quote.amount_out,
+        )
+    }
+
+    #[test]
+    fn exact_in_matches_the_constant_product_formula() {
+        for &(reserve_a, reserve_b, amount_in) in &[
+            (1_000_000u64, 1_000_000u64, 1_000u64),
+            (5_000_000_000, 20_000_000_000, 10_000_000_000),
+            (123_456_789, 987_654_321, 55_555),
+            (u64::MAX / 4, u64::MAX / 8, 1 << 40),
+        ] {
+            let state = constant_product_pool(reserve_a, reserve_b);
+
+            for a_to_b in [true, false] {
+                let (reserve_in, reserve_out) = if a_to_b {
+                    (reserve_a, reserve_b)
+                } else {
+                    (reserve_b, reserve_a)
+                };
+                let quote = quote_swap_exact_in(&state, amount_in, a_to_b).unwrap();
+
+                assert_eq!(quote.amount_in, amount_in);
+                assert_eq!(
+                    quote.amount_out,
+                    reference_amount_out(amount_in, reserve_in, reserve_out, state.fee_bps)
+                );
+                assert_eq!(quote.fee, amount_in * 30 / 10_000);
+                assert_eq!(quote.protocol_fee, quote.fee * 1_667 / 10_000);
+
+                // Booking the quote never shrinks k
+                let (new_in, new_out) = booked_reserves(reserve_in, reserve_out, &quote);
+                assert!(
+                    new_in as u128 * new_out as u128 >= reserve_in as u128 * reserve_out as u128
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn exact_out_asks_for_the_smallest_sufficient_input() {
+        let state = constant_product_pool(5_000_000_000, 20_000_000_000);
+
+        for &amount_out in &[1u64, 1_000, 1_000_000_000, 4_000_000_000] {
+            for a_to_b in [true, false] {
+                let quote = quote_swap_exact_out(&state, amount_out, a_to_b).unwrap();
+                assert_eq!(quote.amount_out, amount_out);
+
+                // The exact-input handler pays at least the requested output
+                // for that input. Both roundings go up, so the input is at
+                // most a unit above the smallest one that would do.
+                let paid = quote_swap_exact_in(&state, quote.amount_in, a_to_b).unwrap();
+                assert!(paid.amount_out >= amount_out);
+                assert_eq!(paid.fee, quote.fee);
+                assert_eq!(paid.protocol_fee, quote.protocol_fee);
+
+                let short = quote_swap_exact_in(&state, quote.amount_in - 2, a_to_b).unwrap();
+                assert!(short.amount_out < amount_out);
+            }
+        }
+    }
+
+    #[test]
+    fn exact_out_cannot_drain_the_pool() {
+        let state = constant_product_pool(1_000_000, 1_000_000);
+
+        assert_eq!(
+            quote_swap_exact_out(&state, 1_000_000, true),
+            Err(crate::amm::AmmError::InsufficientReserves)
+        );
+    }
+
+    #[test]
+    fn empty_pools_cannot_be_quoted() {
+        let state = constant_product_pool(0, 0);
+
+        assert_eq!(
+            quote_swap_exact_in(&state, 1_000, true),
+            Err(crate::amm::AmmError::EmptyReserves)
+        );
+    }
+
+    #[test]
+    fn first_deposit_locks_the_minimum_liquidity() {
+        let empty = PoolState {
+            lp_supply: 0,
+            ..constant_product_pool(0, 0)
+        };
+
+        let quote = quote_deposit(&empty, 4_000_000, 1_000_000).unwrap();
+        assert_eq!(quote.lp_tokens, 2_000_000 - MINIMUM_LIQUIDITY);
+        assert_eq!(quote.lp_tokens_locked, MINIMUM_LIQUIDITY);
+
+        // Burning everything the depositor got leaves the locked share behind
+        let lp_supply = quote.lp_tokens + quote.lp_tokens_locked;
+        let (amount_a, amount_b) = crate::amm::calculate_withdraw_amounts(
+            quote.lp_tokens,
+            4_000_000,
+            1_000_000,
+            lp_supply,
+        )
+        .unwrap();
+        assert!(amount_a < 4_000_000 && amount_b < 1_000_000);
+
+        // A deposit that cannot cover the locked part mints nothing
+        let dust = quote_deposit(&empty, 1_000, 1_000).unwrap();
+        assert_eq!(dust.lp_tokens, 0);
+
+        // Only the first deposit locks anything
+        let state = PoolState {
+            lp_supply,
+            ..constant_product_pool(4_000_000, 1_000_000)
+        };
+        let quote = quote_deposit(&state, 400_000, 100_000).unwrap();
+        assert_eq!(quote.lp_tokens, lp_supply / 10);
+        assert_eq!(quote.lp_tokens_locked, 0);
+    }
+}
@@ -0,0 +1,594 @@
+// StableSwap invariant for two tokens, as described in the Curve whitepaper:
+//
+//     A * n^n * (x + y) + D = A * n^n * D + D^(n + 1) / (n^n * x * y),  n = 2
+//
+// A high amplification `A` keeps the curve close to x + y = D around the
+// peg, a low one moves it towards x * y = k. Products of D with Ann * D go
+// through 256-bit intermediates, so any u64 reserves work up to the highest
+// amplification; values that do not fit return `AmmError::Overflow` instead
+// of wrapping.
+use crate::amm::{
+    calculate_deposit_amounts, calculate_fee, directional_reserves, mul_div, mul_div_ceil, to_u64,
+    AmmError, AmmResult, SwapCurve, BPS_DENOMINATOR,
+};
+use crate::full_math::{mul_div_wide, wide_div_rem, Q64};
+
+const N_COINS: u128 = 2;
+// Newton's method converges in a handful of steps, this only guards the loop
+const MAX_ITERATIONS: usize = 255;
+
+// A * n^n, the amplification has to be at least 1
+fn amp_times_coins(amp: u64) -> AmmResult<u128> {
+    if amp == 0 {
+        return Err(AmmError::Overflow);
+    }
+
+    Ok(amp as u128 * N_COINS * N_COINS)
+}
+
+// D^3 / (4 * a * b). The smaller factor divides first: the rounding of that
+// division is then scaled down by D / b instead of up, which on imbalanced
+// pools is what keeps Newton's method from cycling around the root.
+fn d_cubed_over(d: u128, a: u128, b: u128) -> AmmResult<u128> {
+    let (small, large) = if a <= b { (a, b) } else { (b, a) };
+
+    mul_div_wide(mul_div_wide(d, d, small * N_COINS)?, d, large * N_COINS)
+}
+
+// Amplification at `current_time` while it is being ramped linearly from
+// `initial_amp` at `ramp_start_time` to `target_amp` at `ramp_stop_time`
+pub fn current_amp(
+    initial_amp: u64,
+    target_amp: u64,
+    ramp_start_time: i64,
+    ramp_stop_time: i64,
+    current_time: i64,
+) -> u64 {
+    if current_time >= ramp_stop_time || ramp_stop_time <= ramp_start_time {
+        return target_amp;
+    }
+    if current_time <= ramp_start_time {
+        return initial_amp;
+    }
+
+    let elapsed = (current_time - ramp_start_time) as u128;
+    let duration = (ramp_stop_time - ramp_start_time) as u128;
+
+    // The result lies between the two amplifications, so it fits in a u64
+    if target_amp >= initial_amp {
+        let change = (target_amp - initial_amp) as u128 * elapsed / duration;
+        initial_amp + change as u64
+    } else {
+        let change = (initial_amp - target_amp) as u128 * elapsed / duration;
+        initial_amp - change as u64
+    }
+}
+
+// Invariant D for the balances `x` and `y`
+pub fn compute_d(x: u64, y: u64, amp: u64) -> AmmResult<u128> {
+    if x == 0 || y == 0 {
+        return Err(AmmError::EmptyReserves);
+    }
+
+    let (x, y) = (x as u128, y as u128);
+    let sum = x + y;
+    let ann = amp_times_coins(amp)?;
+
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        let d_p = d_cubed_over(d, x, y)?;
+
+        let numerator = ann
+            .checked_mul(sum)
+            .and_then(|value| value.checked_add(d_p * N_COINS))
+            .ok_or(AmmError::Overflow)?;
+        let denominator = (ann - 1)
+            .checked_mul(d)
+            .and_then(|value| value.checked_add((N_COINS + 1) * d_p))
+            .ok_or(AmmError::Overflow)?;
+
+        let d_prev = d;
+        d = mul_div_wide(numerator, d, denominator)?;
+
+        if d.abs_diff(d_prev) <= 1 {
+            return Ok(d);
+        }
+    }
+
+    Err(AmmError::Overflow)
+}
+
+// Balance of the other token that keeps the invariant at `d` once one side
+// holds `balance`
+pub fn compute_y(balance: u128, d: u128, amp: u64) -> AmmResult<u128> {
+    if balance == 0 {
+        return Err(AmmError::EmptyReserves);
+    }
+
+    let ann = amp_times_coins(amp)?;
+
+    // c = D^3 / (4 * balance * Ann), b = balance + D / Ann
+    let c = d_cubed_over(d, balance, ann)?;
+    let b = balance + d / ann;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let denominator = (N_COINS * y + b)
+            .checked_sub(d)
+            .filter(|value| *value > 0)
+            .ok_or(AmmError::Overflow)?;
+
+        // (y^2 + c) / denominator, y^2 alone outgrows a u128 on large pools.
+        // The two remainders are each below the denominator, so adding them
+        // carries at most one unit into the quotient.
+        let (square, square_remainder) = wide_div_rem(y, y, denominator)?;
+        let remainders = (square_remainder + c % denominator) / denominator;
+
+        let y_prev = y;
+        y = square
+            .checked_add(c / denominator + remainders)
+            .ok_or(AmmError::Overflow)?;
+
+        if y.abs_diff(y_prev) <= 1 {
+            return Ok(y);
+        }
+    }
+
+    Err(AmmError::Overflow)
+}
+
+// StableSwap output for an exact-input swap. The fee is taken from the input
+// as on the constant-product curve. Returns (amount_out, fee).
+pub fn calculate_stable_swap_output(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amp: u64,
+    fee_bps: u16,
+) -> AmmResult<(u64, u64)> {
+    let d = compute_d(reserve_in, reserve_out, amp)?;
+
+    let fee = calculate_fee(amount_in as u128, fee_bps)?;
+    let new_reserve_in = reserve_in as u128 + (amount_in as u128 - fee);
+
+    let new_reserve_out = compute_y(new_reserve_in, d, amp)?;
+
+    // One unit is kept back so the rounding of y always favours the pool
+    let amount_out = (reserve_out as u128)
+        .saturating_sub(new_reserve_out)
+        .saturating_sub(1);
+
+    Ok((amount_out as u64, fee as u64))
+}
+
+// StableSwap input needed to receive exactly `amount_out`, rounded up.
+// Returns (amount_in, fee).
+pub fn calculate_stable_swap_input(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amp: u64,
+    fee_bps: u16,
+) -> AmmResult<(u64, u64)> {
+    if amount_out >= reserve_out {
+        return Err(AmmError::InsufficientReserves);
+    }
+
+    let d = compute_d(reserve_in, reserve_out, amp)?;
+
+    let new_reserve_in = compute_y((reserve_out - amount_out) as u128, d, amp)?;
+
+    let amount_in_after_fee = new_reserve_in
+        .saturating_sub(reserve_in as u128)
+        .checked_add(1)
+        .ok_or(AmmError::Overflow)?;
+
+    let amount_in = mul_div_ceil(
+        amount_in_after_fee,
+        BPS_DENOMINATOR,
+        BPS_DENOMINATOR - fee_bps as u128,
+    )?;
+
+    let fee = calculate_fee(amount_in, fee_bps)?;
+
+    Ok((to_u64(amount_in)?, fee as u64))
+}
+
+// Output `amount_in` would get at the marginal price of the curve,
+// dy/dx = y * (Ann * x + D_P) / (x * (Ann * y + D_P)) with D_P = D^3 / (4xy)
+pub fn calculate_stable_spot_output(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amp: u64,
+) -> AmmResult<u128> {
+    let d = compute_d(reserve_in, reserve_out, amp)?;
+    let ann = amp_times_coins(amp)?;
+    let (x, y) = (reserve_in as u128, reserve_out as u128);
+
+    let d_p = d_cubed_over(d, x, y)?;
+
+    let constant_product_output = mul_div(amount_in as u128, y, x)?;
+
+    mul_div_wide(
+        constant_product_output,
+        ann.checked_mul(x).ok_or(AmmError::Overflow)? + d_p,
+        ann.checked_mul(y).ok_or(AmmError::Overflow)? + d_p,
+    )
+}
+
+// Marginal price of token A in token B as a Q64.64 number, the same slope as
+// `calculate_stable_spot_output` without the u128 limit on the product
+pub fn calculate_stable_marginal_price(
+    reserve_a: u64,
+    reserve_b: u64,
+    amp: u64,
+) -> AmmResult<u128> {
+    let d = compute_d(reserve_a, reserve_b, amp)?;
+    let ann = amp_times_coins(amp)?;
+    let (x, y) = (reserve_a as u128, reserve_b as u128);
+
+    let d_p = d_cubed_over(d, x, y)?;
+
+    mul_div_wide(
+        mul_div_wide(y, Q64, x)?,
+        ann.checked_mul(x).ok_or(AmmError::Overflow)? + d_p,
+        ann.checked_mul(y).ok_or(AmmError::Overflow)? + d_p,
+    )
+}
+
+// LP tokens minted for a deposit into a stable pool. The first deposit mints
+// D, so one LP token is worth about one unit of the pegged asset, later
+// deposits mint their share of the growth of D.
+pub fn calculate_stable_lp_tokens_to_mint(
+    amount_a: u64,
+    amount_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    lp_supply: u64,
+    amp: u64,
+) -> AmmResult<u64> {
+    let new_reserve_a = reserve_a.checked_add(amount_a).ok_or(AmmError::Overflow)?;
+    let new_reserve_b = reserve_b.checked_add(amount_b).ok_or(AmmError::Overflow)?;
+
+    let d_after = compute_d(new_reserve_a, new_reserve_b, amp)?;
+
+    if lp_supply == 0 {
+        return to_u64(d_after);
+    }
+
+    let d_before = compute_d(reserve_a, reserve_b, amp)?;
+
+    let lp_tokens = mul_div_wide(
+        d_after.saturating_sub(d_before),
+        lp_supply as u128,
+        d_before,
+    )?;
+
+    to_u64(lp_tokens)
+}
+
+// StableSwap curve with the amplification in effect for the current slot
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StableCurve {
+    pub amp: u64,
+}
+
+impl SwapCurve for StableCurve {
+    fn swap_exact_in(
+        &self,
+        amount_in: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+        fee_bps: u16,
+    ) -> AmmResult<(u64, u64)> {
+        let (reserve_in, reserve_out) = directional_reserves(reserve_a, reserve_b, a_to_b);
+
+        calculate_stable_swap_output(amount_in, reserve_in, reserve_out, self.amp, fee_bps)
+    }
+
+    fn swap_exact_out(
+        &self,
+        amount_out: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+        fee_bps: u16,
+    ) -> AmmResult<(u64, u64)> {
+        let (reserve_in, reserve_out) = directional_reserves(reserve_a, reserve_b, a_to_b);
+
+        calculate_stable_swap_input(amount_out, reserve_in, reserve_out, self.amp, fee_bps)
+    }
+
+    fn deposit(
+        &self,
+        max_amount_a: u64,
+        max_amount_b: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        lp_supply: u64,
+    ) -> AmmResult<(u64, u64, u64)> {
+        let (amount_a, amount_b) =
+            calculate_deposit_amounts(max_amount_a, max_amount_b, reserve_a, reserve_b)?;
+
+        let lp_tokens = calculate_stable_lp_tokens_to_mint(
+            amount_a, amount_b, reserve_a, reserve_b, lp_supply, self.amp,
+        )?;
+
+        Ok((amount_a, amount_b, lp_tokens))
+    }
+
+    fn spot_price(
+        &self,
+        amount_in: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+    ) -> AmmResult<u128> {
+        let (reserve_in, reserve_out) = directional_reserves(reserve_a, reserve_b, a_to_b);
+
+        calculate_stable_spot_output(amount_in, reserve_in, reserve_out, self.amp)
+    }
+
+    fn marginal_price(&self, reserve_a: u64, reserve_b: u64) -> AmmResult<u128> {
+        calculate_stable_marginal_price(reserve_a, reserve_b, self.amp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amm::calculate_withdraw_amounts;
+
+    // Amplification bounds the program accepts
+    const MIN_AMP: u64 = 1;
+    const MAX_AMP: u64 = 1_000_000;
+
+    const RESERVES: [(u64, u64); 4] = [
+        (1_000_000_000, 1_000_000_000),
+        (1_000_000_000_000, 1_000_000),
+        (1_000_000, 1_000_000_000_000),
+        (123_456_789_012, 98_765_432_109),
+    ];
+
+    // Pools near the top of the u64 range, balanced and skewed
+    const LARGE_RESERVES: [(u64, u64); 4] = [
+        (10_000_000_000_000_000, 10_000_000_000_000_000),
+        (9_000_000_000_000_000_000, 9_000_000_000_000_000_000),
+        (9_000_000_000_000_000_000, 1_000_000_000_000_000_000),
+        (1_000_000_000_000_000, 8_000_000_000_000_000_000),
+    ];
+
+    #[test]
+    fn invariant_of_a_balanced_pool_is_the_sum_of_its_reserves() {
+        for amp in [MIN_AMP, 100, MAX_AMP] {
+            let d = compute_d(1_000_000_000, 1_000_000_000, amp).unwrap();
+            assert!(d.abs_diff(2_000_000_000) <= 1);
+        }
+    }
+
+    #[test]
+    fn invariant_converges_across_the_amp_range_and_imbalanced_reserves() {
+        for amp in [MIN_AMP, 10, 1_000, MAX_AMP] {
+            for (x, y) in RESERVES {
+                let d = compute_d(x, y, amp).unwrap();
+
+                // D lies between the constant-product and the constant-sum value
+                let geometric = 2 * crate::amm::integer_sqrt(x as u128 * y as u128);
+                assert!(d + 1 >= geometric && d <= x as u128 + y as u128 + 1);
+
+                // Solving back for y from x lands on y again, up to what one
+                // unit of D is worth in y on a skewed pool
+                let y_below = compute_y(x as u128, d - 1, amp).unwrap();
+                let y_above = compute_y(x as u128, d + 1, amp).unwrap();
+                assert!(
+                    y_below <= y as u128 && y as u128 <= y_above,
+                    "amp {amp}, ({x}, {y})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invariant_does_not_cycle_on_a_skewed_pool() {
+        // Dividing by the large reserve first left Newton's method cycling
+        // about 30 units around the root here
+        assert!(compute_d(1_100_000_000_000, 825_466, MIN_AMP).is_ok());
+    }
+
+    #[test]
+    fn invariant_converges_on_u64_scale_reserves() {
+        // Both overflowed the u128 product of the Newton numerator with D
+        let d = compute_d(1_000_000_000_000_000_000, 1_000_000_000_000_000_000, 100).unwrap();
+        assert!(d.abs_diff(2_000_000_000_000_000_000) <= 1);
+        let d = compute_d(10_000_000_000_000_000, 10_000_000_000_000_000, MAX_AMP).unwrap();
+        assert!(d.abs_diff(20_000_000_000_000_000) <= 1);
+
+        for (x, y) in LARGE_RESERVES {
+            let d = compute_d(x, y, MAX_AMP).unwrap();
+
+            let geometric = 2 * crate::amm::integer_sqrt(x as u128 * y as u128);
+            assert!(d + 1 >= geometric && d <= x as u128 + y as u128 + 1);
+
+            let y_below = compute_y(x as u128, d - 1, MAX_AMP).unwrap();
+            let y_above = compute_y(x as u128, d + 1, MAX_AMP).unwrap();
+            assert!(y_below <= y as u128 && y as u128 <= y_above, "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn u64_scale_swaps_keep_the_invariant_and_round_for_the_pool() {
+        for (reserve_in, reserve_out) in LARGE_RESERVES {
+            let amount_in = reserve_in / 10;
+            let (amount_out, _) =
+                calculate_stable_swap_output(amount_in, reserve_in, reserve_out, MAX_AMP, 4)
+                    .unwrap();
+
+            let d_before = compute_d(reserve_in, reserve_out, MAX_AMP).unwrap();
+            let d_after =
+                compute_d(reserve_in + amount_in, reserve_out - amount_out, MAX_AMP).unwrap();
+            assert!(d_after >= d_before, "({reserve_in}, {reserve_out})");
+
+            let amount_out = reserve_out / 10;
+            let (amount_in, _) =
+                calculate_stable_swap_input(amount_out, reserve_in, reserve_out, MAX_AMP, 4)
+                    .unwrap();
+            let (paid, _) =
+                calculate_stable_swap_output(amount_in, reserve_in, reserve_out, MAX_AMP, 4)
+                    .unwrap();
+            assert!(paid >= amount_out, "({reserve_in}, {reserve_out})");
+
+            // Near the peg the marginal price stays close to one
+            let spot =
+                calculate_stable_spot_output(1_000_000, reserve_in, reserve_out, MAX_AMP).unwrap();
+            assert!(spot > 0);
+            assert!(calculate_stable_marginal_price(reserve_in, reserve_out, MAX_AMP).is_ok());
+        }
+    }
+
+    #[test]
+    fn u64_scale_deposits_mint_their_share_of_the_invariant() {
+        let (reserve_a, reserve_b) = (4_000_000_000_000_000_000u64, 4_000_000_000_000_000_000u64);
+        let lp_supply = compute_d(reserve_a, reserve_b, MAX_AMP).unwrap() as u64;
+
+        let lp_tokens = calculate_stable_lp_tokens_to_mint(
+            reserve_a, reserve_b, reserve_a, reserve_b, lp_supply, MAX_AMP,
+        )
+        .unwrap();
+
+        // Doubling the reserves doubles the supply, less rounding
+        assert!(lp_tokens <= lp_supply && lp_supply - lp_tokens <= 2);
+    }
+
+    #[test]
+    fn swaps_never_decrease_the_invariant() {
+        for amp in [MIN_AMP, 100, MAX_AMP] {
+            for (reserve_in, reserve_out) in RESERVES {
+                for fee_bps in [0, 4, 30] {
+                    let amount_in = reserve_in / 10;
+                    let (amount_out, _) = calculate_stable_swap_output(
+                        amount_in,
+                        reserve_in,
+                        reserve_out,
+                        amp,
+                        fee_bps,
+                    )
+                    .unwrap();
+
+                    let d_before = compute_d(reserve_in, reserve_out, amp).unwrap();
+                    let d_after =
+                        compute_d(reserve_in + amount_in, reserve_out - amount_out, amp).unwrap();
+                    assert!(
+                        d_after >= d_before,
+                        "amp {amp}, ({reserve_in}, {reserve_out})"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_never_pay_out_more_than_was_put_in() {
+        for amp in [MIN_AMP, 100, MAX_AMP] {
+            let (reserve_a, reserve_b) = (5_000_000_000u64, 4_000_000_000u64);
+            let amount_in = 1_000_000_000;
+
+            let (amount_b, _) =
+                calculate_stable_swap_output(amount_in, reserve_a, reserve_b, amp, 0).unwrap();
+            let (amount_a, _) = calculate_stable_swap_output(
+                amount_b,
+                reserve_b - amount_b,
+                reserve_a + amount_in,
+                amp,
+                0,
+            )
+            .unwrap();
+
+            assert!(amount_a < amount_in);
+        }
+    }
+
+    #[test]
+    fn exact_output_swaps_round_the_input_up() {
+        for amp in [MIN_AMP, 100, MAX_AMP] {
+            for (reserve_in, reserve_out) in RESERVES {
+                let amount_out = reserve_out / 10;
+                let (amount_in, fee) =
+                    calculate_stable_swap_input(amount_out, reserve_in, reserve_out, amp, 30)
+                        .unwrap();
+
+                // The same input as an exact-input swap pays at least as much
+                let (paid, paid_fee) =
+                    calculate_stable_swap_output(amount_in, reserve_in, reserve_out, amp, 30)
+                        .unwrap();
+                assert!(
+                    paid >= amount_out,
+                    "amp {amp}, ({reserve_in}, {reserve_out})"
+                );
+                assert_eq!(paid_fee, fee);
+            }
+        }
+    }
+
+    #[test]
+    fn exact_output_cannot_empty_the_pool() {
+        assert_eq!(
+            calculate_stable_swap_input(1_000, 1_000, 1_000, 100, 30),
+            Err(AmmError::InsufficientReserves)
+        );
+    }
+
+    #[test]
+    fn amp_ramps_linearly_between_its_end_points() {
+        // Up from 100 to 200 over 100 seconds
+        assert_eq!(current_amp(100, 200, 1_000, 1_100, 900), 100);
+        assert_eq!(current_amp(100, 200, 1_000, 1_100, 1_000), 100);
+        assert_eq!(current_amp(100, 200, 1_000, 1_100, 1_025), 125);
+        assert_eq!(current_amp(100, 200, 1_000, 1_100, 1_100), 200);
+        assert_eq!(current_amp(100, 200, 1_000, 1_100, 2_000), 200);
+
+        // Down from 1_000 to 100 over 90 seconds
+        assert_eq!(current_amp(1_000, 100, 0, 90, 30), 700);
+        assert_eq!(current_amp(1_000, 100, 0, 90, 89), 110);
+        assert_eq!(current_amp(1_000, 100, 0, 90, 90), 100);
+
+        // A stopped ramp holds its target
+        assert_eq!(current_amp(100, 300, 1_000, 1_000, 1_000), 300);
+    }
+
+    #[test]
+    fn first_deposit_mints_the_invariant() {
+        let lp_tokens =
+            calculate_stable_lp_tokens_to_mint(1_000_000, 3_000_000, 0, 0, 0, 100).unwrap();
+
+        assert_eq!(
+            lp_tokens as u128,
+            compute_d(1_000_000, 3_000_000, 100).unwrap()
+        );
+    }
+
+    #[test]
+    fn deposits_round_their_lp_tokens_down() {
+        for amp in [MIN_AMP, 100, MAX_AMP] {
+            let (reserve_a, reserve_b) = (7_000_000_000u64, 3_000_000_000u64);
+            let lp_supply = compute_d(reserve_a, reserve_b, amp).unwrap() as u64;
+
+            let curve = StableCurve { amp };
+            let (amount_a, amount_b, lp_tokens) = curve
+                .deposit(700_000_001, 300_000_000, reserve_a, reserve_b, lp_supply)
+                .unwrap();
+
+            // Withdrawing the fresh LP tokens straight away returns no more
+            // than the deposit
+            let (back_a, back_b) = calculate_withdraw_amounts(
+                lp_tokens,
+                reserve_a + amount_a,
+                reserve_b + amount_b,
+                lp_supply + lp_tokens,
+            )
+            .unwrap();
+            assert!(back_a <= amount_a && back_b <= amount_b, "amp {amp}");
+        }
+    }
+}
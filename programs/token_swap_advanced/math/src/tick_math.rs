@@ -0,0 +1,169 @@
+// Conversions between ticks and sqrt prices for concentrated liquidity
+// pools. The price at tick `i` is 1.0001^i, and sqrt prices are Q64.64
+// fixed-point numbers.
+use crate::amm::{AmmError, AmmResult};
+
+pub const MIN_TICK: i32 = -443_636;
+pub const MAX_TICK: i32 = 443_636;
+// sqrt_price_from_tick(MIN_TICK) and sqrt_price_from_tick(MAX_TICK)
+pub const MIN_SQRT_PRICE: u128 = 4_295_048_016;
+pub const MAX_SQRT_PRICE: u128 = 79_226_673_521_066_979_257_578_248_091;
+
+const Q64: u128 = 1 << 64;
+
+// 2^64 / sqrt(1.0001)^(2^i), so each set bit of |tick| is one multiply
+const TICK_FACTORS: [u128; 19] = [
+    18_445_821_805_675_392_311,
+    18_444_899_583_751_176_498,
+    18_443_055_278_223_354_162,
+    18_439_367_220_385_604_838,
+    18_431_993_317_065_449_817,
+    18_417_254_355_718_160_513,
+    18_387_811_781_193_591_352,
+    18_329_067_761_203_520_168,
+    18_212_142_134_806_087_854,
+    17_980_523_815_641_551_639,
+    17_526_086_738_831_147_013,
+    16_651_378_430_235_024_244,
+    15_030_750_278_693_429_944,
+    12_247_334_978_882_834_399,
+    8_131_365_268_884_726_200,
+    3_584_323_654_723_342_297,
+    696_457_651_847_595_233,
+    26_294_789_957_452_057,
+    37_481_735_321_082,
+];
+
+// Q64.64 sqrt price at `tick`
+pub fn sqrt_price_from_tick(tick: i32) -> AmmResult<u128> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(AmmError::InvalidTick);
+    }
+
+    // sqrt(1.0001)^-|tick|, every factor is below one so this stays <= 2^64
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = Q64;
+    for (bit, factor) in TICK_FACTORS.iter().enumerate() {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * factor) >> 64;
+        }
+    }
+
+    if tick > 0 {
+        // 2^128 / ratio
+        ratio = u128::MAX / ratio;
+    }
+
+    Ok(ratio)
+}
+
+// Largest tick whose sqrt price is at or below `sqrt_price`
+pub fn tick_from_sqrt_price(sqrt_price: u128) -> AmmResult<i32> {
+    if !(MIN_SQRT_PRICE..=MAX_SQRT_PRICE).contains(&sqrt_price) {
+        return Err(AmmError::InvalidTick);
+    }
+
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        if sqrt_price_from_tick(mid)? <= sqrt_price {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    Ok(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICKS: [i32; 11] = [
+        MIN_TICK,
+        MIN_TICK + 1,
+        -200_000,
+        -887,
+        -1,
+        0,
+        1,
+        60,
+        200_000,
+        MAX_TICK - 1,
+        MAX_TICK,
+    ];
+
+    #[test]
+    fn tick_factors_are_powers_of_the_tick_ratio() {
+        for (bit, &factor) in TICK_FACTORS.iter().enumerate() {
+            let expected = (-((1u64 << bit) as f64) / 2.0 * 0.0001f64.ln_1p()).exp() * Q64 as f64;
+            assert!(
+                (factor as f64 - expected).abs() <= expected * 1e-12,
+                "factor {}",
+                bit
+            );
+        }
+
+        // Each factor is the square of the one before, up to the rounding of
+        // the larger one
+        for pair in TICK_FACTORS.windows(2) {
+            let squared = (pair[0] * pair[0]) >> 64;
+            assert!(squared.abs_diff(pair[1]) <= 2);
+        }
+    }
+
+    #[test]
+    fn bounds_match_the_extreme_ticks() {
+        assert_eq!(sqrt_price_from_tick(0), Ok(Q64));
+        assert_eq!(sqrt_price_from_tick(MIN_TICK), Ok(MIN_SQRT_PRICE));
+        assert_eq!(sqrt_price_from_tick(MAX_TICK), Ok(MAX_SQRT_PRICE));
+
+        assert_eq!(
+            sqrt_price_from_tick(MIN_TICK - 1),
+            Err(AmmError::InvalidTick)
+        );
+        assert_eq!(
+            sqrt_price_from_tick(MAX_TICK + 1),
+            Err(AmmError::InvalidTick)
+        );
+        assert_eq!(
+            tick_from_sqrt_price(MIN_SQRT_PRICE - 1),
+            Err(AmmError::InvalidTick)
+        );
+        assert_eq!(
+            tick_from_sqrt_price(MAX_SQRT_PRICE + 1),
+            Err(AmmError::InvalidTick)
+        );
+    }
+
+    #[test]
+    fn sqrt_prices_follow_the_tick_ratio() {
+        for &tick in &TICKS {
+            let sqrt_price = sqrt_price_from_tick(tick).unwrap() as f64 / Q64 as f64;
+            let expected = (tick as f64 / 2.0 * 0.0001f64.ln_1p()).exp();
+            assert!(
+                (sqrt_price - expected).abs() <= expected * 1e-9,
+                "tick {}",
+                tick
+            );
+        }
+    }
+
+    #[test]
+    fn ticks_round_trip_through_their_sqrt_prices() {
+        for &tick in &TICKS {
+            let sqrt_price = sqrt_price_from_tick(tick).unwrap();
+            assert_eq!(tick_from_sqrt_price(sqrt_price), Ok(tick));
+
+            // Anything below the tick's price belongs to the tick before it
+            if tick > MIN_TICK {
+                assert_eq!(tick_from_sqrt_price(sqrt_price - 1), Ok(tick - 1));
+                assert!(sqrt_price_from_tick(tick - 1).unwrap() < sqrt_price);
+            }
+            if tick < MAX_TICK {
+                assert_eq!(tick_from_sqrt_price(sqrt_price + 1), Ok(tick));
+            }
+        }
+    }
+}
@@ -0,0 +1,400 @@
+// Balancer-style weighted-product invariant for two tokens:
+//
+//     V = x^w_a * y^w_b,  w_a + w_b = 1
+//
+// A 50/50 pool prices like x * y = k, an 80/20 pool lets most of the
+// liquidity sit in one token. Weights are normalized 18-decimal fixed-point
+// values.
+use crate::amm::{
+    calculate_deposit_amounts, calculate_fee, calculate_lp_tokens_to_mint, directional_reserves,
+    mul_div, mul_div_ceil, to_u64, AmmError, AmmResult, SwapCurve, BPS_DENOMINATOR,
+};
+use crate::fixed_point::{div_down, div_up, mul_down, mul_up, pow_up, weighted_product, ONE};
+use crate::full_math::{mul_div_wide, Q64};
+
+// A single swap can move at most 30% of a reserve, beyond that the
+// fixed-point `pow` loses too much precision
+const MAX_IN_RATIO: u128 = 300_000_000_000_000_000;
+const MAX_OUT_RATIO: u128 = 300_000_000_000_000_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WeightedCurve {
+    pub weight_a: u64,
+    pub weight_b: u64,
+}
+
+impl WeightedCurve {
+    // (weight_in, weight_out) for a swap in the given direction
+    fn weights(&self, a_to_b: bool) -> (u128, u128) {
+        if a_to_b {
+            (self.weight_a as u128, self.weight_b as u128)
+        } else {
+            (self.weight_b as u128, self.weight_a as u128)
+        }
+    }
+}
+
+// Weighted-product output for an exact-input swap, fee taken from the input:
+// out = reserve_out * (1 - (reserve_in / (reserve_in + in))^(w_in / w_out)).
+// Returns (amount_out, fee).
+pub fn calculate_weighted_swap_output(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    weight_in: u128,
+    weight_out: u128,
+    fee_bps: u16,
+) -> AmmResult<(u64, u64)> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(AmmError::EmptyReserves);
+    }
+
+    let fee = calculate_fee(amount_in as u128, fee_bps)?;
+    let amount_in_after_fee = amount_in as u128 - fee;
+
+    if amount_in_after_fee > mul_down(reserve_in as u128, MAX_IN_RATIO)? {
+        return Err(AmmError::InsufficientReserves);
+    }
+
+    // The base and the power round up, so the output rounds down
+    let base = div_up(reserve_in as u128, reserve_in as u128 + amount_in_after_fee)?;
+    let exponent = div_down(weight_in, weight_out)?;
+    let power = pow_up(base, exponent)?;
+
+    let amount_out = mul_down(reserve_out as u128, ONE.saturating_sub(power))?;
+
+    Ok((amount_out as u64, fee as u64))
+}
+
+// Weighted-product input needed to receive exactly `amount_out`:
+// in = reserve_in * ((reserve_out / (reserve_out - out))^(w_out / w_in) - 1),
+// grossed up by the fee. Returns (amount_in, fee).
+pub fn calculate_weighted_swap_input(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    weight_in: u128,
+    weight_out: u128,
+    fee_bps: u16,
+) -> AmmResult<(u64, u64)> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(AmmError::EmptyReserves);
+    }
+    if amount_out as u128 > mul_down(reserve_out as u128, MAX_OUT_RATIO)? {
+        return Err(AmmError::InsufficientReserves);
+    }
+
+    let base = div_up(reserve_out as u128, (reserve_out - amount_out) as u128)?;
+    let exponent = div_up(weight_out, weight_in)?;
+    let power = pow_up(base, exponent)?;
+
+    let amount_in_after_fee = mul_up(reserve_in as u128, power - ONE)?;
+
+    let amount_in = mul_div_ceil(
+        amount_in_after_fee,
+        BPS_DENOMINATOR,
+        BPS_DENOMINATOR - fee_bps as u128,
+    )?;
+
+    let fee = calculate_fee(amount_in, fee_bps)?;
+
+    Ok((to_u64(amount_in)?, fee as u64))
+}
+
+impl SwapCurve for WeightedCurve {
+    fn swap_exact_in(
+        &self,
+        amount_in: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+        fee_bps: u16,
+    ) -> AmmResult<(u64, u64)> {
+        let (reserve_in, reserve_out) = directional_reserves(reserve_a, reserve_b, a_to_b);
+        let (weight_in, weight_out) = self.weights(a_to_b);
+
+        calculate_weighted_swap_output(
+            amount_in,
+            reserve_in,
+            reserve_out,
+            weight_in,
+            weight_out,
+            fee_bps,
+        )
+    }
+
+    fn swap_exact_out(
+        &self,
+        amount_out: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+        fee_bps: u16,
+    ) -> AmmResult<(u64, u64)> {
+        let (reserve_in, reserve_out) = directional_reserves(reserve_a, reserve_b, a_to_b);
+        let (weight_in, weight_out) = self.weights(a_to_b);
+
+        calculate_weighted_swap_input(
+            amount_out,
+            reserve_in,
+            reserve_out,
+            weight_in,
+            weight_out,
+            fee_bps,
+        )
+    }
+
+    // Deposits keep the reserve ratio like on the other curves. The first one
+    // mints the invariant V, later ones their share of the reserves.
+    fn deposit(
+        &self,
+        max_amount_a: u64,
+        max_amount_b: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        lp_supply: u64,
+    ) -> AmmResult<(u64, u64, u64)> {
+        let (amount_a, amount_b) =
+            calculate_deposit_amounts(max_amount_a, max_amount_b, reserve_a, reserve_b)?;
+
+        let lp_tokens = if lp_supply == 0 {
+            to_u64(weighted_product(&[
+                (amount_a, self.weight_a as u128),
+                (amount_b, self.weight_b as u128),
+            ])?)?
+        } else {
+            calculate_lp_tokens_to_mint(amount_a, amount_b, reserve_a, reserve_b, lp_supply)?
+        };
+
+        Ok((amount_a, amount_b, lp_tokens))
+    }
+
+    // Marginal price (reserve_out / w_out) / (reserve_in / w_in)
+    fn spot_price(
+        &self,
+        amount_in: u64,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+    ) -> AmmResult<u128> {
+        let (reserve_in, reserve_out) = directional_reserves(reserve_a, reserve_b, a_to_b);
+        let (weight_in, weight_out) = self.weights(a_to_b);
+
+        mul_div(
+            mul_div(amount_in as u128, reserve_out as u128, reserve_in as u128)?,
+            weight_in,
+            weight_out,
+        )
+    }
+
+    // (reserve_b / weight_b) / (reserve_a / weight_a)
+    fn marginal_price(&self, reserve_a: u64, reserve_b: u64) -> AmmResult<u128> {
+        mul_div_wide(
+            mul_div_wide(reserve_b as u128, Q64, reserve_a as u128)?,
+            self.weight_a as u128,
+            self.weight_b as u128,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEIGHTS: [(u64, u64); 4] = [
+        (500_000_000_000_000_000, 500_000_000_000_000_000),
+        (800_000_000_000_000_000, 200_000_000_000_000_000),
+        (200_000_000_000_000_000, 800_000_000_000_000_000),
+        (10_000_000_000_000_000, 990_000_000_000_000_000),
+    ];
+    const RESERVES: [(u64, u64); 3] = [
+        (1_000_000_000, 1_000_000_000),
+        (5_000_000_000_000, 20_000_000),
+        (1_000_000, 3_000_000_000_000),
+    ];
+    const FEE_BPS: u16 = 30;
+
+    fn curves() -> impl Iterator<Item = WeightedCurve> {
+        WEIGHTS
+            .iter()
+            .map(|&(weight_a, weight_b)| WeightedCurve { weight_a, weight_b })
+    }
+
+    // Inputs from a unit up to just under the 30% cap on the input reserve
+    fn amounts_in(reserve_in: u64) -> [u64; 4] {
+        [1, reserve_in / 1_000, reserve_in / 10, reserve_in / 4]
+    }
+
+    #[test]
+    fn swapping_back_never_returns_more_than_was_put_in() {
+        for curve in curves() {
+            for &(reserve_a, reserve_b) in &RESERVES {
+                for &amount_in in &amounts_in(reserve_a) {
+                    let (amount_out, _) = curve
+                        .swap_exact_in(amount_in, reserve_a, reserve_b, true, FEE_BPS)
+                        .unwrap();
+                    if amount_out == 0 {
+                        continue;
+                    }
+
+                    // Book the trade without a protocol fee, then undo it
+                    let (reserve_a, reserve_b) = (reserve_a + amount_in, reserve_b - amount_out);
+                    let amount_back =
+                        match curve.swap_exact_in(amount_out, reserve_a, reserve_b, false, 0) {
+                            Ok((amount_back, _)) => amount_back,
+                            // On skewed weights the way back can be over the cap
+                            Err(AmmError::InsufficientReserves) => continue,
+                            Err(error) => panic!("{:?}", error),
+                        };
+                    assert!(
+                        amount_back <= amount_in,
+                        "{:?} {} -> {} -> {}",
+                        curve,
+                        amount_in,
+                        amount_out,
+                        amount_back
+                    );
+                }
+            }
+        }
+    }
+
+    // Relative change of the invariant x^w_a * y^w_b when `amount_in` joins
+    // one reserve and `amount_out` leaves the other, as a natural logarithm
+    fn ln_invariant_change(
+        curve: &WeightedCurve,
+        reserve_a: u64,
+        reserve_b: u64,
+        a_to_b: bool,
+        amount_in: u64,
+        amount_out: u64,
+    ) -> f64 {
+        let (reserve_in, reserve_out) = directional_reserves(reserve_a, reserve_b, a_to_b);
+        let (weight_in, weight_out) = curve.weights(a_to_b);
+
+        weight_in as f64 / ONE as f64 * (amount_in as f64 / reserve_in as f64).ln_1p()
+            + weight_out as f64 / ONE as f64 * (-(amount_out as f64) / reserve_out as f64).ln_1p()
+    }
+
+    #[test]
+    fn swaps_never_shrink_the_invariant() {
+        for curve in curves() {
+            for &(reserve_a, reserve_b) in &RESERVES {
+                for a_to_b in [true, false] {
+                    let (reserve_in, reserve_out) =
+                        directional_reserves(reserve_a, reserve_b, a_to_b);
+
+                    // Without a fee any gain of the pool comes from rounding
+                    for &amount_in in &amounts_in(reserve_in) {
+                        let (amount_out, _) = curve
+                            .swap_exact_in(amount_in, reserve_a, reserve_b, a_to_b, 0)
+                            .unwrap();
+                        let change = ln_invariant_change(
+                            &curve, reserve_a, reserve_b, a_to_b, amount_in, amount_out,
+                        );
+                        assert!(change >= 0.0, "{:?} in {}: {}", curve, amount_in, change);
+                    }
+
+                    for &amount_out in &amounts_in(reserve_out) {
+                        let amount_in = match curve
+                            .swap_exact_out(amount_out, reserve_a, reserve_b, a_to_b, 0)
+                        {
+                            Ok((amount_in, _)) => amount_in,
+                            // A 1/99 pool can ask for more than fits in a u64
+                            Err(AmmError::Overflow) => continue,
+                            Err(error) => panic!("{:?}", error),
+                        };
+                        let change = ln_invariant_change(
+                            &curve, reserve_a, reserve_b, a_to_b, amount_in, amount_out,
+                        );
+                        assert!(change >= 0.0, "{:?} out {}: {}", curve, amount_out, change);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_output_fees_gross_up_the_input() {
+        for curve in curves() {
+            let (amount_in, fee) = curve
+                .swap_exact_out(1_000_000, 1_000_000_000, 1_000_000_000, true, FEE_BPS)
+                .unwrap();
+            let (amount_in_without_fee, _) = curve
+                .swap_exact_out(1_000_000, 1_000_000_000, 1_000_000_000, true, 0)
+                .unwrap();
+
+            assert_eq!(fee, amount_in * FEE_BPS as u64 / 10_000);
+            assert!(amount_in - fee >= amount_in_without_fee);
+        }
+    }
+
+    #[test]
+    fn fifty_fifty_pools_never_beat_the_constant_product() {
+        let curve = WeightedCurve {
+            weight_a: WEIGHTS[0].0,
+            weight_b: WEIGHTS[0].1,
+        };
+
+        for &(reserve_a, reserve_b) in &RESERVES {
+            for &amount_in in &amounts_in(reserve_a) {
+                let (amount_out, fee) = curve
+                    .swap_exact_in(amount_in, reserve_a, reserve_b, true, FEE_BPS)
+                    .unwrap();
+
+                let amount_in_after_fee = (amount_in - fee) as u128;
+                let constant_product = amount_in_after_fee * reserve_b as u128
+                    / (reserve_a as u128 + amount_in_after_fee);
+                assert!(amount_out as u128 <= constant_product);
+                // The padding on `pow` costs at most a unit per 1e13 of reserve
+                assert!(
+                    constant_product - amount_out as u128
+                        <= 1 + reserve_b as u128 / 10_u128.pow(13)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn swaps_are_capped_at_thirty_percent_of_a_reserve() {
+        let curve = WeightedCurve {
+            weight_a: WEIGHTS[1].0,
+            weight_b: WEIGHTS[1].1,
+        };
+        let reserve = 1_000_000_000;
+
+        // The cap applies to the input after the fee
+        assert!(curve
+            .swap_exact_in(300_000_000, reserve, reserve, true, 0)
+            .is_ok());
+        assert_eq!(
+            curve.swap_exact_in(300_000_001, reserve, reserve, true, 0),
+            Err(AmmError::InsufficientReserves)
+        );
+
+        assert!(curve
+            .swap_exact_out(300_000_000, reserve, reserve, true, 0)
+            .is_ok());
+        assert_eq!(
+            curve.swap_exact_out(300_000_001, reserve, reserve, true, 0),
+            Err(AmmError::InsufficientReserves)
+        );
+    }
+
+    #[test]
+    fn empty_pools_cannot_swap() {
+        let curve = WeightedCurve {
+            weight_a: WEIGHTS[0].0,
+            weight_b: WEIGHTS[0].1,
+        };
+
+        assert_eq!(
+            curve.swap_exact_in(1_000, 0, 1_000_000, true, FEE_BPS),
+            Err(AmmError::EmptyReserves)
+        );
+        assert_eq!(
+            curve.swap_exact_out(1_000, 1_000_000, 0, true, FEE_BPS),
+            Err(AmmError::EmptyReserves)
+        );
+    }
+}
@@ -1,4 +1,11 @@
+use crate::errors::error::TokenSwapError;
+use crate::state::clmm_pool::ClmmPool;
+use crate::state::config::Config;
 use crate::state::liquidity_account::LiquidityAccount;
+use crate::state::observation::{ObservationBuffer, MAX_OBSERVATION_GROWTH};
+use crate::state::pool::Pool;
+use crate::state::position::Position;
+use crate::state::tick_array::TickArray;
 use crate::state::token_mint_metadata::TokenMintMetadata;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, InitializeAccount, TokenAccount as SPLTokenAccount, Transfer};
@@ -8,36 +15,176 @@ use anchor_spl::{
         create_metadata_accounts_v3, mpl_token_metadata::types::DataV2, CreateMetadataAccountsV3,
         Metadata as Metaplex,
     },
-    token::{mint_to, Mint, MintTo, Token, TokenAccount},
+    token::{mint_to, MintTo, Token},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // Only the upgrade authority of the program can set the first admin
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key())
+            @ TokenSwapError::UnauthorizedUpgradeAuthority
+    )]
+    pub program: Program<'info, crate::program::SimpleTokenSwap>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key())
+            @ TokenSwapError::UnauthorizedUpgradeAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigAdmin<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ TokenSwapError::UnauthorizedProgramAdmin
+    )]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptConfigAdmin<'info> {
+    pub pending_admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = pending_admin @ TokenSwapError::UnauthorizedPendingAdmin
+    )]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(fee_bps: u16)]
+pub struct InitializePool<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Pool::INIT_SPACE,
+        seeds = [b"pool", mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+
+    // Starts with room for a single observation, see `GrowObservations`
+    #[account(
+        init,
+        payer = payer,
+        space = ObservationBuffer::space(1),
+        seeds = [b"observations", pool.key().as_ref()],
+        bump
+    )]
+    pub observations: AccountLoader<'info, ObservationBuffer>,
+
+    // Mints are stored in sorted order so each pair has a single pool address
+    #[account(
+        constraint = mint_a.key() != mint_b.key() @ TokenSwapError::IdenticalMints,
+        constraint = mint_a.key() < mint_b.key() @ TokenSwapError::UnsortedMints
+    )]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+
+    pub mint_b: InterfaceAccount<'info, Mint>,
+
+    // Pools are created by the program admin, who also administers them
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ TokenSwapError::UnauthorizedProgramAdmin
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PoolAdmin<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool", pool.mint_a.as_ref(), pool.mint_b.as_ref()],
+        bump = pool.bump,
+        has_one = admin @ TokenSwapError::Unauthorized
+    )]
+    pub pool: Account<'info, Pool>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptPoolAdmin<'info> {
+    pub pending_admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool", pool.mint_a.as_ref(), pool.mint_b.as_ref()],
+        bump = pool.bump,
+        has_one = pending_admin @ TokenSwapError::UnauthorizedPendingAdmin
+    )]
+    pub pool: Account<'info, Pool>,
+}
+
 #[derive(Accounts)]
 #[instruction()]
 pub struct InitializeVaultTokenA<'info> {
     #[account(
         init_if_needed,
-        seeds = [b"vaultTokenA", mint.key().as_ref()],
+        seeds = [b"vaultTokenA", pool.key().as_ref()],
         bump,
         payer = payer,
         token::mint = mint,
-        token::authority = vault_auth
+        token::authority = vault_auth,
+        token::token_program = token_program
     )]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: PDA will be the authority for the vault PDA
     #[account{
-        seeds = [b"vaultTokenA", mint.key().as_ref()],
+        seeds = [b"vaultTokenA", pool.key().as_ref()],
         bump
     }]
     pub vault_auth: AccountInfo<'info>,
 
+    #[account(
+        mut,
+        has_one = admin @ TokenSwapError::Unauthorized
+    )]
+    pub pool: Account<'info, Pool>,
+
+    pub admin: Signer<'info>,
+
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    pub mint: Account<'info, Mint>,
+    #[account(address = pool.mint_a)]
+    pub mint: InterfaceAccount<'info, Mint>,
 
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub rent: Sysvar<'info, Rent>,
 }
 
@@ -46,28 +193,38 @@ pub struct InitializeVaultTokenA<'info> {
 pub struct InitializeVaultTokenB<'info> {
     #[account(
         init_if_needed,
-        seeds = [b"vaultTokenB", mint.key().as_ref()],
+        seeds = [b"vaultTokenB", pool.key().as_ref()],
         bump,
         payer = payer,
         token::mint = mint,
-        token::authority = vault_auth
+        token::authority = vault_auth,
+        token::token_program = token_program
     )]
-    pub vault_token_account: Account<'info, TokenAccount>,
+    pub vault_token_account: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: PDA will be the authority for the vault PDAs
     #[account{
-        seeds = [b"vaultTokenB", mint.key().as_ref()],
+        seeds = [b"vaultTokenB", pool.key().as_ref()],
         bump
     }]
     pub vault_auth: AccountInfo<'info>,
 
+    #[account(
+        mut,
+        has_one = admin @ TokenSwapError::Unauthorized
+    )]
+    pub pool: Account<'info, Pool>,
+
+    pub admin: Signer<'info>,
+
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    pub mint: Account<'info, Mint>,
+    #[account(address = pool.mint_b)]
+    pub mint: InterfaceAccount<'info, Mint>,
 
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub rent: Sysvar<'info, Rent>,
 }
 
@@ -80,11 +237,13 @@ pub struct InitializeUserLiquidityAccount<'info> {
         init,
         payer = user,
         space = 8 + 32 + 8 + 8,
-        seeds = [b"userliquidityPDA", user.key().as_ref()],
+        seeds = [b"userliquidityPDA", pool.key().as_ref(), user.key().as_ref()],
         bump
     )]
     pub user_pda_account: Account<'info, LiquidityAccount>,
 
+    pub pool: Account<'info, Pool>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -98,20 +257,28 @@ pub struct CreateTokenMint<'info> {
     #[account(
         init,
         payer = payer,
-        seeds = [b"mint"],
+        seeds = [b"mint", pool.key().as_ref()],
         bump,
         mint::decimals = params.decimals,
         mint::authority = authority.key(),
     )]
-    pub mint: Account<'info, Mint>,
+    pub mint: InterfaceAccount<'info, Mint>,
 
     /// CHECK: PDA that controls the mint
     #[account(
-        seeds = [b"authority"],
+        seeds = [b"authority", pool.key().as_ref()],
         bump,
     )]
     pub authority: UncheckedAccount<'info>,
 
+    #[account(
+        mut,
+        has_one = admin @ TokenSwapError::Unauthorized
+    )]
+    pub pool: Account<'info, Pool>,
+
+    pub admin: Signer<'info>,
+
     #[account(mut)]
     pub payer: Signer<'info>,
 
@@ -128,61 +295,82 @@ pub struct Liquidity<'info> {
 
     #[account(
         mut,
-        seeds = [b"userliquidityPDA", user.key().as_ref()],
+        seeds = [b"userliquidityPDA", pool.key().as_ref(), user.key().as_ref()],
         bump
     )]
     pub user_pda_account: Account<'info, LiquidityAccount>,
 
+    #[account(
+        mut,
+        seeds = [b"pool", mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump = pool.bump,
+        has_one = mint_a,
+        has_one = mint_b
+    )]
+    pub pool: Account<'info, Pool>,
+
     #[account(mut)]
-    pub user_token_account_for_token_a: Account<'info, TokenAccount>,
+    pub user_token_account_for_token_a: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
-    pub user_token_account_for_token_b: Account<'info, TokenAccount>,
+    pub user_token_account_for_token_b: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [b"vaultTokenA", mint_a.key().as_ref()],
-        bump
+        seeds = [b"vaultTokenA", pool.key().as_ref()],
+        bump = pool.vault_a_bump,
+        address = pool.vault_a
     )]
-    pub vault_token_a_account: Account<'info, TokenAccount>,
+    pub vault_token_a_account: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [b"vaultTokenB", mint_b.key().as_ref()],
-        bump
+        seeds = [b"vaultTokenB", pool.key().as_ref()],
+        bump = pool.vault_b_bump,
+        address = pool.vault_b
     )]
-    pub vault_token_b_account: Account<'info, TokenAccount>,
+    pub vault_token_b_account: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: This is just a signer PDA, no data
     #[account(
-        seeds = [b"vaultTokenA", mint_a.key().as_ref()],
-        bump
+        seeds = [b"vaultTokenA", pool.key().as_ref()],
+        bump = pool.vault_a_bump
     )]
     pub vault_auth_a: AccountInfo<'info>,
 
     /// CHECK: This is just a signer PDA, no data
     #[account(
-        seeds = [b"vaultTokenB", mint_b.key().as_ref()],
-        bump
+        seeds = [b"vaultTokenB", pool.key().as_ref()],
+        bump = pool.vault_b_bump
     )]
     pub vault_auth_b: AccountInfo<'info>,
 
-    pub mint_a: Account<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"observations", pool.key().as_ref()],
+        bump = pool.observations_bump
+    )]
+    pub observations: AccountLoader<'info, ObservationBuffer>,
+
+    #[account(mint::token_program = token_program_a)]
+    pub mint_a: InterfaceAccount<'info, Mint>,
 
-    pub mint_b: Account<'info, Mint>,
+    #[account(mint::token_program = token_program_b)]
+    pub mint_b: InterfaceAccount<'info, Mint>,
 
     // For Minting LP Tokens
     #[account(
         mut,
-        seeds = [b"mint"],
-        bump,
+        seeds = [b"mint", pool.key().as_ref()],
+        bump = pool.lp_mint_bump,
+        address = pool.lp_mint
     )]
-    pub mint: Account<'info, Mint>,
+    pub mint: InterfaceAccount<'info, Mint>,
 
     /// CHECK
     #[account(
-        seeds = [b"authority"],
-        bump
+        seeds = [b"authority", pool.key().as_ref()],
+        bump = pool.authority_bump
     )]
     pub authority: UncheckedAccount<'info>,
 
@@ -192,7 +380,7 @@ pub struct Liquidity<'info> {
         associated_token::mint = mint,
         associated_token::authority = destination_owner,
     )]
-    pub destination: Account<'info, TokenAccount>,
+    pub destination: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: we use this to validate token owner
     pub destination_owner: UncheckedAccount<'info>,
@@ -202,7 +390,10 @@ pub struct Liquidity<'info> {
 
     pub rent: Sysvar<'info, Rent>,
     pub system_program: Program<'info, System>,
+    // Program of the LP mint, which is always an SPL Token mint
     pub token_program: Program<'info, Token>,
+    pub token_program_a: Interface<'info, TokenInterface>,
+    pub token_program_b: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
@@ -211,43 +402,456 @@ pub struct TokenSwap<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"pool", mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump = pool.bump,
+        has_one = mint_a,
+        has_one = mint_b
+    )]
+    pub pool: Account<'info, Pool>,
+
     #[account(mut)]
-    pub user_token_account_for_token_a: Account<'info, TokenAccount>,
+    pub user_token_account_for_token_a: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
-    pub user_token_account_for_token_b: Account<'info, TokenAccount>,
+    pub user_token_account_for_token_b: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [b"vaultTokenA", mint_a.key().as_ref()],
-        bump
+        seeds = [b"vaultTokenA", pool.key().as_ref()],
+        bump = pool.vault_a_bump,
+        address = pool.vault_a
     )]
-    pub vault_token_a_account: Account<'info, TokenAccount>,
+    pub vault_token_a_account: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [b"vaultTokenB", mint_b.key().as_ref()],
-        bump
+        seeds = [b"vaultTokenB", pool.key().as_ref()],
+        bump = pool.vault_b_bump,
+        address = pool.vault_b
     )]
-    pub vault_token_b_account: Account<'info, TokenAccount>,
+    pub vault_token_b_account: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: This is just a signer PDA, no data
     #[account(
-        seeds = [b"vaultTokenA", mint_a.key().as_ref()],
-        bump
+        seeds = [b"vaultTokenA", pool.key().as_ref()],
+        bump = pool.vault_a_bump
+    )]
+    pub vault_auth_a: AccountInfo<'info>,
+
+    /// CHECK: This is just a signer PDA, no data
+    #[account(
+        seeds = [b"vaultTokenB", pool.key().as_ref()],
+        bump = pool.vault_b_bump
+    )]
+    pub vault_auth_b: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"observations", pool.key().as_ref()],
+        bump = pool.observations_bump
+    )]
+    pub observations: AccountLoader<'info, ObservationBuffer>,
+
+    #[account(mint::token_program = token_program_a)]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(mint::token_program = token_program_b)]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+
+    pub token_program_a: Interface<'info, TokenInterface>,
+    pub token_program_b: Interface<'info, TokenInterface>,
+}
+// The pools and vaults of the route are passed as remaining accounts
+#[derive(Accounts)]
+pub struct SwapRoute<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        token::mint = source_mint,
+        token::authority = user,
+        token::token_program = source_token_program
+    )]
+    pub user_source_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_destination_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub source_mint: InterfaceAccount<'info, Mint>,
+
+    pub source_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pool", mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump = pool.bump,
+        has_one = admin @ TokenSwapError::Unauthorized,
+        has_one = mint_a,
+        has_one = mint_b
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"vaultTokenA", pool.key().as_ref()],
+        bump = pool.vault_a_bump,
+        address = pool.vault_a
+    )]
+    pub vault_token_a_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"vaultTokenB", pool.key().as_ref()],
+        bump = pool.vault_b_bump,
+        address = pool.vault_b
+    )]
+    pub vault_token_b_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: This is just a signer PDA, no data
+    #[account(
+        seeds = [b"vaultTokenA", pool.key().as_ref()],
+        bump = pool.vault_a_bump
     )]
     pub vault_auth_a: AccountInfo<'info>,
 
     /// CHECK: This is just a signer PDA, no data
     #[account(
-        seeds = [b"vaultTokenB", mint_b.key().as_ref()],
+        seeds = [b"vaultTokenB", pool.key().as_ref()],
+        bump = pool.vault_b_bump
+    )]
+    pub vault_auth_b: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        token::mint = mint_a,
+        token::authority = pool.treasury
+    )]
+    pub treasury_token_a_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint_b,
+        token::authority = pool.treasury
+    )]
+    pub treasury_token_b_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mint::token_program = token_program_a)]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(mint::token_program = token_program_b)]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+
+    pub token_program_a: Interface<'info, TokenInterface>,
+    pub token_program_b: Interface<'info, TokenInterface>,
+}
+
+// The quotes net out Token-2022 transfer fees like the handlers, so they
+// need the pool's mints
+#[derive(Accounts)]
+pub struct QuotePool<'info> {
+    #[account(
+        seeds = [b"pool", pool.mint_a.as_ref(), pool.mint_b.as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(address = pool.mint_a)]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(address = pool.mint_b)]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+}
+
+// The oracle views only read the pool's sums
+#[derive(Accounts)]
+pub struct ReadPool<'info> {
+    #[account(
+        seeds = [b"pool", pool.mint_a.as_ref(), pool.mint_b.as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+}
+
+// Anyone can pay for more observation slots
+#[derive(Accounts)]
+#[instruction(cardinality_next: u16)]
+pub struct GrowObservations<'info> {
+    // Checked here because the realloc below runs before the handler
+    #[account(
+        seeds = [b"pool", pool.mint_a.as_ref(), pool.mint_b.as_ref()],
+        bump = pool.bump,
+        constraint = cardinality_next
+            <= observations.load()?.cardinality_next.saturating_add(MAX_OBSERVATION_GROWTH)
+            @ TokenSwapError::ObservationGrowthTooLarge
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"observations", pool.key().as_ref()],
+        bump = pool.observations_bump,
+        realloc = ObservationBuffer::space(cardinality_next),
+        realloc::payer = payer,
+        realloc::zero = true
+    )]
+    pub observations: AccountLoader<'info, ObservationBuffer>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Observe<'info> {
+    #[account(
+        seeds = [b"pool", pool.mint_a.as_ref(), pool.mint_b.as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        seeds = [b"observations", pool.key().as_ref()],
+        bump = pool.observations_bump
+    )]
+    pub observations: AccountLoader<'info, ObservationBuffer>,
+}
+
+#[derive(Accounts)]
+#[instruction(tick_spacing: u16)]
+pub struct InitializeClmmPool<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ClmmPool::INIT_SPACE,
+        seeds = [
+            b"clmmPool",
+            mint_a.key().as_ref(),
+            mint_b.key().as_ref(),
+            tick_spacing.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub pool: Box<Account<'info, ClmmPool>>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"vaultTokenA", pool.key().as_ref()],
+        bump,
+        token::mint = mint_a,
+        token::authority = vault_auth_a,
+        token::token_program = token_program_a
+    )]
+    pub vault_token_a_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"vaultTokenB", pool.key().as_ref()],
+        bump,
+        token::mint = mint_b,
+        token::authority = vault_auth_b,
+        token::token_program = token_program_b
+    )]
+    pub vault_token_b_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: PDA will be the authority for the vault PDA
+    #[account(
+        seeds = [b"vaultTokenA", pool.key().as_ref()],
+        bump
+    )]
+    pub vault_auth_a: AccountInfo<'info>,
+
+    /// CHECK: PDA will be the authority for the vault PDA
+    #[account(
+        seeds = [b"vaultTokenB", pool.key().as_ref()],
         bump
     )]
     pub vault_auth_b: AccountInfo<'info>,
 
-    pub mint_a: Account<'info, Mint>,
+    // Mints are stored in sorted order so each pair has a single pool address
+    #[account(
+        constraint = mint_a.key() != mint_b.key() @ TokenSwapError::IdenticalMints,
+        constraint = mint_a.key() < mint_b.key() @ TokenSwapError::UnsortedMints
+    )]
+    pub mint_a: Box<InterfaceAccount<'info, Mint>>,
 
-    pub mint_b: Account<'info, Mint>,
+    pub mint_b: Box<InterfaceAccount<'info, Mint>>,
 
-    pub token_program: Program<'info, Token>,
-}
\ No newline at end of file
+    // Like constant-product pools, only the program admin can create these
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ TokenSwapError::UnauthorizedProgramAdmin
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program_a: Interface<'info, TokenInterface>,
+    pub token_program_b: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(start_tick_index: i32)]
+pub struct InitializeTickArray<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<TickArray>(),
+        seeds = [
+            b"tickArray",
+            pool.key().as_ref(),
+            start_tick_index.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub tick_array: AccountLoader<'info, TickArray>,
+
+    pub pool: Account<'info, ClmmPool>,
+
+    // Tick arrays are part of the pool setup, so they are gated the same way
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ TokenSwapError::UnauthorizedProgramAdmin
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tick_lower: i32, tick_upper: i32)]
+pub struct OpenPosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            tick_lower.to_le_bytes().as_ref(),
+            tick_upper.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    pub pool: Account<'info, ClmmPool>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// The tick arrays holding the position bounds, which may be the same account
+#[derive(Accounts)]
+pub struct ModifyPosition<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"clmmPool",
+            pool.mint_a.as_ref(),
+            pool.mint_b.as_ref(),
+            pool.tick_spacing.to_le_bytes().as_ref()
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, ClmmPool>>,
+
+    #[account(
+        mut,
+        has_one = owner @ TokenSwapError::Unauthorized,
+        has_one = pool
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(mut)]
+    pub tick_array_lower: AccountLoader<'info, TickArray>,
+
+    #[account(mut)]
+    pub tick_array_upper: AccountLoader<'info, TickArray>,
+
+    #[account(mut)]
+    pub user_token_account_for_token_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub user_token_account_for_token_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, address = pool.vault_a)]
+    pub vault_token_a_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, address = pool.vault_b)]
+    pub vault_token_b_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = pool.mint_a, mint::token_program = token_program_a)]
+    pub mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(address = pool.mint_b, mint::token_program = token_program_b)]
+    pub mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program_a: Interface<'info, TokenInterface>,
+    pub token_program_b: Interface<'info, TokenInterface>,
+}
+
+// The tick arrays the swap moves through are passed as remaining accounts
+#[derive(Accounts)]
+pub struct ClmmSwap<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"clmmPool",
+            pool.mint_a.as_ref(),
+            pool.mint_b.as_ref(),
+            pool.tick_spacing.to_le_bytes().as_ref()
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, ClmmPool>>,
+
+    #[account(mut)]
+    pub user_token_account_for_token_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub user_token_account_for_token_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, address = pool.vault_a)]
+    pub vault_token_a_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, address = pool.vault_b)]
+    pub vault_token_b_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = pool.mint_a, mint::token_program = token_program_a)]
+    pub mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(address = pool.mint_b, mint::token_program = token_program_b)]
+    pub mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program_a: Interface<'info, TokenInterface>,
+    pub token_program_b: Interface<'info, TokenInterface>,
+}
@@ -1,3 +1,4 @@
+use crate::utils::amm::AmmError;
 use anchor_lang::prelude::*;
 
 #[error_code]
@@ -14,9 +15,104 @@ pub enum TokenSwapError {
     #[msg("Insufficient amount of tokens provided in the liquidity pool")]
     InsufficientLiquidityTokens,
 
-    #[msg("Time Constraint, Can't remove liquidity before 10 days")]
+    #[msg("Time Constraint, Can't remove liquidity before the pool's lockup has passed")]
     TimeConstraint,
 
     #[msg("Slippage Exceeded")]
-    SlippageExceeded
-}
\ No newline at end of file
+    SlippageExceeded,
+
+    #[msg("Pool mints must be different")]
+    IdenticalMints,
+
+    #[msg("Deposit is too small to mint any LP tokens")]
+    InsufficientLiquidityMinted,
+
+    #[msg("Swap fee exceeds the maximum allowed")]
+    InvalidFee,
+
+    #[msg("Only the pool admin can perform this action")]
+    Unauthorized,
+
+    #[msg("Protocol fee exceeds the maximum allowed")]
+    InvalidProtocolFee,
+
+    #[msg("Transaction deadline has passed")]
+    DeadlineExceeded,
+
+    #[msg("Not enough tokens in the pool to fill the swap")]
+    InsufficientPoolReserves,
+
+    #[msg("Swap route accounts are invalid or do not chain")]
+    InvalidRoute,
+
+    #[msg("Amplification coefficient is out of range")]
+    InvalidAmp,
+
+    #[msg("Amplification ramp is too fast or too short")]
+    InvalidAmpRamp,
+
+    #[msg("This action is not supported by the pool curve")]
+    UnsupportedCurve,
+
+    #[msg("Pool weights must each be at least 1% and add up to 100%")]
+    InvalidWeights,
+
+    #[msg("Tick or sqrt price is outside the supported range")]
+    InvalidTickRange,
+
+    #[msg("Tick spacing is out of range or ticks are not aligned to it")]
+    InvalidTickSpacing,
+
+    #[msg("Tick array does not belong to the pool or does not hold the tick")]
+    InvalidTickArray,
+
+    #[msg("Swap needs a tick array that was not passed in")]
+    TickArrayMissing,
+
+    #[msg("Sqrt price limit is on the wrong side of the current price")]
+    InvalidSqrtPriceLimit,
+
+    #[msg("Price observation must be older than the current time")]
+    InvalidObservation,
+
+    #[msg("Observation buffer does not belong to the pool")]
+    InvalidObservationBuffer,
+
+    #[msg("Observation cardinality can only grow, up to the maximum")]
+    InvalidObservationCardinality,
+
+    #[msg("Requested time is older than the oldest observation")]
+    ObservationTooOld,
+
+    #[msg("Pool mints must be passed in ascending order")]
+    UnsortedMints,
+
+    #[msg("Lockup duration can't be negative")]
+    InvalidLockupDuration,
+
+    #[msg("Mints with a transfer hook or a permanent delegate are not supported")]
+    UnsupportedMintExtension,
+
+    #[msg("Only the program admin can perform this action")]
+    UnauthorizedProgramAdmin,
+
+    #[msg("Only the program upgrade authority can initialize the config")]
+    UnauthorizedUpgradeAuthority,
+
+    #[msg("Only the proposed admin can accept the admin role")]
+    UnauthorizedPendingAdmin,
+
+    #[msg("Observation buffer can't grow that much in one call, grow it in steps")]
+    ObservationGrowthTooLarge,
+}
+
+impl From<AmmError> for TokenSwapError {
+    fn from(error: AmmError) -> Self {
+        match error {
+            AmmError::Overflow | AmmError::EmptyReserves => TokenSwapError::CalculationError,
+            AmmError::InsufficientReserves => TokenSwapError::InsufficientPoolReserves,
+            AmmError::InsufficientLpSupply => TokenSwapError::InsufficientLiquidityTokens,
+            AmmError::InvalidTick => TokenSwapError::InvalidTickRange,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::state::curve_type::CurveType;
+use crate::state::swap_direction::SwapDirection;
+use anchor_lang::prelude::*;
+
+// Typed events for indexers, reserves and supply are the values after the action
+
+#[event]
+pub struct PoolCreatedEvent {
+    pub pool: Pubkey,
+    pub admin: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub fee_bps: u16,
+    pub curve_type: CurveType,
+    pub amp: u64,
+    pub weight_a: u64,
+    pub weight_b: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SwapEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub direction: SwapDirection,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub protocol_fee: u64,
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidityAddedEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub lp_tokens_minted: u64,
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+    pub lp_supply: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidityRemovedEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub lp_tokens_burned: u64,
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+    pub lp_supply: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ClmmPoolCreatedEvent {
+    pub pool: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub tick_spacing: u16,
+    pub fee_bps: u16,
+    pub sqrt_price: u128,
+    pub tick_current: i32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ClmmSwapEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub direction: SwapDirection,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub sqrt_price: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionLiquidityEvent {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    // Positive when liquidity was added, negative when it was removed
+    pub liquidity_delta: i128,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub fees_a: u64,
+    pub fees_b: u64,
+    // Pool liquidity in range after the change
+    pub pool_liquidity: u128,
+    pub timestamp: i64,
+}
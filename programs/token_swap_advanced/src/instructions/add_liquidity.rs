@@ -1,36 +1,73 @@
 use crate::contexts::Liquidity;
-use crate::state::liquidity_account::LiquidityAccount;
-use crate::utils::transfer::deposit_to_vault_token_a;
-use crate::utils::transfer::deposit_to_vault_token_b;
+use crate::errors::error::TokenSwapError;
+use crate::events::LiquidityAddedEvent;
+use crate::state::observation::record_observation;
+use crate::state::pool::Pool;
+use crate::state::trade_result::LiquidityResult;
+use crate::utils::deadline::check_deadline;
+use crate::utils::quote::{quote_deposit, DepositQuote};
+use crate::utils::transfer::{deposit_to_vault, inverse_transfer_fee, transfer_fee};
 use anchor_lang::prelude::*;
-use anchor_spl::{
-    associated_token::AssociatedToken,
-    metadata::{
-        create_metadata_accounts_v3, mpl_token_metadata::types::DataV2, CreateMetadataAccountsV3,
-        Metadata as Metaplex,
-    },
-    token::{mint_to, Mint, MintTo, Token, TokenAccount},
-};
-
-pub fn addLiquidity(ctx: Context<Liquidity>, tokenAmount: u64) -> Result<()> {
-    deposit_to_vault_token_a(
+use anchor_spl::token::{mint_to, MintTo};
+use anchor_spl::token_interface::Mint;
+
+pub fn addLiquidity(
+    ctx: Context<Liquidity>,
+    max_amount_a: u64,
+    max_amount_b: u64,
+    min_lp_out: u64,
+    deadline: Option<i64>,
+) -> Result<LiquidityResult> {
+    let clock = Clock::get()?;
+
+    check_deadline(deadline, clock.unix_timestamp)?;
+
+    let (quote, amount_a_sent, amount_b_sent) = quote_deposit_with_transfer_fees(
+        &ctx.accounts.pool,
+        &ctx.accounts.mint_a,
+        &ctx.accounts.mint_b,
+        max_amount_a,
+        max_amount_b,
+        &clock,
+    )?;
+
+    let (amount_a, amount_b, lp_tokens_to_mint) = (quote.amount_a, quote.amount_b, quote.lp_tokens);
+
+    require!(
+        lp_tokens_to_mint > 0,
+        TokenSwapError::InsufficientLiquidityMinted
+    );
+
+    require!(
+        lp_tokens_to_mint >= min_lp_out,
+        TokenSwapError::SlippageExceeded
+    );
+
+    deposit_to_vault(
         &ctx.accounts.user.to_account_info(),
         &ctx.accounts.user_token_account_for_token_a,
         &ctx.accounts.vault_token_a_account,
-        &ctx.accounts.token_program,
-        tokenAmount,
+        &ctx.accounts.mint_a,
+        &ctx.accounts.token_program_a,
+        amount_a_sent,
     )?;
 
-    deposit_to_vault_token_b(
+    deposit_to_vault(
         &ctx.accounts.user.to_account_info(),
         &ctx.accounts.user_token_account_for_token_b,
         &ctx.accounts.vault_token_b_account,
-        &ctx.accounts.token_program,
-        tokenAmount,
+        &ctx.accounts.mint_b,
+        &ctx.accounts.token_program_b,
+        amount_b_sent,
     )?;
 
     // Minting Tokens
-    let signer_seeds: &[&[&[u8]]] = &[&[b"authority", &[ctx.bumps.authority]]];
+    let pool_key = ctx.accounts.pool.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        b"authority",
+        pool_key.as_ref(),
+        &[ctx.accounts.pool.authority_bump],
+    ]];
 
     let cpi_accounts = MintTo {
         mint: ctx.accounts.mint.to_account_info(),
@@ -43,17 +80,93 @@ pub fn addLiquidity(ctx: Context<Liquidity>, tokenAmount: u64) -> Result<()> {
         cpi_accounts,
         signer_seeds,
     );
-    mint_to(cpi_ctx, tokenAmount)?;
-
-    msg!("Minted LP Tokens Successfully");
-
-    let clock = Clock::get()?;
+    mint_to(cpi_ctx, lp_tokens_to_mint)?;
 
     let pda = &mut ctx.accounts.user_pda_account;
-    pda.stakedTokenAmount += tokenAmount;
+    pda.stakedTokenAmount = pda
+        .stakedTokenAmount
+        .checked_add(lp_tokens_to_mint)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
     pda.last_update_time = clock.unix_timestamp;
 
-    msg!("Liquidity Added Successfully");
+    let pool = &mut ctx.accounts.pool;
+    pool.update_oracle(clock.unix_timestamp);
+    record_observation(&ctx.accounts.observations, pool)?;
+    pool.reserve_a = pool
+        .reserve_a
+        .checked_add(amount_a)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+    pool.reserve_b = pool
+        .reserve_b
+        .checked_add(amount_b)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+    // The locked part of the first deposit counts towards the supply but is
+    // never minted, so nobody can redeem it
+    pool.lp_supply = pool
+        .lp_supply
+        .checked_add(lp_tokens_to_mint)
+        .and_then(|lp_supply| lp_supply.checked_add(quote.lp_tokens_locked))
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+
+    emit!(LiquidityAddedEvent {
+        pool: pool.key(),
+        user: ctx.accounts.user.key(),
+        amount_a: amount_a_sent,
+        amount_b: amount_b_sent,
+        lp_tokens_minted: lp_tokens_to_mint,
+        reserve_a: pool.reserve_a,
+        reserve_b: pool.reserve_b,
+        lp_supply: pool.lp_supply,
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(LiquidityResult {
+        amount_a: amount_a_sent,
+        amount_b: amount_b_sent,
+        lp_tokens: lp_tokens_to_mint,
+        reserve_a: pool.reserve_a,
+        reserve_b: pool.reserve_b,
+        lp_supply: pool.lp_supply,
+    })
+}
+
+// Deposit quote for the maximums the user is willing to send, with what the
+// user sends of each token for the quoted amounts to arrive. The vaults
+// receive the deposit less any Token-2022 transfer fee, so it is sized on
+// what can arrive out of the maximums.
+pub fn quote_deposit_with_transfer_fees(
+    pool: &Pool,
+    mint_a: &InterfaceAccount<Mint>,
+    mint_b: &InterfaceAccount<Mint>,
+    max_amount_a: u64,
+    max_amount_b: u64,
+    clock: &Clock,
+) -> Result<(DepositQuote, u64, u64)> {
+    let max_amount_a_in_vault = max_amount_a - transfer_fee(mint_a, max_amount_a, clock.epoch)?;
+    let max_amount_b_in_vault = max_amount_b - transfer_fee(mint_b, max_amount_b, clock.epoch)?;
+
+    // Only deposit what keeps the current price, the rest stays with the user
+    let quote = quote_deposit(
+        &pool.quote_state(clock.unix_timestamp),
+        max_amount_a_in_vault,
+        max_amount_b_in_vault,
+    )
+    .map_err(TokenSwapError::from)?;
+
+    let amount_a_sent = quote
+        .amount_a
+        .checked_add(inverse_transfer_fee(mint_a, quote.amount_a, clock.epoch)?)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+    let amount_b_sent = quote
+        .amount_b
+        .checked_add(inverse_transfer_fee(mint_b, quote.amount_b, clock.epoch)?)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+
+    // The inverse fee can round a unit above the maximum
+    require!(
+        amount_a_sent <= max_amount_a && amount_b_sent <= max_amount_b,
+        TokenSwapError::SlippageExceeded
+    );
 
-    Ok(())
+    Ok((quote, amount_a_sent, amount_b_sent))
 }
@@ -0,0 +1,141 @@
+use crate::contexts::{AcceptPoolAdmin, PoolAdmin};
+use crate::errors::error::TokenSwapError;
+use crate::state::curve_type::CurveType;
+use crate::state::pool::{
+    MAX_AMP, MAX_AMP_CHANGE, MAX_FEE_BPS, MAX_PROTOCOL_FEE_BPS, MIN_AMP, MIN_RAMP_DURATION,
+};
+use anchor_lang::prelude::*;
+
+pub fn set_fee_bps(ctx: Context<PoolAdmin>, fee_bps: u16) -> Result<()> {
+    require!(fee_bps <= MAX_FEE_BPS, TokenSwapError::InvalidFee);
+
+    let pool = &mut ctx.accounts.pool;
+    pool.fee_bps = fee_bps;
+
+    msg!("Swap fee updated to {} bps", fee_bps);
+
+    Ok(())
+}
+
+// Proposes a new pool admin, the current one stays in charge until the new
+// one accepts, so a wrong address can't lock the pool's settings
+pub fn set_admin(ctx: Context<PoolAdmin>, new_admin: Pubkey) -> Result<()> {
+    ctx.accounts.pool.pending_admin = new_admin;
+
+    msg!("Pool admin proposed to {}", new_admin);
+
+    Ok(())
+}
+
+pub fn accept_admin(ctx: Context<AcceptPoolAdmin>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.admin = pool.pending_admin;
+    pool.pending_admin = Pubkey::default();
+
+    msg!("Pool admin transferred to {}", pool.admin);
+
+    Ok(())
+}
+
+// Seconds a deposit has to stay in the pool before it can be removed, 0
+// lets LPs withdraw right away
+pub fn set_lockup_duration(ctx: Context<PoolAdmin>, lockup_duration: i64) -> Result<()> {
+    require!(lockup_duration >= 0, TokenSwapError::InvalidLockupDuration);
+
+    ctx.accounts.pool.lockup_duration = lockup_duration;
+
+    msg!("Lockup updated to {} seconds", lockup_duration);
+
+    Ok(())
+}
+
+pub fn set_protocol_fee(
+    ctx: Context<PoolAdmin>,
+    protocol_fee_bps: u16,
+    treasury: Pubkey,
+) -> Result<()> {
+    require!(
+        protocol_fee_bps <= MAX_PROTOCOL_FEE_BPS,
+        TokenSwapError::InvalidProtocolFee
+    );
+
+    let pool = &mut ctx.accounts.pool;
+    pool.protocol_fee_bps = protocol_fee_bps;
+    pool.treasury = treasury;
+
+    msg!(
+        "Protocol fee updated to {} bps of the swap fee",
+        protocol_fee_bps
+    );
+
+    Ok(())
+}
+
+// Starts moving the amplification of a stable pool from its current value
+// to `target_amp`, reached at `ramp_stop_time`
+pub fn ramp_amp(ctx: Context<PoolAdmin>, target_amp: u64, ramp_stop_time: i64) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+    let pool = &mut ctx.accounts.pool;
+
+    require!(
+        pool.curve_type == CurveType::Stable,
+        TokenSwapError::UnsupportedCurve
+    );
+    require!(
+        (MIN_AMP..=MAX_AMP).contains(&target_amp),
+        TokenSwapError::InvalidAmp
+    );
+
+    let amp = pool.amp(current_time);
+
+    require!(
+        ramp_stop_time >= current_time.saturating_add(MIN_RAMP_DURATION),
+        TokenSwapError::InvalidAmpRamp
+    );
+    require!(
+        target_amp <= amp.saturating_mul(MAX_AMP_CHANGE)
+            && amp <= target_amp.saturating_mul(MAX_AMP_CHANGE),
+        TokenSwapError::InvalidAmpRamp
+    );
+
+    // The oracle sums up to now were earned at the old amplification
+    pool.update_oracle(current_time);
+
+    pool.initial_amp = amp;
+    pool.target_amp = target_amp;
+    pool.ramp_start_time = current_time;
+    pool.ramp_stop_time = ramp_stop_time;
+
+    msg!(
+        "Ramping amplification from {} to {} until {}",
+        amp,
+        target_amp,
+        ramp_stop_time
+    );
+
+    Ok(())
+}
+
+// Freezes the amplification of a stable pool at its current value
+pub fn stop_ramp_amp(ctx: Context<PoolAdmin>) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+    let pool = &mut ctx.accounts.pool;
+
+    require!(
+        pool.curve_type == CurveType::Stable,
+        TokenSwapError::UnsupportedCurve
+    );
+
+    let amp = pool.amp(current_time);
+
+    pool.update_oracle(current_time);
+
+    pool.initial_amp = amp;
+    pool.target_amp = amp;
+    pool.ramp_start_time = current_time;
+    pool.ramp_stop_time = current_time;
+
+    msg!("Amplification ramp stopped at {}", amp);
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use crate::contexts::{InitializeClmmPool, InitializeTickArray};
+use crate::errors::error::TokenSwapError;
+use crate::events::ClmmPoolCreatedEvent;
+use crate::state::clmm_pool::MAX_TICK_SPACING;
+use crate::state::pool::MAX_FEE_BPS;
+use crate::utils::tick_math::{tick_from_sqrt_price, MAX_TICK, MIN_TICK};
+use crate::utils::transfer::check_supported_mint;
+use anchor_lang::prelude::*;
+
+pub fn initialize_clmm_pool(
+    ctx: Context<InitializeClmmPool>,
+    tick_spacing: u16,
+    fee_bps: u16,
+    initial_sqrt_price: u128,
+) -> Result<()> {
+    require!(fee_bps <= MAX_FEE_BPS, TokenSwapError::InvalidFee);
+    check_supported_mint(&ctx.accounts.mint_a)?;
+    check_supported_mint(&ctx.accounts.mint_b)?;
+    require!(
+        tick_spacing > 0 && tick_spacing <= MAX_TICK_SPACING,
+        TokenSwapError::InvalidTickSpacing
+    );
+
+    let tick_current = tick_from_sqrt_price(initial_sqrt_price).map_err(TokenSwapError::from)?;
+
+    let pool = &mut ctx.accounts.pool;
+    pool.mint_a = ctx.accounts.mint_a.key();
+    pool.mint_b = ctx.accounts.mint_b.key();
+    pool.vault_a = ctx.accounts.vault_token_a_account.key();
+    pool.vault_b = ctx.accounts.vault_token_b_account.key();
+    pool.tick_spacing = tick_spacing;
+    pool.fee_bps = fee_bps;
+    pool.liquidity = 0;
+    pool.sqrt_price = initial_sqrt_price;
+    pool.tick_current = tick_current;
+    pool.fee_growth_global_a = 0;
+    pool.fee_growth_global_b = 0;
+    pool.bump = ctx.bumps.pool;
+    pool.vault_a_bump = ctx.bumps.vault_token_a_account;
+    pool.vault_b_bump = ctx.bumps.vault_token_b_account;
+
+    emit!(ClmmPoolCreatedEvent {
+        pool: pool.key(),
+        mint_a: pool.mint_a,
+        mint_b: pool.mint_b,
+        tick_spacing,
+        fee_bps,
+        sqrt_price: initial_sqrt_price,
+        tick_current,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+pub fn initialize_tick_array(
+    ctx: Context<InitializeTickArray>,
+    start_tick_index: i32,
+) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    let span = pool.tick_array_span();
+
+    // Arrays tile the tick range, each one has to overlap it
+    require!(
+        start_tick_index % span == 0,
+        TokenSwapError::InvalidTickSpacing
+    );
+    require!(
+        start_tick_index <= MAX_TICK && start_tick_index + span > MIN_TICK,
+        TokenSwapError::InvalidTickRange
+    );
+
+    let mut tick_array = ctx.accounts.tick_array.load_init()?;
+    tick_array.pool = pool.key();
+    tick_array.start_tick_index = start_tick_index;
+
+    Ok(())
+}
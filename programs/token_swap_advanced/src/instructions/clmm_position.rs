@@ -0,0 +1,334 @@
+use crate::contexts::{ModifyPosition, OpenPosition};
+use crate::errors::error::TokenSwapError;
+use crate::events::PositionLiquidityEvent;
+use crate::state::clmm_pool::ClmmPool;
+use crate::state::tick_array::{Tick, TickArray};
+use crate::state::trade_result::PositionResult;
+use crate::utils::amm::to_u64;
+use crate::utils::clmm::{add_liquidity_delta, fee_growth_inside, fees_earned, position_amounts};
+use crate::utils::tick_math::sqrt_price_from_tick;
+use crate::utils::transfer::{
+    deposit_to_vault, inverse_transfer_fee, transfer_fee, withdraw_from_vault,
+};
+use anchor_lang::prelude::*;
+
+pub fn open_position(ctx: Context<OpenPosition>, tick_lower: i32, tick_upper: i32) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    pool.check_tick(tick_lower)?;
+    pool.check_tick(tick_upper)?;
+    require!(tick_lower < tick_upper, TokenSwapError::InvalidTickRange);
+
+    let position = &mut ctx.accounts.position;
+    position.owner = ctx.accounts.owner.key();
+    position.pool = pool.key();
+    position.tick_lower = tick_lower;
+    position.tick_upper = tick_upper;
+    position.liquidity = 0;
+    position.fee_growth_inside_last_a = 0;
+    position.fee_growth_inside_last_b = 0;
+    position.tokens_owed_a = 0;
+    position.tokens_owed_b = 0;
+    position.bump = ctx.bumps.position;
+
+    Ok(())
+}
+
+pub fn increase_liquidity(
+    ctx: Context<ModifyPosition>,
+    liquidity: u128,
+    max_amount_a: u64,
+    max_amount_b: u64,
+) -> Result<PositionResult> {
+    require!(liquidity > 0, TokenSwapError::InsufficientLiquidityMinted);
+
+    let liquidity_delta =
+        i128::try_from(liquidity).map_err(|_| error!(TokenSwapError::CalculationError))?;
+    let (amount_a, amount_b) = modify_position(ctx.accounts, liquidity_delta)?;
+
+    // The owner sends the transfer fee on top so the vaults receive the full
+    // amounts backing the liquidity
+    let epoch = Clock::get()?.epoch;
+    let amount_a = amount_a
+        .checked_add(inverse_transfer_fee(&ctx.accounts.mint_a, amount_a, epoch)?)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+    let amount_b = amount_b
+        .checked_add(inverse_transfer_fee(&ctx.accounts.mint_b, amount_b, epoch)?)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+
+    require!(
+        amount_a <= max_amount_a && amount_b <= max_amount_b,
+        TokenSwapError::SlippageExceeded
+    );
+
+    let accounts = ctx.accounts;
+    deposit_to_vault(
+        &accounts.owner.to_account_info(),
+        &accounts.user_token_account_for_token_a,
+        &accounts.vault_token_a_account,
+        &accounts.mint_a,
+        &accounts.token_program_a,
+        amount_a,
+    )?;
+    deposit_to_vault(
+        &accounts.owner.to_account_info(),
+        &accounts.user_token_account_for_token_b,
+        &accounts.vault_token_b_account,
+        &accounts.mint_b,
+        &accounts.token_program_b,
+        amount_b,
+    )?;
+
+    emit_position_event(accounts, liquidity_delta, amount_a, amount_b, 0, 0)?;
+
+    Ok(PositionResult {
+        amount_a,
+        amount_b,
+        fees_a: 0,
+        fees_b: 0,
+        liquidity: accounts.position.liquidity,
+    })
+}
+
+// Removes `liquidity` from the position and pays it out together with every
+// fee the position has earned. Passing zero liquidity only collects the fees.
+pub fn decrease_liquidity(
+    ctx: Context<ModifyPosition>,
+    liquidity: u128,
+    min_amount_a: u64,
+    min_amount_b: u64,
+) -> Result<PositionResult> {
+    require!(
+        liquidity <= ctx.accounts.position.liquidity,
+        TokenSwapError::InsufficientLiquidityTokens
+    );
+
+    let liquidity_delta =
+        -i128::try_from(liquidity).map_err(|_| error!(TokenSwapError::CalculationError))?;
+    let (amount_a, amount_b) = modify_position(ctx.accounts, liquidity_delta)?;
+
+    // The minimums hold for what reaches the owner after any transfer fee
+    let epoch = Clock::get()?.epoch;
+    require!(
+        amount_a - transfer_fee(&ctx.accounts.mint_a, amount_a, epoch)? >= min_amount_a
+            && amount_b - transfer_fee(&ctx.accounts.mint_b, amount_b, epoch)? >= min_amount_b,
+        TokenSwapError::SlippageExceeded
+    );
+
+    let accounts = ctx.accounts;
+    let position = &mut accounts.position;
+    let (fees_a, fees_b) = (position.tokens_owed_a, position.tokens_owed_b);
+    position.tokens_owed_a = 0;
+    position.tokens_owed_b = 0;
+
+    let payout_a = amount_a
+        .checked_add(fees_a)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+    let payout_b = amount_b
+        .checked_add(fees_b)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+
+    // The vaults are their own authority
+    withdraw_from_vault(
+        accounts.pool.as_ref(),
+        b"vaultTokenA",
+        &accounts.vault_token_a_account.to_account_info(),
+        &accounts.vault_token_a_account,
+        &accounts.user_token_account_for_token_a,
+        &accounts.mint_a,
+        &accounts.token_program_a,
+        accounts.pool.vault_a_bump,
+        payout_a,
+    )?;
+    withdraw_from_vault(
+        accounts.pool.as_ref(),
+        b"vaultTokenB",
+        &accounts.vault_token_b_account.to_account_info(),
+        &accounts.vault_token_b_account,
+        &accounts.user_token_account_for_token_b,
+        &accounts.mint_b,
+        &accounts.token_program_b,
+        accounts.pool.vault_b_bump,
+        payout_b,
+    )?;
+
+    emit_position_event(
+        accounts,
+        liquidity_delta,
+        amount_a,
+        amount_b,
+        fees_a,
+        fees_b,
+    )?;
+
+    Ok(PositionResult {
+        amount_a,
+        amount_b,
+        fees_a,
+        fees_b,
+        liquidity: accounts.position.liquidity,
+    })
+}
+
+// Applies `liquidity_delta` to the position, its boundary ticks and, when the
+// range holds the current price, the pool. Fees earned so far are credited to
+// the position first. Returns the token amounts backing the change, rounded
+// up for deposits and down for withdrawals.
+fn modify_position(accounts: &mut ModifyPosition, liquidity_delta: i128) -> Result<(u64, u64)> {
+    let pool = &mut accounts.pool;
+    let position = &mut accounts.position;
+    let (tick_lower, tick_upper) = (position.tick_lower, position.tick_upper);
+
+    let (outside_lower, outside_upper) =
+        if accounts.tick_array_lower.key() == accounts.tick_array_upper.key() {
+            let mut tick_array = load_tick_array(&accounts.tick_array_lower, pool)?;
+            let lower = update_tick(&mut tick_array, pool, tick_lower, liquidity_delta, false)?;
+            let upper = update_tick(&mut tick_array, pool, tick_upper, liquidity_delta, true)?;
+            (lower, upper)
+        } else {
+            let mut tick_array_lower = load_tick_array(&accounts.tick_array_lower, pool)?;
+            let mut tick_array_upper = load_tick_array(&accounts.tick_array_upper, pool)?;
+            (
+                update_tick(
+                    &mut tick_array_lower,
+                    pool,
+                    tick_lower,
+                    liquidity_delta,
+                    false,
+                )?,
+                update_tick(
+                    &mut tick_array_upper,
+                    pool,
+                    tick_upper,
+                    liquidity_delta,
+                    true,
+                )?,
+            )
+        };
+
+    let fee_growth_inside_a = fee_growth_inside(
+        pool.tick_current,
+        tick_lower,
+        tick_upper,
+        pool.fee_growth_global_a,
+        outside_lower.0,
+        outside_upper.0,
+    );
+    let fee_growth_inside_b = fee_growth_inside(
+        pool.tick_current,
+        tick_lower,
+        tick_upper,
+        pool.fee_growth_global_b,
+        outside_lower.1,
+        outside_upper.1,
+    );
+
+    let earned_a = fees_earned(
+        fee_growth_inside_a,
+        position.fee_growth_inside_last_a,
+        position.liquidity,
+    )
+    .map_err(TokenSwapError::from)?;
+    let earned_b = fees_earned(
+        fee_growth_inside_b,
+        position.fee_growth_inside_last_b,
+        position.liquidity,
+    )
+    .map_err(TokenSwapError::from)?;
+
+    position.tokens_owed_a = position
+        .tokens_owed_a
+        .checked_add(earned_a)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+    position.tokens_owed_b = position
+        .tokens_owed_b
+        .checked_add(earned_b)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+    position.fee_growth_inside_last_a = fee_growth_inside_a;
+    position.fee_growth_inside_last_b = fee_growth_inside_b;
+    position.liquidity =
+        add_liquidity_delta(position.liquidity, liquidity_delta).map_err(TokenSwapError::from)?;
+
+    if pool.is_active(tick_lower, tick_upper) {
+        pool.liquidity =
+            add_liquidity_delta(pool.liquidity, liquidity_delta).map_err(TokenSwapError::from)?;
+    }
+
+    let (amount_a, amount_b) = position_amounts(
+        pool.sqrt_price,
+        sqrt_price_from_tick(tick_lower).map_err(TokenSwapError::from)?,
+        sqrt_price_from_tick(tick_upper).map_err(TokenSwapError::from)?,
+        liquidity_delta.unsigned_abs(),
+        liquidity_delta > 0,
+    )
+    .map_err(TokenSwapError::from)?;
+
+    Ok((
+        to_u64(amount_a).map_err(TokenSwapError::from)?,
+        to_u64(amount_b).map_err(TokenSwapError::from)?,
+    ))
+}
+
+fn load_tick_array<'a>(
+    loader: &'a AccountLoader<TickArray>,
+    pool: &Account<ClmmPool>,
+) -> Result<std::cell::RefMut<'a, TickArray>> {
+    let tick_array = loader.load_mut()?;
+    require_keys_eq!(
+        tick_array.pool,
+        pool.key(),
+        TokenSwapError::InvalidTickArray
+    );
+
+    Ok(tick_array)
+}
+
+// Updates one boundary tick and returns its fee growth outside (a, b). A tick
+// no position uses any more is cleared after its growth has been read.
+fn update_tick(
+    tick_array: &mut TickArray,
+    pool: &ClmmPool,
+    tick_index: i32,
+    liquidity_delta: i128,
+    is_upper: bool,
+) -> Result<(u128, u128)> {
+    let tick: &mut Tick = tick_array.tick_mut(tick_index, pool.tick_spacing)?;
+    tick.update(
+        tick_index,
+        pool.tick_current,
+        liquidity_delta,
+        pool.fee_growth_global_a,
+        pool.fee_growth_global_b,
+        is_upper,
+    )?;
+
+    let fee_growth_outside = (tick.fee_growth_outside_a, tick.fee_growth_outside_b);
+    if !tick.is_initialized() {
+        tick.clear();
+    }
+
+    Ok(fee_growth_outside)
+}
+
+fn emit_position_event(
+    accounts: &ModifyPosition,
+    liquidity_delta: i128,
+    amount_a: u64,
+    amount_b: u64,
+    fees_a: u64,
+    fees_b: u64,
+) -> Result<()> {
+    emit!(PositionLiquidityEvent {
+        pool: accounts.pool.key(),
+        position: accounts.position.key(),
+        owner: accounts.owner.key(),
+        liquidity_delta,
+        amount_a,
+        amount_b,
+        fees_a,
+        fees_b,
+        pool_liquidity: accounts.pool.liquidity,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,341 @@
+use crate::contexts::ClmmSwap;
+use crate::errors::error::TokenSwapError;
+use crate::events::ClmmSwapEvent;
+use crate::state::swap_direction::SwapDirection;
+use crate::state::tick_array::{TickArray, TICK_ARRAY_SIZE};
+use crate::state::trade_result::ClmmSwapResult;
+use crate::utils::clmm::{add_liquidity_delta, compute_swap_step, fee_growth_delta};
+use crate::utils::deadline::check_deadline;
+use crate::utils::tick_math::{
+    sqrt_price_from_tick, tick_from_sqrt_price, MAX_SQRT_PRICE, MAX_TICK, MIN_SQRT_PRICE, MIN_TICK,
+};
+use crate::utils::transfer::{
+    deposit_to_vault, inverse_transfer_fee, transfer_fee, withdraw_from_vault,
+};
+use anchor_lang::prelude::*;
+
+// Tick arrays are passed in the remaining accounts, writable and in the order
+// the price moves through them: the first one holds the current tick and
+// every next one is the neighbouring array in the swap direction.
+pub const MAX_SWAP_TICK_ARRAYS: usize = 3;
+
+// Price and liquidity as the swap walks across ticks
+struct SwapState {
+    amount_remaining: u64,
+    amount_out: u64,
+    fee: u64,
+    sqrt_price: u128,
+    tick_current: i32,
+    liquidity: u128,
+    fee_growth_global_a: u128,
+    fee_growth_global_b: u128,
+}
+
+pub fn clmm_swap<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ClmmSwap<'info>>,
+    amount_in: u64,
+    min_amount_out: u64,
+    direction: SwapDirection,
+    sqrt_price_limit: Option<u128>,
+    deadline: Option<i64>,
+) -> Result<ClmmSwapResult> {
+    let clock = Clock::get()?;
+    let timestamp = clock.unix_timestamp;
+
+    check_deadline(deadline, timestamp)?;
+
+    let a_to_b = direction.is_a_to_b();
+    let pool = &ctx.accounts.pool;
+    let (mint_in, mint_out) = match direction {
+        SwapDirection::AToB => (&ctx.accounts.mint_a, &ctx.accounts.mint_b),
+        SwapDirection::BToA => (&ctx.accounts.mint_b, &ctx.accounts.mint_a),
+    };
+
+    // Without a limit the swap may move the price as far as the tick range
+    let sqrt_price_limit = sqrt_price_limit.unwrap_or(if a_to_b {
+        MIN_SQRT_PRICE
+    } else {
+        MAX_SQRT_PRICE
+    });
+    require!(
+        if a_to_b {
+            sqrt_price_limit < pool.sqrt_price && sqrt_price_limit >= MIN_SQRT_PRICE
+        } else {
+            sqrt_price_limit > pool.sqrt_price && sqrt_price_limit <= MAX_SQRT_PRICE
+        },
+        TokenSwapError::InvalidSqrtPriceLimit
+    );
+
+    let tick_arrays = load_tick_arrays(
+        ctx.remaining_accounts,
+        &pool.key(),
+        pool.tick_current,
+        pool.tick_spacing,
+        a_to_b,
+    )?;
+
+    // Only what reaches the vault after the input mint's transfer fee is swapped
+    let amount_in_net = amount_in - transfer_fee(mint_in, amount_in, clock.epoch)?;
+    let mut state = SwapState {
+        amount_remaining: amount_in_net,
+        amount_out: 0,
+        fee: 0,
+        sqrt_price: pool.sqrt_price,
+        tick_current: pool.tick_current,
+        liquidity: pool.liquidity,
+        fee_growth_global_a: pool.fee_growth_global_a,
+        fee_growth_global_b: pool.fee_growth_global_b,
+    };
+
+    while state.amount_remaining > 0 && state.sqrt_price != sqrt_price_limit {
+        // Past the supplied arrays the swap settles what it filled so far,
+        // `min_amount_out` guards the user against a fill that is too small
+        let Some((tick_next, tick_array_index)) =
+            next_tick(&tick_arrays, state.tick_current, pool.tick_spacing, a_to_b)?
+        else {
+            break;
+        };
+        let sqrt_price_next_tick = sqrt_price_from_tick(tick_next).map_err(TokenSwapError::from)?;
+        let sqrt_price_target = if a_to_b {
+            sqrt_price_next_tick.max(sqrt_price_limit)
+        } else {
+            sqrt_price_next_tick.min(sqrt_price_limit)
+        };
+
+        let step = compute_swap_step(
+            state.sqrt_price,
+            sqrt_price_target,
+            state.liquidity,
+            state.amount_remaining,
+            pool.fee_bps,
+            a_to_b,
+        )
+        .map_err(TokenSwapError::from)?;
+
+        state.amount_remaining -= step.amount_in + step.fee;
+        state.amount_out = state
+            .amount_out
+            .checked_add(step.amount_out)
+            .ok_or(error!(TokenSwapError::CalculationError))?;
+        state.fee += step.fee;
+
+        // The whole fee goes to the liquidity in range for this step
+        let fee_growth = fee_growth_delta(step.fee, state.liquidity);
+        if a_to_b {
+            state.fee_growth_global_a = state.fee_growth_global_a.wrapping_add(fee_growth);
+        } else {
+            state.fee_growth_global_b = state.fee_growth_global_b.wrapping_add(fee_growth);
+        }
+
+        if step.sqrt_price_next == sqrt_price_next_tick {
+            if let Some(index) = tick_array_index {
+                let mut tick_array = tick_arrays[index].load_mut()?;
+                let tick = tick_array.tick_mut(tick_next, pool.tick_spacing)?;
+                let liquidity_net =
+                    tick.cross(state.fee_growth_global_a, state.fee_growth_global_b);
+
+                state.liquidity = add_liquidity_delta(
+                    state.liquidity,
+                    if a_to_b {
+                        -liquidity_net
+                    } else {
+                        liquidity_net
+                    },
+                )
+                .map_err(TokenSwapError::from)?;
+            }
+
+            // Moving down leaves the crossed tick above the price
+            state.tick_current = if a_to_b { tick_next - 1 } else { tick_next };
+        } else if step.sqrt_price_next != state.sqrt_price {
+            state.tick_current =
+                tick_from_sqrt_price(step.sqrt_price_next).map_err(TokenSwapError::from)?;
+        }
+
+        state.sqrt_price = step.sqrt_price_next;
+    }
+
+    // The user sends the input used plus its transfer fee and receives the
+    // output less the output mint's. The inverse fee can round a unit above
+    // what the user offered, while sending `amount_in` already delivers
+    // `amount_in_net`, which covers any fill.
+    let amount_used = amount_in_net - state.amount_remaining;
+    let amount_in = amount_used
+        .checked_add(inverse_transfer_fee(mint_in, amount_used, clock.epoch)?)
+        .ok_or(error!(TokenSwapError::CalculationError))?
+        .min(amount_in);
+    let amount_out = state.amount_out - transfer_fee(mint_out, state.amount_out, clock.epoch)?;
+
+    require!(
+        amount_out >= min_amount_out,
+        TokenSwapError::SlippageExceeded
+    );
+
+    let accounts = ctx.accounts;
+    let (user_token_in, vault_in, user_token_out, vault_out, vault_seed_out, vault_bump_out) =
+        match direction {
+            SwapDirection::AToB => (
+                &accounts.user_token_account_for_token_a,
+                &accounts.vault_token_a_account,
+                &accounts.user_token_account_for_token_b,
+                &accounts.vault_token_b_account,
+                b"vaultTokenB".as_ref(),
+                accounts.pool.vault_b_bump,
+            ),
+            SwapDirection::BToA => (
+                &accounts.user_token_account_for_token_b,
+                &accounts.vault_token_b_account,
+                &accounts.user_token_account_for_token_a,
+                &accounts.vault_token_a_account,
+                b"vaultTokenA".as_ref(),
+                accounts.pool.vault_a_bump,
+            ),
+        };
+    let (mint_in, token_program_in, mint_out, token_program_out) = match direction {
+        SwapDirection::AToB => (
+            &accounts.mint_a,
+            &accounts.token_program_a,
+            &accounts.mint_b,
+            &accounts.token_program_b,
+        ),
+        SwapDirection::BToA => (
+            &accounts.mint_b,
+            &accounts.token_program_b,
+            &accounts.mint_a,
+            &accounts.token_program_a,
+        ),
+    };
+
+    deposit_to_vault(
+        &accounts.user.to_account_info(),
+        user_token_in,
+        vault_in,
+        mint_in,
+        token_program_in,
+        amount_in,
+    )?;
+
+    // The vaults are their own authority
+    withdraw_from_vault(
+        accounts.pool.as_ref(),
+        vault_seed_out,
+        &vault_out.to_account_info(),
+        vault_out,
+        user_token_out,
+        mint_out,
+        token_program_out,
+        vault_bump_out,
+        state.amount_out,
+    )?;
+
+    let pool = &mut accounts.pool;
+    pool.sqrt_price = state.sqrt_price;
+    pool.tick_current = state.tick_current;
+    pool.liquidity = state.liquidity;
+    pool.fee_growth_global_a = state.fee_growth_global_a;
+    pool.fee_growth_global_b = state.fee_growth_global_b;
+
+    emit!(ClmmSwapEvent {
+        pool: pool.key(),
+        user: accounts.user.key(),
+        direction,
+        amount_in,
+        amount_out,
+        fee: state.fee,
+        sqrt_price: state.sqrt_price,
+        tick_current: state.tick_current,
+        liquidity: state.liquidity,
+        timestamp,
+    });
+
+    Ok(ClmmSwapResult {
+        amount_in,
+        amount_out,
+        fee: state.fee,
+        sqrt_price: state.sqrt_price,
+        tick_current: state.tick_current,
+        liquidity: state.liquidity,
+    })
+}
+
+// Loads the tick arrays and checks they belong to the pool and follow each
+// other in the swap direction, starting with the array of the current tick
+fn load_tick_arrays<'info>(
+    accounts: &'info [AccountInfo<'info>],
+    pool: &Pubkey,
+    tick_current: i32,
+    tick_spacing: u16,
+    a_to_b: bool,
+) -> Result<Vec<AccountLoader<'info, TickArray>>> {
+    require!(
+        !accounts.is_empty() && accounts.len() <= MAX_SWAP_TICK_ARRAYS,
+        TokenSwapError::TickArrayMissing
+    );
+
+    let span = TICK_ARRAY_SIZE as i32 * tick_spacing as i32;
+    let mut tick_arrays = Vec::with_capacity(accounts.len());
+    let mut expected_start: Option<i32> = None;
+
+    for account in accounts {
+        let loader = AccountLoader::<TickArray>::try_from(account)?;
+        let start_tick_index = {
+            let tick_array = loader.load()?;
+            require_keys_eq!(tick_array.pool, *pool, TokenSwapError::InvalidTickArray);
+
+            match expected_start {
+                None => require!(
+                    tick_array.contains(tick_current, tick_spacing),
+                    TokenSwapError::InvalidTickArray
+                ),
+                Some(start) => require!(
+                    tick_array.start_tick_index == start,
+                    TokenSwapError::InvalidTickArray
+                ),
+            }
+
+            tick_array.start_tick_index
+        };
+
+        expected_start = Some(if a_to_b {
+            start_tick_index - span
+        } else {
+            start_tick_index + span
+        });
+        tick_arrays.push(loader);
+    }
+
+    Ok(tick_arrays)
+}
+
+// Next tick the price moves to and, when that tick is initialized, the index
+// of the array holding it. Past the last initialized tick the swap stops at
+// the far end of the last array, since nothing is known beyond it, and once
+// the price is there no tick is left.
+fn next_tick(
+    tick_arrays: &[AccountLoader<TickArray>],
+    tick_current: i32,
+    tick_spacing: u16,
+    a_to_b: bool,
+) -> Result<Option<(i32, Option<usize>)>> {
+    for (index, loader) in tick_arrays.iter().enumerate() {
+        let tick_array = loader.load()?;
+        if let Some(tick) = tick_array.next_initialized_tick(tick_current, tick_spacing, a_to_b) {
+            return Ok(Some((tick, Some(index))));
+        }
+    }
+
+    let last_start = tick_arrays[tick_arrays.len() - 1].load()?.start_tick_index;
+    let edge = if a_to_b {
+        last_start.max(MIN_TICK)
+    } else {
+        (last_start + (TICK_ARRAY_SIZE as i32 - 1) * tick_spacing as i32).min(MAX_TICK)
+    };
+
+    let edge_ahead = if a_to_b {
+        edge <= tick_current
+    } else {
+        edge > tick_current
+    };
+
+    Ok(edge_ahead.then_some((edge, None)))
+}
@@ -0,0 +1,48 @@
+use crate::contexts::CollectProtocolFees;
+use crate::utils::transfer::withdraw_from_vault;
+use anchor_lang::prelude::*;
+
+pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+    let fees_a = ctx.accounts.pool.protocol_fees_a;
+    let fees_b = ctx.accounts.pool.protocol_fees_b;
+
+    if fees_a > 0 {
+        withdraw_from_vault(
+            &ctx.accounts.pool,
+            b"vaultTokenA",
+            &ctx.accounts.vault_auth_a,
+            &ctx.accounts.vault_token_a_account,
+            &ctx.accounts.treasury_token_a_account,
+            &ctx.accounts.mint_a,
+            &ctx.accounts.token_program_a,
+            ctx.accounts.pool.vault_a_bump,
+            fees_a,
+        )?;
+    }
+
+    if fees_b > 0 {
+        withdraw_from_vault(
+            &ctx.accounts.pool,
+            b"vaultTokenB",
+            &ctx.accounts.vault_auth_b,
+            &ctx.accounts.vault_token_b_account,
+            &ctx.accounts.treasury_token_b_account,
+            &ctx.accounts.mint_b,
+            &ctx.accounts.token_program_b,
+            ctx.accounts.pool.vault_b_bump,
+            fees_b,
+        )?;
+    }
+
+    let pool = &mut ctx.accounts.pool;
+    pool.protocol_fees_a = 0;
+    pool.protocol_fees_b = 0;
+
+    msg!(
+        "Collected protocol fees: {} token A, {} token B",
+        fees_a,
+        fees_b
+    );
+
+    Ok(())
+}
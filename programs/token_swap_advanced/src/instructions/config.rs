@@ -0,0 +1,33 @@
+use crate::contexts::{AcceptConfigAdmin, ConfigAdmin, InitializeConfig};
+use anchor_lang::prelude::*;
+
+pub fn initialize_config(ctx: Context<InitializeConfig>, admin: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.admin = admin;
+    config.pending_admin = Pubkey::default();
+    config.bump = ctx.bumps.config;
+
+    msg!("Program admin set to {}", admin);
+
+    Ok(())
+}
+
+// Proposes a new program admin, the current one stays in charge until the
+// new one accepts. Existing pools keep their own admin.
+pub fn set_config_admin(ctx: Context<ConfigAdmin>, new_admin: Pubkey) -> Result<()> {
+    ctx.accounts.config.pending_admin = new_admin;
+
+    msg!("Program admin proposed to {}", new_admin);
+
+    Ok(())
+}
+
+pub fn accept_config_admin(ctx: Context<AcceptConfigAdmin>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.admin = config.pending_admin;
+    config.pending_admin = Pubkey::default();
+
+    msg!("Program admin transferred to {}", config.admin);
+
+    Ok(())
+}
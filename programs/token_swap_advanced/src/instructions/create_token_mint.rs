@@ -11,7 +11,8 @@ use anchor_spl::{
 };
 
 pub fn create_token_mint(ctx: Context<CreateTokenMint>, metadata: TokenMintMetadata) -> Result<()> {
-    let signer_seeds: &[&[&[u8]]] = &[&[b"authority", &[ctx.bumps.authority]]];
+    let pool_key = ctx.accounts.pool.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[b"authority", pool_key.as_ref(), &[ctx.bumps.authority]]];
 
     let token_data = DataV2 {
         name: metadata.name,
@@ -38,5 +39,11 @@ pub fn create_token_mint(ctx: Context<CreateTokenMint>, metadata: TokenMintMetad
     );
 
     create_metadata_accounts_v3(metadata_ctx, token_data, false, true, None)?;
+
+    let pool = &mut ctx.accounts.pool;
+    pool.lp_mint = ctx.accounts.mint.key();
+    pool.lp_mint_bump = ctx.bumps.mint;
+    pool.authority_bump = ctx.bumps.authority;
+
     Ok(())
 }
\ No newline at end of file
@@ -1,14 +1,121 @@
 use crate::contexts::{
-    InitializeUserLiquidityAccount, InitializeVaultTokenA, InitializeVaultTokenB,
+    InitializePool, InitializeUserLiquidityAccount, InitializeVaultTokenA, InitializeVaultTokenB,
 };
+use crate::errors::error::TokenSwapError;
+use crate::events::PoolCreatedEvent;
+use crate::state::curve_type::CurveType;
 use crate::state::liquidity_account::LiquidityAccount;
+use crate::state::observation::record_observation;
+use crate::state::pool::{
+    DEFAULT_LOCKUP_DURATION, MAX_AMP, MAX_FEE_BPS, MIN_AMP, MIN_WEIGHT, WEIGHT_ONE,
+};
+use crate::utils::transfer::check_supported_mint;
 use anchor_lang::prelude::*;
 
+pub fn initialize_pool(
+    ctx: Context<InitializePool>,
+    fee_bps: u16,
+    curve_type: CurveType,
+    amp: u64,
+    weight_a: u64,
+) -> Result<()> {
+    require!(fee_bps <= MAX_FEE_BPS, TokenSwapError::InvalidFee);
+    check_supported_mint(&ctx.accounts.mint_a)?;
+    check_supported_mint(&ctx.accounts.mint_b)?;
+
+    // The amplification only applies to stable pools
+    let amp = match curve_type {
+        CurveType::ConstantProduct | CurveType::Weighted => 0,
+        CurveType::Stable => {
+            require!(
+                (MIN_AMP..=MAX_AMP).contains(&amp),
+                TokenSwapError::InvalidAmp
+            );
+            amp
+        }
+    };
+
+    // Custom weights only apply to weighted pools
+    let weight_a = match curve_type {
+        CurveType::ConstantProduct | CurveType::Stable => WEIGHT_ONE / 2,
+        CurveType::Weighted => {
+            require!(
+                (MIN_WEIGHT..=WEIGHT_ONE - MIN_WEIGHT).contains(&weight_a),
+                TokenSwapError::InvalidWeights
+            );
+            weight_a
+        }
+    };
+    let weight_b = WEIGHT_ONE - weight_a;
+
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let pool = &mut ctx.accounts.pool;
+    pool.admin = ctx.accounts.config.admin;
+    pool.pending_admin = Pubkey::default();
+    pool.mint_a = ctx.accounts.mint_a.key();
+    pool.mint_b = ctx.accounts.mint_b.key();
+    pool.fee_bps = fee_bps;
+    pool.protocol_fee_bps = 0;
+    pool.treasury = ctx.accounts.config.admin;
+    pool.lockup_duration = DEFAULT_LOCKUP_DURATION;
+    pool.curve_type = curve_type;
+    pool.initial_amp = amp;
+    pool.target_amp = amp;
+    pool.ramp_start_time = current_time;
+    pool.ramp_stop_time = current_time;
+    pool.weight_a = weight_a;
+    pool.weight_b = weight_b;
+    pool.lp_supply = 0;
+    pool.reserve_a = 0;
+    pool.reserve_b = 0;
+    pool.protocol_fees_a = 0;
+    pool.protocol_fees_b = 0;
+    pool.price_a_cumulative = 0;
+    pool.price_b_cumulative = 0;
+    pool.liquidity_cumulative = 0;
+    pool.oracle_timestamp = current_time;
+    pool.bump = ctx.bumps.pool;
+    pool.observations_bump = ctx.bumps.observations;
+
+    {
+        let mut buffer = ctx.accounts.observations.load_init()?;
+        buffer.pool = pool.key();
+        buffer.index = 0;
+        buffer.cardinality = 1;
+        buffer.cardinality_next = 1;
+    }
+    record_observation(&ctx.accounts.observations, pool)?;
+
+    emit!(PoolCreatedEvent {
+        pool: pool.key(),
+        admin: pool.admin,
+        mint_a: pool.mint_a,
+        mint_b: pool.mint_b,
+        fee_bps,
+        curve_type,
+        amp,
+        weight_a,
+        weight_b,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
 pub fn initialize_vault_token_a(ctx: Context<InitializeVaultTokenA>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.vault_a = ctx.accounts.vault_token_account.key();
+    pool.vault_a_bump = ctx.bumps.vault_token_account;
+
     Ok(())
 }
 
 pub fn initialize_vault_token_b(ctx: Context<InitializeVaultTokenB>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.vault_b = ctx.accounts.vault_token_account.key();
+    pool.vault_b_bump = ctx.bumps.vault_token_account;
+
     Ok(())
 }
 
@@ -22,4 +129,4 @@ pub fn initialize_user_liquidity_account(
     pda.stakedTokenAmount = 0;
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,6 +1,15 @@
 pub mod initialize;
 pub mod add_liquidity;
 pub mod remove_liquidity;
-pub mod swap_a;
-pub mod swap_b;
-pub mod create_token_mint;
\ No newline at end of file
+pub mod swap;
+pub mod swap_exact_out;
+pub mod swap_route;
+pub mod create_token_mint;
+pub mod admin;
+pub mod config;
+pub mod collect_protocol_fees;
+pub mod quote;
+pub mod clmm_pool;
+pub mod clmm_position;
+pub mod clmm_swap;
+pub mod oracle;
\ No newline at end of file
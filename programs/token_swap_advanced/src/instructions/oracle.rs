@@ -0,0 +1,88 @@
+use crate::contexts::{GrowObservations, Observe, ReadPool};
+use crate::errors::error::TokenSwapError;
+use crate::state::observation::{load_observations, MAX_OBSERVATION_CARDINALITY};
+use crate::state::oracle_result::{CumulativePricesResult, ObserveResult, TwapResult};
+use crate::utils::oracle::twap;
+use anchor_lang::prelude::*;
+
+// Like the quote handlers these only read the pool. An integrator stores one
+// `observe_prices` reading and later passes it to `quote_twap`, or takes the
+// difference of two readings itself with `utils::oracle::twap`.
+
+pub fn observe_prices(ctx: Context<ReadPool>) -> Result<CumulativePricesResult> {
+    let timestamp = Clock::get()?.unix_timestamp;
+    let cumulatives = ctx.accounts.pool.cumulatives(timestamp);
+
+    Ok(CumulativePricesResult {
+        price_a_cumulative: cumulatives.price_a,
+        price_b_cumulative: cumulatives.price_b,
+        liquidity_cumulative: cumulatives.liquidity,
+        timestamp,
+    })
+}
+
+// Average prices from an earlier `observe_prices` reading until now
+pub fn quote_twap(
+    ctx: Context<ReadPool>,
+    price_a_cumulative_start: u128,
+    price_b_cumulative_start: u128,
+    timestamp_start: i64,
+) -> Result<TwapResult> {
+    let timestamp = Clock::get()?.unix_timestamp;
+
+    require!(
+        timestamp_start < timestamp,
+        TokenSwapError::InvalidObservation
+    );
+
+    let cumulatives = ctx.accounts.pool.cumulatives(timestamp);
+    let elapsed = (timestamp - timestamp_start) as u64;
+
+    Ok(TwapResult {
+        price_a: twap(price_a_cumulative_start, cumulatives.price_a, elapsed)
+            .map_err(TokenSwapError::from)?,
+        price_b: twap(price_b_cumulative_start, cumulatives.price_b, elapsed)
+            .map_err(TokenSwapError::from)?,
+        elapsed,
+    })
+}
+
+// The account has already been resized to `cardinality_next` slots by the
+// time this runs; the new slots are used once the buffer next wraps
+pub fn grow_observations(ctx: Context<GrowObservations>, cardinality_next: u16) -> Result<()> {
+    let mut buffer = ctx.accounts.observations.load_mut()?;
+
+    require!(
+        cardinality_next > buffer.cardinality_next
+            && cardinality_next <= MAX_OBSERVATION_CARDINALITY,
+        TokenSwapError::InvalidObservationCardinality
+    );
+
+    buffer.cardinality_next = cardinality_next;
+
+    Ok(())
+}
+
+// Oracle sums `seconds_ago` seconds back for every entry, so a TWAP over any
+// window the buffer covers takes a single call
+pub fn observe(ctx: Context<Observe>, seconds_agos: Vec<u32>) -> Result<ObserveResult> {
+    let timestamp = Clock::get()?.unix_timestamp;
+    let pool = &ctx.accounts.pool;
+    let (buffer, observations) = load_observations(&ctx.accounts.observations, &pool.key())?;
+
+    let mut result = ObserveResult {
+        price_a_cumulatives: Vec::with_capacity(seconds_agos.len()),
+        price_b_cumulatives: Vec::with_capacity(seconds_agos.len()),
+        liquidity_cumulatives: Vec::with_capacity(seconds_agos.len()),
+    };
+
+    for seconds_ago in seconds_agos {
+        let cumulatives = buffer.observe(&observations, pool, timestamp - seconds_ago as i64)?;
+
+        result.price_a_cumulatives.push(cumulatives.price_a);
+        result.price_b_cumulatives.push(cumulatives.price_b);
+        result.liquidity_cumulatives.push(cumulatives.liquidity);
+    }
+
+    Ok(result)
+}
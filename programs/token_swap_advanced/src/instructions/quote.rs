@@ -0,0 +1,80 @@
+use crate::contexts::QuotePool;
+use crate::instructions::add_liquidity::quote_deposit_with_transfer_fees;
+use crate::instructions::remove_liquidity::quote_withdraw_with_transfer_fees;
+use crate::instructions::swap::quote_swap_with_transfer_fees;
+use crate::state::quote_result::{DepositQuoteResult, SwapQuoteResult, WithdrawQuoteResult};
+use crate::state::swap_direction::SwapDirection;
+use anchor_lang::prelude::*;
+
+// These handlers never move tokens. Anchor writes the returned struct with
+// `set_return_data`, so other programs can CPI for a price and clients can
+// simulate them to get the amounts the swap and liquidity handlers would use.
+// The pool's reserves track the vault balances net of accrued protocol fees,
+// which is exactly what the handlers price against, and the amounts are
+// what leaves and reaches the user after any Token-2022 transfer fee.
+
+pub fn quote_swap(
+    ctx: Context<QuotePool>,
+    amount_in: u64,
+    direction: SwapDirection,
+) -> Result<SwapQuoteResult> {
+    let accounts = &ctx.accounts;
+    let (mint_in, mint_out) = match direction {
+        SwapDirection::AToB => (&accounts.mint_a, &accounts.mint_b),
+        SwapDirection::BToA => (&accounts.mint_b, &accounts.mint_a),
+    };
+
+    let (quote, _, transfer_fee_out) = quote_swap_with_transfer_fees(
+        &accounts.pool,
+        mint_in,
+        mint_out,
+        amount_in,
+        direction,
+        &Clock::get()?,
+    )?;
+
+    Ok(SwapQuoteResult {
+        amount_in,
+        amount_out: quote.amount_out - transfer_fee_out,
+        ..quote.into()
+    })
+}
+
+pub fn quote_add_liquidity(
+    ctx: Context<QuotePool>,
+    max_amount_a: u64,
+    max_amount_b: u64,
+) -> Result<DepositQuoteResult> {
+    let (quote, amount_a_sent, amount_b_sent) = quote_deposit_with_transfer_fees(
+        &ctx.accounts.pool,
+        &ctx.accounts.mint_a,
+        &ctx.accounts.mint_b,
+        max_amount_a,
+        max_amount_b,
+        &Clock::get()?,
+    )?;
+
+    Ok(DepositQuoteResult {
+        amount_a: amount_a_sent,
+        amount_b: amount_b_sent,
+        lp_tokens: quote.lp_tokens,
+    })
+}
+
+pub fn quote_remove_liquidity(
+    ctx: Context<QuotePool>,
+    lp_amount: u64,
+) -> Result<WithdrawQuoteResult> {
+    let (_, amount_a_received, amount_b_received) = quote_withdraw_with_transfer_fees(
+        &ctx.accounts.pool,
+        &ctx.accounts.mint_a,
+        &ctx.accounts.mint_b,
+        lp_amount,
+        &Clock::get()?,
+    )?;
+
+    Ok(WithdrawQuoteResult {
+        amount_a: amount_a_received,
+        amount_b: amount_b_received,
+    })
+}
@@ -1,48 +1,149 @@
 use crate::contexts::Liquidity;
 use crate::errors::error::TokenSwapError;
-use crate::state::liquidity_account::LiquidityAccount;
-use crate::utils::transfer::send_token_a_from_token_vault_to_user;
-use crate::utils::transfer::send_token_b_from_token_vault_to_user;
+use crate::events::LiquidityRemovedEvent;
+use crate::state::observation::record_observation;
+use crate::state::pool::Pool;
+use crate::state::trade_result::LiquidityResult;
+use crate::utils::deadline::check_deadline;
+use crate::utils::quote::{quote_withdraw, WithdrawQuote};
+use crate::utils::transfer::{transfer_fee, withdraw_from_vault};
 use anchor_lang::prelude::*;
+use anchor_spl::token::{burn, Burn};
+use anchor_spl::token_interface::Mint;
 
-pub fn removeLiquidity(ctx: Context<Liquidity>, tokenAmount: u64) -> Result<()> {
+pub fn removeLiquidity(
+    ctx: Context<Liquidity>,
+    lp_amount: u64,
+    min_amount_a: u64,
+    min_amount_b: u64,
+    deadline: Option<i64>,
+) -> Result<LiquidityResult> {
     let pda_account_time = &ctx.accounts.user_pda_account;
 
     let current_time = Clock::get()?;
 
+    check_deadline(deadline, current_time.unix_timestamp)?;
+
     require!(
-        current_time.unix_timestamp - pda_account_time.last_update_time >= 100,
+        current_time.unix_timestamp - pda_account_time.last_update_time
+            >= ctx.accounts.pool.lockup_duration,
         TokenSwapError::TimeConstraint
     );
 
-    let userProvidedLiquidity = &mut ctx.accounts.user_pda_account;
-
+    // The LP token balance is the claim on the pool, not the per-user counter
     require!(
-        userProvidedLiquidity.stakedTokenAmount >= tokenAmount,
+        ctx.accounts.destination.amount >= lp_amount,
         TokenSwapError::InsufficientLiquidityTokens
     );
 
-    send_token_a_from_token_vault_to_user(
+    let (quote, amount_a_received, amount_b_received) = quote_withdraw_with_transfer_fees(
+        &ctx.accounts.pool,
         &ctx.accounts.mint_a,
+        &ctx.accounts.mint_b,
+        lp_amount,
+        &current_time,
+    )?;
+
+    let (amount_a, amount_b) = (quote.amount_a, quote.amount_b);
+
+    // The minimums apply to what arrives after any Token-2022 transfer fee
+    require!(
+        amount_a_received >= min_amount_a && amount_b_received >= min_amount_b,
+        TokenSwapError::SlippageExceeded
+    );
+
+    // Burning LP Tokens
+    let cpi_accounts = Burn {
+        mint: ctx.accounts.mint.to_account_info(),
+        from: ctx.accounts.destination.to_account_info(),
+        authority: ctx.accounts.user.to_account_info(),
+    };
+
+    let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+    burn(cpi_ctx, lp_amount)?;
+
+    withdraw_from_vault(
+        &ctx.accounts.pool,
+        b"vaultTokenA",
         &ctx.accounts.vault_auth_a,
         &ctx.accounts.vault_token_a_account,
         &ctx.accounts.user_token_account_for_token_a,
-        &ctx.accounts.token_program,
-        ctx.bumps.vault_auth_a,
-        tokenAmount,
+        &ctx.accounts.mint_a,
+        &ctx.accounts.token_program_a,
+        ctx.accounts.pool.vault_a_bump,
+        amount_a,
     )?;
 
-    send_token_b_from_token_vault_to_user(
-        &ctx.accounts.mint_b,
+    withdraw_from_vault(
+        &ctx.accounts.pool,
+        b"vaultTokenB",
         &ctx.accounts.vault_auth_b,
         &ctx.accounts.vault_token_b_account,
         &ctx.accounts.user_token_account_for_token_b,
-        &ctx.accounts.token_program,
-        ctx.bumps.vault_auth_b,
-        tokenAmount,
+        &ctx.accounts.mint_b,
+        &ctx.accounts.token_program_b,
+        ctx.accounts.pool.vault_b_bump,
+        amount_b,
     )?;
 
-    userProvidedLiquidity.stakedTokenAmount -= tokenAmount;
+    // LP tokens may have been received from another wallet
+    let user_provided_liquidity = &mut ctx.accounts.user_pda_account;
+    user_provided_liquidity.stakedTokenAmount = user_provided_liquidity
+        .stakedTokenAmount
+        .saturating_sub(lp_amount);
+
+    let pool = &mut ctx.accounts.pool;
+    pool.update_oracle(current_time.unix_timestamp);
+    record_observation(&ctx.accounts.observations, pool)?;
+    pool.lp_supply = pool
+        .lp_supply
+        .checked_sub(lp_amount)
+        .ok_or(error!(TokenSwapError::InsufficientLiquidityTokens))?;
+    pool.reserve_a = pool
+        .reserve_a
+        .checked_sub(amount_a)
+        .ok_or(error!(TokenSwapError::InsufficientTokenA))?;
+    pool.reserve_b = pool
+        .reserve_b
+        .checked_sub(amount_b)
+        .ok_or(error!(TokenSwapError::InsufficientTokenB))?;
+
+    emit!(LiquidityRemovedEvent {
+        pool: pool.key(),
+        user: ctx.accounts.user.key(),
+        amount_a: amount_a_received,
+        amount_b: amount_b_received,
+        lp_tokens_burned: lp_amount,
+        reserve_a: pool.reserve_a,
+        reserve_b: pool.reserve_b,
+        lp_supply: pool.lp_supply,
+        timestamp: current_time.unix_timestamp,
+    });
+
+    Ok(LiquidityResult {
+        amount_a: amount_a_received,
+        amount_b: amount_b_received,
+        lp_tokens: lp_amount,
+        reserve_a: pool.reserve_a,
+        reserve_b: pool.reserve_b,
+        lp_supply: pool.lp_supply,
+    })
+}
+
+// Withdrawal quote for `lp_amount`, with what arrives of each token after
+// any Token-2022 transfer fee
+pub fn quote_withdraw_with_transfer_fees(
+    pool: &Pool,
+    mint_a: &InterfaceAccount<Mint>,
+    mint_b: &InterfaceAccount<Mint>,
+    lp_amount: u64,
+    clock: &Clock,
+) -> Result<(WithdrawQuote, u64, u64)> {
+    let quote = quote_withdraw(&pool.quote_state(clock.unix_timestamp), lp_amount)
+        .map_err(TokenSwapError::from)?;
+
+    let amount_a_received = quote.amount_a - transfer_fee(mint_a, quote.amount_a, clock.epoch)?;
+    let amount_b_received = quote.amount_b - transfer_fee(mint_b, quote.amount_b, clock.epoch)?;
 
-    Ok(())
-}
\ No newline at end of file
+    Ok((quote, amount_a_received, amount_b_received))
+}
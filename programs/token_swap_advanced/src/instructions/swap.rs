@@ -0,0 +1,201 @@
+use crate::contexts::TokenSwap;
+use crate::errors::error::TokenSwapError;
+use crate::events::SwapEvent;
+use crate::state::observation::record_observation;
+use crate::state::pool::Pool;
+use crate::state::swap_direction::SwapDirection;
+use crate::state::trade_result::SwapResult;
+use crate::utils::deadline::check_deadline;
+use crate::utils::quote::{quote_swap_exact_in, SwapQuote};
+use crate::utils::transfer::{deposit_to_vault, transfer_fee, withdraw_from_vault};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+pub fn swap(
+    ctx: Context<TokenSwap>,
+    amount_in: u64,
+    min_amount_out: u64,
+    direction: SwapDirection,
+    deadline: Option<i64>,
+) -> Result<SwapResult> {
+    let clock = Clock::get()?;
+    let current_time = clock.unix_timestamp;
+
+    check_deadline(deadline, current_time)?;
+
+    let (mint_in, mint_out) = swap_mints(ctx.accounts, direction);
+    let (quote, transfer_fee_in, transfer_fee_out) = quote_swap_with_transfer_fees(
+        &ctx.accounts.pool,
+        mint_in,
+        mint_out,
+        amount_in,
+        direction,
+        &clock,
+    )?;
+
+    // Slippage is checked on what the user is left with after the output fee
+    require!(
+        quote.amount_out - transfer_fee_out >= min_amount_out,
+        TokenSwapError::SlippageExceeded
+    );
+
+    settle_swap(
+        ctx.accounts,
+        direction,
+        &quote,
+        transfer_fee_in,
+        transfer_fee_out,
+    )
+}
+
+// Quote for `amount_in` leaving the user, with the transfer fees of the input
+// and the output. A Token-2022 transfer fee on the input is withheld before
+// the tokens reach the vault, so the curve only prices what arrives. The LP
+// part of the swap fee stays in the vault as part of the input reserve.
+pub fn quote_swap_with_transfer_fees(
+    pool: &Pool,
+    mint_in: &InterfaceAccount<Mint>,
+    mint_out: &InterfaceAccount<Mint>,
+    amount_in: u64,
+    direction: SwapDirection,
+    clock: &Clock,
+) -> Result<(SwapQuote, u64, u64)> {
+    let transfer_fee_in = transfer_fee(mint_in, amount_in, clock.epoch)?;
+
+    let quote = quote_swap_exact_in(
+        &pool.quote_state(clock.unix_timestamp),
+        amount_in - transfer_fee_in,
+        direction.is_a_to_b(),
+    )
+    .map_err(TokenSwapError::from)?;
+
+    let transfer_fee_out = transfer_fee(mint_out, quote.amount_out, clock.epoch)?;
+
+    Ok((quote, transfer_fee_in, transfer_fee_out))
+}
+
+// Mints of the input and the output token
+pub fn swap_mints<'a, 'info>(
+    accounts: &'a TokenSwap<'info>,
+    direction: SwapDirection,
+) -> (
+    &'a InterfaceAccount<'info, Mint>,
+    &'a InterfaceAccount<'info, Mint>,
+) {
+    match direction {
+        SwapDirection::AToB => (&accounts.mint_a, &accounts.mint_b),
+        SwapDirection::BToA => (&accounts.mint_b, &accounts.mint_a),
+    }
+}
+
+// Moves the quoted input plus its transfer fee from the user to the input
+// vault and the quoted output from the output vault to the user, then books
+// both legs against the pool reserves. The reserves only move by the quote,
+// the transfer fees are what the token program withholds on the way.
+pub fn settle_swap(
+    accounts: &mut TokenSwap,
+    direction: SwapDirection,
+    quote: &SwapQuote,
+    transfer_fee_in: u64,
+    transfer_fee_out: u64,
+) -> Result<SwapResult> {
+    let protocol_fee = quote.protocol_fee;
+    // What leaves the user's account and what arrives in it
+    let amount_in = quote
+        .amount_in
+        .checked_add(transfer_fee_in)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+    let amount_out = quote.amount_out - transfer_fee_out;
+
+    let (
+        user_token_in,
+        vault_in,
+        user_token_out,
+        vault_out,
+        vault_auth_out,
+        vault_seed_out,
+        vault_bump_out,
+    ) = match direction {
+        SwapDirection::AToB => (
+            &accounts.user_token_account_for_token_a,
+            &accounts.vault_token_a_account,
+            &accounts.user_token_account_for_token_b,
+            &accounts.vault_token_b_account,
+            &accounts.vault_auth_b,
+            b"vaultTokenB".as_ref(),
+            accounts.pool.vault_b_bump,
+        ),
+        SwapDirection::BToA => (
+            &accounts.user_token_account_for_token_b,
+            &accounts.vault_token_b_account,
+            &accounts.user_token_account_for_token_a,
+            &accounts.vault_token_a_account,
+            &accounts.vault_auth_a,
+            b"vaultTokenA".as_ref(),
+            accounts.pool.vault_a_bump,
+        ),
+    };
+    let (mint_in, token_program_in, mint_out, token_program_out) = match direction {
+        SwapDirection::AToB => (
+            &accounts.mint_a,
+            &accounts.token_program_a,
+            &accounts.mint_b,
+            &accounts.token_program_b,
+        ),
+        SwapDirection::BToA => (
+            &accounts.mint_b,
+            &accounts.token_program_b,
+            &accounts.mint_a,
+            &accounts.token_program_a,
+        ),
+    };
+
+    deposit_to_vault(
+        &accounts.user.to_account_info(),
+        user_token_in,
+        vault_in,
+        mint_in,
+        token_program_in,
+        amount_in,
+    )?;
+
+    withdraw_from_vault(
+        &accounts.pool,
+        vault_seed_out,
+        vault_auth_out,
+        vault_out,
+        user_token_out,
+        mint_out,
+        token_program_out,
+        vault_bump_out,
+        quote.amount_out,
+    )?;
+
+    let timestamp = Clock::get()?.unix_timestamp;
+
+    accounts.pool.update_oracle(timestamp);
+    record_observation(&accounts.observations, &accounts.pool)?;
+    accounts.pool.record_swap(direction, quote)?;
+
+    emit!(SwapEvent {
+        pool: accounts.pool.key(),
+        user: accounts.user.key(),
+        direction,
+        amount_in,
+        amount_out,
+        fee: quote.fee,
+        protocol_fee,
+        reserve_a: accounts.pool.reserve_a,
+        reserve_b: accounts.pool.reserve_b,
+        timestamp,
+    });
+
+    Ok(SwapResult {
+        amount_in,
+        amount_out,
+        fee: quote.fee,
+        protocol_fee,
+        reserve_a: accounts.pool.reserve_a,
+        reserve_b: accounts.pool.reserve_b,
+    })
+}
@@ -0,0 +1,55 @@
+use crate::contexts::TokenSwap;
+use crate::errors::error::TokenSwapError;
+use crate::instructions::swap::{settle_swap, swap_mints};
+use crate::state::swap_direction::SwapDirection;
+use crate::state::trade_result::SwapResult;
+use crate::utils::deadline::check_deadline;
+use crate::utils::quote::quote_swap_exact_out;
+use crate::utils::transfer::inverse_transfer_fee;
+use anchor_lang::prelude::*;
+
+pub fn swap_exact_out(
+    ctx: Context<TokenSwap>,
+    amount_out: u64,
+    max_amount_in: u64,
+    direction: SwapDirection,
+    deadline: Option<i64>,
+) -> Result<SwapResult> {
+    let clock = Clock::get()?;
+    let current_time = clock.unix_timestamp;
+
+    check_deadline(deadline, current_time)?;
+
+    // The pool pays out enough for `amount_out` to arrive after the output
+    // mint's transfer fee, and the user sends enough for the quoted input
+    // to arrive after the input mint's
+    let (mint_in, mint_out) = swap_mints(ctx.accounts, direction);
+    let transfer_fee_out = inverse_transfer_fee(mint_out, amount_out, clock.epoch)?;
+    let amount_out_with_fee = amount_out
+        .checked_add(transfer_fee_out)
+        .ok_or(error!(TokenSwapError::CalculationError))?;
+
+    let quote = quote_swap_exact_out(
+        &ctx.accounts.pool.quote_state(current_time),
+        amount_out_with_fee,
+        direction.is_a_to_b(),
+    )
+    .map_err(TokenSwapError::from)?;
+
+    let transfer_fee_in = inverse_transfer_fee(mint_in, quote.amount_in, clock.epoch)?;
+    require!(
+        quote
+            .amount_in
+            .checked_add(transfer_fee_in)
+            .is_some_and(|amount_in| amount_in <= max_amount_in),
+        TokenSwapError::SlippageExceeded
+    );
+
+    settle_swap(
+        ctx.accounts,
+        direction,
+        &quote,
+        transfer_fee_in,
+        transfer_fee_out,
+    )
+}
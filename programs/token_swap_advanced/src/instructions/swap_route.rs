@@ -0,0 +1,219 @@
+use crate::contexts::SwapRoute;
+use crate::errors::error::TokenSwapError;
+use crate::events::SwapEvent;
+use crate::state::observation::{record_observation, ObservationBuffer};
+use crate::state::pool::Pool;
+use crate::state::swap_direction::SwapDirection;
+use crate::state::trade_result::{RouteHopResult, RouteSwapResult};
+use crate::utils::deadline::check_deadline;
+use crate::utils::quote::{quote_swap_exact_in, SwapQuote};
+use crate::utils::transfer::{deposit_to_vault, transfer_fee, withdraw_from_vault};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+// Every hop is passed in the remaining accounts as [pool, vault_in, vault_out,
+// observations, mint_out, token_program_out], the first four writable. The
+// vaults are their own authority, so `vault_out` also signs for the transfer
+// out of the pool, which goes through the program owning `mint_out`.
+pub const ROUTE_HOP_ACCOUNTS: usize = 6;
+pub const MAX_ROUTE_HOPS: usize = 4;
+
+struct RouteHop<'info> {
+    pool: Account<'info, Pool>,
+    direction: SwapDirection,
+    vault_in: InterfaceAccount<'info, TokenAccount>,
+    vault_out: InterfaceAccount<'info, TokenAccount>,
+    observations: AccountLoader<'info, ObservationBuffer>,
+    mint_out: InterfaceAccount<'info, Mint>,
+    token_program_out: Interface<'info, TokenInterface>,
+}
+
+pub fn swap_route<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SwapRoute<'info>>,
+    amount_in: u64,
+    min_amount_out: u64,
+    deadline: Option<i64>,
+) -> Result<RouteSwapResult> {
+    let clock = Clock::get()?;
+    let timestamp = clock.unix_timestamp;
+
+    check_deadline(deadline, timestamp)?;
+
+    let remaining_accounts = ctx.remaining_accounts;
+    let hop_count = remaining_accounts.len() / ROUTE_HOP_ACCOUNTS;
+    require!(
+        hop_count > 0
+            && hop_count <= MAX_ROUTE_HOPS
+            && hop_count * ROUTE_HOP_ACCOUNTS == remaining_accounts.len(),
+        TokenSwapError::InvalidRoute
+    );
+
+    let mut hops = remaining_accounts
+        .chunks(ROUTE_HOP_ACCOUNTS)
+        .map(load_hop)
+        .collect::<Result<Vec<_>>>()?;
+
+    // A pool loaded twice would be written back from a stale copy
+    for (index, hop) in hops.iter().enumerate() {
+        require!(
+            hops[..index]
+                .iter()
+                .all(|previous| previous.pool.key() != hop.pool.key()),
+            TokenSwapError::InvalidRoute
+        );
+    }
+
+    // Each hop has to pay out the token the next one takes in
+    require_keys_eq!(
+        hops[0].vault_in.mint,
+        ctx.accounts.user_source_token_account.mint,
+        TokenSwapError::InvalidRoute
+    );
+    for pair in hops.windows(2) {
+        require_keys_eq!(
+            pair[0].vault_out.mint,
+            pair[1].vault_in.mint,
+            TokenSwapError::InvalidRoute
+        );
+    }
+    require_keys_eq!(
+        hops[hops.len() - 1].vault_out.mint,
+        ctx.accounts.user_destination_token_account.mint,
+        TokenSwapError::InvalidRoute
+    );
+
+    // Price the whole route before moving any tokens. Every transfer, into
+    // the first pool, between pools and out to the user, loses the transfer
+    // fee of its mint, so each hop only prices what reaches its vault.
+    let mut amount = amount_in - transfer_fee(&ctx.accounts.source_mint, amount_in, clock.epoch)?;
+    let mut quotes: Vec<SwapQuote> = Vec::with_capacity(hop_count);
+    // What reaches the next vault or the user from each hop
+    let mut amounts_received: Vec<u64> = Vec::with_capacity(hop_count);
+    for hop in &hops {
+        let quote = quote_swap_exact_in(
+            &hop.pool.quote_state(timestamp),
+            amount,
+            hop.direction.is_a_to_b(),
+        )
+        .map_err(TokenSwapError::from)?;
+        amount = quote.amount_out - transfer_fee(&hop.mint_out, quote.amount_out, clock.epoch)?;
+        quotes.push(quote);
+        amounts_received.push(amount);
+    }
+
+    require!(amount >= min_amount_out, TokenSwapError::SlippageExceeded);
+
+    deposit_to_vault(
+        &ctx.accounts.user.to_account_info(),
+        &ctx.accounts.user_source_token_account,
+        &hops[0].vault_in,
+        &ctx.accounts.source_mint,
+        &ctx.accounts.source_token_program,
+        amount_in,
+    )?;
+
+    // Intermediate amounts go straight into the next pool's input vault
+    for index in 0..hops.len() {
+        let hop = &hops[index];
+        let destination = match hops.get(index + 1) {
+            Some(next) => &next.vault_in,
+            None => &ctx.accounts.user_destination_token_account,
+        };
+        let (vault_seed, vault_bump) = match hop.direction {
+            SwapDirection::AToB => (b"vaultTokenB".as_ref(), hop.pool.vault_b_bump),
+            SwapDirection::BToA => (b"vaultTokenA".as_ref(), hop.pool.vault_a_bump),
+        };
+
+        withdraw_from_vault(
+            &hop.pool,
+            vault_seed,
+            &hop.vault_out.to_account_info(),
+            &hop.vault_out,
+            destination,
+            &hop.mint_out,
+            &hop.token_program_out,
+            vault_bump,
+            quotes[index].amount_out,
+        )?;
+    }
+
+    let mut hop_results: Vec<RouteHopResult> = Vec::with_capacity(hop_count);
+    for (index, hop) in hops.iter_mut().enumerate() {
+        let quote = &quotes[index];
+        hop.pool.update_oracle(timestamp);
+        record_observation(&hop.observations, &hop.pool)?;
+        hop.pool.record_swap(hop.direction, quote)?;
+        hop.pool.exit(&crate::ID)?;
+
+        // Like a single swap, the input is what was sent to the pool, by the
+        // user for the first hop and by the previous pool after that
+        let hop_result = RouteHopResult {
+            pool: hop.pool.key(),
+            amount_in: match index {
+                0 => amount_in,
+                _ => quotes[index - 1].amount_out,
+            },
+            amount_out: amounts_received[index],
+            fee: quote.fee,
+            protocol_fee: quote.protocol_fee,
+            reserve_a: hop.pool.reserve_a,
+            reserve_b: hop.pool.reserve_b,
+        };
+
+        emit!(SwapEvent {
+            pool: hop_result.pool,
+            user: ctx.accounts.user.key(),
+            direction: hop.direction,
+            amount_in: hop_result.amount_in,
+            amount_out: hop_result.amount_out,
+            fee: hop_result.fee,
+            protocol_fee: hop_result.protocol_fee,
+            reserve_a: hop_result.reserve_a,
+            reserve_b: hop_result.reserve_b,
+            timestamp,
+        });
+
+        hop_results.push(hop_result);
+    }
+
+    Ok(RouteSwapResult {
+        amount_in,
+        amount_out: amount,
+        hops: hop_results,
+    })
+}
+
+// Loads one hop and infers its direction from which pool vault is the input
+fn load_hop<'info>(accounts: &'info [AccountInfo<'info>]) -> Result<RouteHop<'info>> {
+    let pool = Account::<Pool>::try_from(&accounts[0])?;
+    let vault_in_key = accounts[1].key();
+    let vault_out_key = accounts[2].key();
+
+    let direction = if vault_in_key == pool.vault_a && vault_out_key == pool.vault_b {
+        SwapDirection::AToB
+    } else if vault_in_key == pool.vault_b && vault_out_key == pool.vault_a {
+        SwapDirection::BToA
+    } else {
+        return err!(TokenSwapError::InvalidRoute);
+    };
+
+    let vault_out = InterfaceAccount::<TokenAccount>::try_from(&accounts[2])?;
+    let mint_out = InterfaceAccount::<Mint>::try_from(&accounts[4])?;
+    let token_program_out = Interface::<TokenInterface>::try_from(&accounts[5])?;
+    require_keys_eq!(mint_out.key(), vault_out.mint, TokenSwapError::InvalidRoute);
+    require_keys_eq!(
+        *accounts[4].owner,
+        token_program_out.key(),
+        TokenSwapError::InvalidRoute
+    );
+
+    Ok(RouteHop {
+        pool,
+        direction,
+        vault_in: InterfaceAccount::try_from(&accounts[1])?,
+        vault_out,
+        observations: AccountLoader::try_from(&accounts[3])?,
+        mint_out,
+        token_program_out,
+    })
+}
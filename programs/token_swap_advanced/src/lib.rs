@@ -1,8 +1,16 @@
+use crate::state::curve_type::CurveType;
+use crate::state::oracle_result::{CumulativePricesResult, ObserveResult, TwapResult};
+use crate::state::quote_result::{DepositQuoteResult, SwapQuoteResult, WithdrawQuoteResult};
+use crate::state::swap_direction::SwapDirection;
 use crate::state::token_mint_metadata::TokenMintMetadata;
+use crate::state::trade_result::{
+    ClmmSwapResult, LiquidityResult, PositionResult, RouteSwapResult, SwapResult,
+};
 use anchor_lang::prelude::*;
 
 pub mod contexts;
 pub mod errors;
+pub mod events;
 pub mod instructions;
 pub mod state;
 pub mod utils;
@@ -16,6 +24,64 @@ declare_id!("3jNvySAt5zc2qsePngwS4r93JkaWbhQmy1yUQaoSLXjZ");
 pub mod Simple_Token_Swap {
     use super::*;
 
+    pub fn initialize_config(ctx: Context<InitializeConfig>, admin: Pubkey) -> Result<()> {
+        instructions::config::initialize_config(ctx, admin)
+    }
+
+    pub fn set_config_admin(ctx: Context<ConfigAdmin>, new_admin: Pubkey) -> Result<()> {
+        instructions::config::set_config_admin(ctx, new_admin)
+    }
+
+    pub fn accept_config_admin(ctx: Context<AcceptConfigAdmin>) -> Result<()> {
+        instructions::config::accept_config_admin(ctx)
+    }
+
+    pub fn initialize_pool(
+        ctx: Context<InitializePool>,
+        fee_bps: u16,
+        curve_type: CurveType,
+        amp: u64,
+        weight_a: u64,
+    ) -> Result<()> {
+        instructions::initialize::initialize_pool(ctx, fee_bps, curve_type, amp, weight_a)
+    }
+
+    pub fn set_fee_bps(ctx: Context<PoolAdmin>, fee_bps: u16) -> Result<()> {
+        instructions::admin::set_fee_bps(ctx, fee_bps)
+    }
+
+    pub fn set_admin(ctx: Context<PoolAdmin>, new_admin: Pubkey) -> Result<()> {
+        instructions::admin::set_admin(ctx, new_admin)
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptPoolAdmin>) -> Result<()> {
+        instructions::admin::accept_admin(ctx)
+    }
+
+    pub fn set_lockup_duration(ctx: Context<PoolAdmin>, lockup_duration: i64) -> Result<()> {
+        instructions::admin::set_lockup_duration(ctx, lockup_duration)
+    }
+
+    pub fn set_protocol_fee(
+        ctx: Context<PoolAdmin>,
+        protocol_fee_bps: u16,
+        treasury: Pubkey,
+    ) -> Result<()> {
+        instructions::admin::set_protocol_fee(ctx, protocol_fee_bps, treasury)
+    }
+
+    pub fn ramp_amp(ctx: Context<PoolAdmin>, target_amp: u64, ramp_stop_time: i64) -> Result<()> {
+        instructions::admin::ramp_amp(ctx, target_amp, ramp_stop_time)
+    }
+
+    pub fn stop_ramp_amp(ctx: Context<PoolAdmin>) -> Result<()> {
+        instructions::admin::stop_ramp_amp(ctx)
+    }
+
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        instructions::collect_protocol_fees::collect_protocol_fees(ctx)
+    }
+
     pub fn initialize_vault_token_a(ctx: Context<InitializeVaultTokenA>) -> Result<()> {
         instructions::initialize::initialize_vault_token_a(ctx)
     }
@@ -37,19 +103,184 @@ pub mod Simple_Token_Swap {
         instructions::create_token_mint::create_token_mint(ctx, metadata)
     }
 
-    pub fn addLiquidity(ctx: Context<Liquidity>, tokenAmount: u64) -> Result<()> {
-        instructions::add_liquidity::addLiquidity(ctx, tokenAmount)
+    pub fn addLiquidity(
+        ctx: Context<Liquidity>,
+        max_amount_a: u64,
+        max_amount_b: u64,
+        min_lp_out: u64,
+        deadline: Option<i64>,
+    ) -> Result<LiquidityResult> {
+        instructions::add_liquidity::addLiquidity(
+            ctx,
+            max_amount_a,
+            max_amount_b,
+            min_lp_out,
+            deadline,
+        )
+    }
+
+    pub fn removeLiquidity(
+        ctx: Context<Liquidity>,
+        lp_amount: u64,
+        min_amount_a: u64,
+        min_amount_b: u64,
+        deadline: Option<i64>,
+    ) -> Result<LiquidityResult> {
+        instructions::remove_liquidity::removeLiquidity(
+            ctx,
+            lp_amount,
+            min_amount_a,
+            min_amount_b,
+            deadline,
+        )
+    }
+
+    pub fn swap(
+        ctx: Context<TokenSwap>,
+        amount_in: u64,
+        min_amount_out: u64,
+        direction: SwapDirection,
+        deadline: Option<i64>,
+    ) -> Result<SwapResult> {
+        instructions::swap::swap(ctx, amount_in, min_amount_out, direction, deadline)
+    }
+
+    pub fn swap_exact_out(
+        ctx: Context<TokenSwap>,
+        amount_out: u64,
+        max_amount_in: u64,
+        direction: SwapDirection,
+        deadline: Option<i64>,
+    ) -> Result<SwapResult> {
+        instructions::swap_exact_out::swap_exact_out(
+            ctx,
+            amount_out,
+            max_amount_in,
+            direction,
+            deadline,
+        )
+    }
+
+    pub fn swap_route<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SwapRoute<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        deadline: Option<i64>,
+    ) -> Result<RouteSwapResult> {
+        instructions::swap_route::swap_route(ctx, amount_in, min_amount_out, deadline)
+    }
+
+    pub fn quote_swap(
+        ctx: Context<QuotePool>,
+        amount_in: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuoteResult> {
+        instructions::quote::quote_swap(ctx, amount_in, direction)
+    }
+
+    pub fn quote_add_liquidity(
+        ctx: Context<QuotePool>,
+        max_amount_a: u64,
+        max_amount_b: u64,
+    ) -> Result<DepositQuoteResult> {
+        instructions::quote::quote_add_liquidity(ctx, max_amount_a, max_amount_b)
+    }
+
+    pub fn quote_remove_liquidity(
+        ctx: Context<QuotePool>,
+        lp_amount: u64,
+    ) -> Result<WithdrawQuoteResult> {
+        instructions::quote::quote_remove_liquidity(ctx, lp_amount)
+    }
+
+    pub fn observe_prices(ctx: Context<ReadPool>) -> Result<CumulativePricesResult> {
+        instructions::oracle::observe_prices(ctx)
+    }
+
+    pub fn quote_twap(
+        ctx: Context<ReadPool>,
+        price_a_cumulative_start: u128,
+        price_b_cumulative_start: u128,
+        timestamp_start: i64,
+    ) -> Result<TwapResult> {
+        instructions::oracle::quote_twap(
+            ctx,
+            price_a_cumulative_start,
+            price_b_cumulative_start,
+            timestamp_start,
+        )
+    }
+
+    pub fn grow_observations(ctx: Context<GrowObservations>, cardinality_next: u16) -> Result<()> {
+        instructions::oracle::grow_observations(ctx, cardinality_next)
+    }
+
+    pub fn observe(ctx: Context<Observe>, seconds_agos: Vec<u32>) -> Result<ObserveResult> {
+        instructions::oracle::observe(ctx, seconds_agos)
+    }
+
+    pub fn initialize_clmm_pool(
+        ctx: Context<InitializeClmmPool>,
+        tick_spacing: u16,
+        fee_bps: u16,
+        initial_sqrt_price: u128,
+    ) -> Result<()> {
+        instructions::clmm_pool::initialize_clmm_pool(
+            ctx,
+            tick_spacing,
+            fee_bps,
+            initial_sqrt_price,
+        )
+    }
+
+    pub fn initialize_tick_array(
+        ctx: Context<InitializeTickArray>,
+        start_tick_index: i32,
+    ) -> Result<()> {
+        instructions::clmm_pool::initialize_tick_array(ctx, start_tick_index)
+    }
+
+    pub fn open_position(
+        ctx: Context<OpenPosition>,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<()> {
+        instructions::clmm_position::open_position(ctx, tick_lower, tick_upper)
     }
 
-    pub fn removeLiquidity(ctx: Context<Liquidity>, tokenAmount: u64) -> Result<()> {
-        instructions::remove_liquidity::removeLiquidity(ctx, tokenAmount)
+    pub fn increase_liquidity(
+        ctx: Context<ModifyPosition>,
+        liquidity: u128,
+        max_amount_a: u64,
+        max_amount_b: u64,
+    ) -> Result<PositionResult> {
+        instructions::clmm_position::increase_liquidity(ctx, liquidity, max_amount_a, max_amount_b)
     }
 
-    pub fn swap_b_for_a(ctx: Context<TokenSwap>, amountOfTokenB: u64, minExpectedOutput: u64) -> Result<()> {
-        instructions::swap_b::swap_b_for_a(ctx, amountOfTokenB, minExpectedOutput)
+    pub fn decrease_liquidity(
+        ctx: Context<ModifyPosition>,
+        liquidity: u128,
+        min_amount_a: u64,
+        min_amount_b: u64,
+    ) -> Result<PositionResult> {
+        instructions::clmm_position::decrease_liquidity(ctx, liquidity, min_amount_a, min_amount_b)
     }
 
-    pub fn swap_a_for_b(ctx: Context<TokenSwap>, amountOfTokenA: u64, minExpectedOutput: u64) -> Result<()> {
-        instructions::swap_a::swap_a_for_b(ctx, amountOfTokenA, minExpectedOutput)
+    pub fn clmm_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClmmSwap<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        direction: SwapDirection,
+        sqrt_price_limit: Option<u128>,
+        deadline: Option<i64>,
+    ) -> Result<ClmmSwapResult> {
+        instructions::clmm_swap::clmm_swap(
+            ctx,
+            amount_in,
+            min_amount_out,
+            direction,
+            sqrt_price_limit,
+            deadline,
+        )
     }
 }
\ No newline at end of file
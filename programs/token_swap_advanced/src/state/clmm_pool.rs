@@ -0,0 +1,64 @@
+use crate::errors::error::TokenSwapError;
+use crate::state::tick_array::TICK_ARRAY_SIZE;
+use crate::utils::tick_math::{MAX_TICK, MIN_TICK};
+use anchor_lang::prelude::*;
+
+// Largest allowed distance between two usable ticks
+pub const MAX_TICK_SPACING: u16 = 16_384;
+
+// Concentrated liquidity pool. Liquidity is only active while the price is
+// inside the range of the positions that provide it, so unlike `Pool` there
+// are no reserves or LP mint, every LP holds a `Position` instead.
+#[account]
+#[derive(InitSpace)]
+pub struct ClmmPool {
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub vault_a: Pubkey,
+    pub vault_b: Pubkey,
+
+    // Positions can only start and end on multiples of the spacing
+    pub tick_spacing: u16,
+    // Swap fee taken from the input amount, in basis points
+    pub fee_bps: u16,
+
+    // Liquidity of the positions whose range holds the current price
+    pub liquidity: u128,
+    // Q64.64 square root of the price of token A in token B
+    pub sqrt_price: u128,
+    pub tick_current: i32,
+    // Fees earned per unit of liquidity over the life of the pool, Q64.64.
+    // These wrap on overflow, only their differences are used.
+    pub fee_growth_global_a: u128,
+    pub fee_growth_global_b: u128,
+
+    pub bump: u8,
+    pub vault_a_bump: u8,
+    pub vault_b_bump: u8,
+}
+
+impl ClmmPool {
+    // Number of ticks covered by one tick array
+    pub fn tick_array_span(&self) -> i32 {
+        TICK_ARRAY_SIZE as i32 * self.tick_spacing as i32
+    }
+
+    // A position bound has to be a usable tick of this pool
+    pub fn check_tick(&self, tick: i32) -> Result<()> {
+        require!(
+            (MIN_TICK..=MAX_TICK).contains(&tick),
+            TokenSwapError::InvalidTickRange
+        );
+        require!(
+            tick % self.tick_spacing as i32 == 0,
+            TokenSwapError::InvalidTickSpacing
+        );
+
+        Ok(())
+    }
+
+    // Whether a position over [tick_lower, tick_upper) is currently in range
+    pub fn is_active(&self, tick_lower: i32, tick_upper: i32) -> bool {
+        tick_lower <= self.tick_current && self.tick_current < tick_upper
+    }
+}
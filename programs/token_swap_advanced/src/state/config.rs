@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+// Program-wide settings, a single PDA at [b"config"]
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    // Creates pools and becomes the admin and treasury of each new pool
+    pub admin: Pubkey,
+    // Admin proposed by `set_config_admin`, takes over once it accepts
+    pub pending_admin: Pubkey,
+    pub bump: u8,
+}
@@ -0,0 +1,9 @@
+use anchor_lang::prelude::*;
+
+// Invariant a pool is created with, it cannot be changed afterwards
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum CurveType {
+    ConstantProduct,
+    Stable,
+    Weighted,
+}
@@ -1,2 +1,13 @@
+pub mod clmm_pool;
+pub mod config;
+pub mod curve_type;
 pub mod liquidity_account;
-pub mod token_mint_metadata;
\ No newline at end of file
+pub mod observation;
+pub mod oracle_result;
+pub mod pool;
+pub mod position;
+pub mod quote_result;
+pub mod swap_direction;
+pub mod tick_array;
+pub mod token_mint_metadata;
+pub mod trade_result;
\ No newline at end of file
@@ -0,0 +1,340 @@
+use crate::errors::error::TokenSwapError;
+use crate::state::pool::Pool;
+use crate::utils::oracle::{interpolate, Cumulatives};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use std::cell::{Ref, RefMut};
+
+// Upper bound for the number of observations a pool keeps
+pub const MAX_OBSERVATION_CARDINALITY: u16 = 1_024;
+// Most slots one call can add, since an instruction can grow an account by
+// at most 10 KiB. Larger buffers are grown over several calls.
+pub const MAX_OBSERVATION_GROWTH: u16 =
+    (MAX_PERMITTED_DATA_INCREASE / std::mem::size_of::<Observation>()) as u16;
+
+// Oracle sums of a pool as of `timestamp`. Packed like the ticks so the
+// u128 fields do not depend on the alignment of account data.
+#[zero_copy(unsafe)]
+#[repr(C, packed)]
+#[derive(Default, Debug)]
+pub struct Observation {
+    // Zero while the slot has never been written
+    pub timestamp: i64,
+    pub price_a_cumulative: u128,
+    pub price_b_cumulative: u128,
+    pub liquidity_cumulative: u128,
+}
+
+// Slots are cast straight from the account data past the header. Packed
+// integers only, so there is no padding and every bit pattern is valid.
+unsafe impl bytemuck::Zeroable for Observation {}
+unsafe impl bytemuck::Pod for Observation {}
+
+impl Observation {
+    pub fn is_initialized(&self) -> bool {
+        self.timestamp != 0
+    }
+
+    pub fn cumulatives(&self) -> Cumulatives {
+        Cumulatives {
+            price_a: self.price_a_cumulative,
+            price_b: self.price_b_cumulative,
+            liquidity: self.liquidity_cumulative,
+        }
+    }
+}
+
+// Ring buffer of observations, as in Uniswap v3. The header is followed by
+// the observation slots, which fill the rest of the account. `index` is the
+// newest observation; once it reaches the end of the `cardinality` slots in
+// use the buffer wraps, or grows to `cardinality_next` if more slots have
+// been paid for in the meantime.
+#[account(zero_copy(unsafe))]
+#[repr(C, packed)]
+pub struct ObservationBuffer {
+    pub pool: Pubkey,
+    pub index: u16,
+    pub cardinality: u16,
+    pub cardinality_next: u16,
+}
+
+impl ObservationBuffer {
+    // Account size holding `cardinality` observations
+    pub fn space(cardinality: u16) -> usize {
+        8 + std::mem::size_of::<ObservationBuffer>()
+            + cardinality as usize * std::mem::size_of::<Observation>()
+    }
+
+    // Stores the pool's oracle sums as the newest observation. Only one
+    // observation is kept per second, like the pool updates its oracle.
+    pub fn write(&mut self, observations: &mut [Observation], pool: &Pool) {
+        let index = self.index as usize;
+        if observations[index].timestamp == pool.oracle_timestamp {
+            return;
+        }
+
+        if self.cardinality_next > self.cardinality && self.index == self.cardinality - 1 {
+            self.cardinality = self.cardinality_next;
+        }
+        self.index = ((index + 1) % self.cardinality as usize) as u16;
+
+        observations[self.index as usize] = Observation {
+            timestamp: pool.oracle_timestamp,
+            price_a_cumulative: pool.price_a_cumulative,
+            price_b_cumulative: pool.price_b_cumulative,
+            liquidity_cumulative: pool.liquidity_cumulative,
+        };
+    }
+
+    // Oracle sums of the pool at `target`, interpolated between the two
+    // observations around it. Past the newest observation the pool's own
+    // sums are extended, since nothing has changed since it was written.
+    pub fn observe(
+        &self,
+        observations: &[Observation],
+        pool: &Pool,
+        target: i64,
+    ) -> Result<Cumulatives> {
+        let newest = &observations[self.index as usize];
+        if target >= newest.timestamp {
+            return Ok(pool.cumulatives(target));
+        }
+
+        // Until the buffer wraps the slot after the newest one is empty and
+        // the oldest observation sits in slot zero
+        let cardinality = self.cardinality as usize;
+        let next = (self.index as usize + 1) % cardinality;
+        let (oldest, count) = if observations[next].is_initialized() {
+            (next, cardinality)
+        } else {
+            (0, self.index as usize + 1)
+        };
+        let at = |position: usize| &observations[(oldest + position) % cardinality];
+
+        require!(at(0).timestamp <= target, TokenSwapError::ObservationTooOld);
+
+        // Last observation at or before `target`; the newest one is after it
+        let (mut low, mut high) = (0, count - 1);
+        while high - low > 1 {
+            let middle = (low + high) / 2;
+            if at(middle).timestamp <= target {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        let (before, after) = (at(low), at(high));
+        if before.timestamp == target {
+            return Ok(before.cumulatives());
+        }
+
+        interpolate(
+            before.cumulatives(),
+            after.cumulatives(),
+            (target - before.timestamp) as u64,
+            (after.timestamp - before.timestamp) as u64,
+        )
+        .map_err(|_| error!(TokenSwapError::CalculationError))
+    }
+}
+
+// Writes the pool's current oracle sums to its observation buffer
+pub fn record_observation(
+    loader: &AccountLoader<ObservationBuffer>,
+    pool: &Account<Pool>,
+) -> Result<()> {
+    let (mut buffer, mut observations) = load_observations_mut(loader, &pool.key())?;
+    buffer.write(&mut observations, pool);
+
+    Ok(())
+}
+
+// Header and observation slots of a buffer account for writing. Checks the
+// buffer belongs to `pool`, since swap routes pass it unchecked.
+pub fn load_observations_mut<'a>(
+    loader: &'a AccountLoader<ObservationBuffer>,
+    pool: &Pubkey,
+) -> Result<(RefMut<'a, ObservationBuffer>, RefMut<'a, [Observation]>)> {
+    let info = loader.as_ref();
+    require!(info.is_writable, ErrorCode::AccountNotMutable);
+
+    let data = info.try_borrow_mut_data()?;
+    let (buffer, observations) = RefMut::map_split(data, |data| {
+        let (header, slots) = data[8..].split_at_mut(std::mem::size_of::<ObservationBuffer>());
+        let slots_len = slots.len() - slots.len() % std::mem::size_of::<Observation>();
+
+        (
+            bytemuck::from_bytes_mut::<ObservationBuffer>(header),
+            bytemuck::cast_slice_mut::<u8, Observation>(&mut slots[..slots_len]),
+        )
+    });
+
+    require_keys_eq!(buffer.pool, *pool, TokenSwapError::InvalidObservationBuffer);
+
+    Ok((buffer, observations))
+}
+
+// Read-only counterpart of `load_observations_mut`
+pub fn load_observations<'a>(
+    loader: &'a AccountLoader<ObservationBuffer>,
+    pool: &Pubkey,
+) -> Result<(Ref<'a, ObservationBuffer>, Ref<'a, [Observation]>)> {
+    let data = loader.as_ref().try_borrow_data()?;
+    let (buffer, observations) = Ref::map_split(data, |data| {
+        let (header, slots) = data[8..].split_at(std::mem::size_of::<ObservationBuffer>());
+        let slots_len = slots.len() - slots.len() % std::mem::size_of::<Observation>();
+
+        (
+            bytemuck::from_bytes::<ObservationBuffer>(header),
+            bytemuck::cast_slice::<u8, Observation>(&slots[..slots_len]),
+        )
+    });
+
+    require_keys_eq!(buffer.pool, *pool, TokenSwapError::InvalidObservationBuffer);
+
+    Ok((buffer, observations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pool whose oracle sums all grow by `rate` per second, last updated at
+    // `timestamp`. Without reserves it extends them no further.
+    fn pool_at(timestamp: i64, rate: u128) -> Pool {
+        let mut pool = Pool::deserialize(&mut &[0u8; Pool::INIT_SPACE][..]).unwrap();
+        pool.oracle_timestamp = timestamp;
+        pool.price_a_cumulative = rate.wrapping_mul(timestamp as u128);
+        pool.price_b_cumulative = rate.wrapping_mul(timestamp as u128) / 2;
+        pool.liquidity_cumulative = rate.wrapping_mul(timestamp as u128) / 4;
+        pool
+    }
+
+    // Buffer as a new pool leaves it, holding its observation at `timestamp`,
+    // then grown to `slots` observations
+    fn buffer(slots: u16, timestamp: i64, rate: u128) -> (ObservationBuffer, Vec<Observation>) {
+        let mut buffer = ObservationBuffer {
+            pool: Pubkey::default(),
+            index: 0,
+            cardinality: 1,
+            cardinality_next: 1,
+        };
+        let mut observations = (0..slots)
+            .map(|_| Observation::default())
+            .collect::<Vec<_>>();
+        buffer.write(&mut observations, &pool_at(timestamp, rate));
+        buffer.cardinality_next = slots;
+
+        (buffer, observations)
+    }
+
+    fn timestamps(observations: &[Observation]) -> Vec<i64> {
+        observations
+            .iter()
+            .map(|observation| observation.timestamp)
+            .collect()
+    }
+
+    #[test]
+    fn writes_wrap_around_at_the_cardinality() {
+        let (mut buffer, mut observations) = buffer(3, 10, 1);
+        assert_eq!(timestamps(&observations), [10, 0, 0]);
+
+        for timestamp in [20, 30, 40, 50] {
+            buffer.write(&mut observations, &pool_at(timestamp, 1));
+        }
+
+        // The first write grew the buffer to its three slots, the fourth one
+        // replaced the oldest observation
+        assert_eq!({ buffer.cardinality }, 3);
+        assert_eq!({ buffer.index }, 1);
+        assert_eq!(timestamps(&observations), [40, 50, 30]);
+    }
+
+    #[test]
+    fn one_observation_is_kept_per_second() {
+        let (mut buffer, mut observations) = buffer(3, 10, 1);
+        buffer.write(&mut observations, &pool_at(10, 1));
+
+        assert_eq!({ buffer.index }, 0);
+        assert_eq!(timestamps(&observations), [10, 0, 0]);
+    }
+
+    #[test]
+    fn growth_waits_for_the_end_of_the_slots_in_use() {
+        let (mut buffer, mut observations) = buffer(1, 10, 1);
+        buffer.write(&mut observations, &pool_at(20, 1));
+        assert_eq!(timestamps(&observations), [20]);
+
+        // Slots paid for later are taken once the newest observation is in
+        // the last slot in use
+        buffer.cardinality_next = 2;
+        observations.push(Observation::default());
+        buffer.write(&mut observations, &pool_at(30, 1));
+        assert_eq!({ buffer.cardinality }, 2);
+        assert_eq!(timestamps(&observations), [20, 30]);
+    }
+
+    #[test]
+    fn targets_before_the_oldest_observation_fail() {
+        let (mut buffer, mut observations) = buffer(3, 10, 1);
+        let pool = pool_at(20, 1);
+        buffer.write(&mut observations, &pool);
+
+        assert_eq!(
+            buffer.observe(&observations, &pool, 9).unwrap_err(),
+            TokenSwapError::ObservationTooOld.into()
+        );
+        assert!(buffer.observe(&observations, &pool, 10).is_ok());
+
+        // Once the buffer wraps the oldest observation is the one after the
+        // newest, not the one in slot zero
+        for timestamp in [30, 40] {
+            buffer.write(&mut observations, &pool_at(timestamp, 1));
+        }
+        let pool = pool_at(40, 1);
+        assert_eq!(
+            buffer.observe(&observations, &pool, 19).unwrap_err(),
+            TokenSwapError::ObservationTooOld.into()
+        );
+        assert!(buffer.observe(&observations, &pool, 20).is_ok());
+    }
+
+    #[test]
+    fn targets_between_observations_are_interpolated() {
+        let rate = 1_000_000;
+        let (mut buffer, mut observations) = buffer(5, 100, rate);
+        for timestamp in [110, 130, 160, 200] {
+            buffer.write(&mut observations, &pool_at(timestamp, rate));
+        }
+        let pool = pool_at(200, rate);
+
+        // Every sum grows steadily, so each target reads like the pool at it
+        for target in [100, 110, 115, 129, 130, 145, 199, 200] {
+            let expected = pool_at(target, rate);
+            assert_eq!(
+                buffer.observe(&observations, &pool, target).unwrap(),
+                Cumulatives {
+                    price_a: expected.price_a_cumulative,
+                    price_b: expected.price_b_cumulative,
+                    liquidity: expected.liquidity_cumulative,
+                },
+                "target {}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn interpolation_follows_wrapped_sums() {
+        // Sums that overflow between the two observations
+        let rate = u128::MAX / 150;
+        let (mut buffer, mut observations) = buffer(2, 100, rate);
+        buffer.write(&mut observations, &pool_at(200, rate));
+        let pool = pool_at(200, rate);
+
+        let observed = buffer.observe(&observations, &pool, 150).unwrap();
+        assert_eq!(observed.price_a, rate.wrapping_mul(150));
+    }
+}
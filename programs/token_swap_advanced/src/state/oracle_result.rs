@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+// Return data of the price oracle instructions. Prices are Q64.64 numbers,
+// `price_a` is the price of token A in token B and `price_b` its inverse.
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct CumulativePricesResult {
+    pub price_a_cumulative: u128,
+    pub price_b_cumulative: u128,
+    pub liquidity_cumulative: u128,
+    pub timestamp: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct TwapResult {
+    pub price_a: u128,
+    pub price_b: u128,
+    // Seconds the average was taken over
+    pub elapsed: u64,
+}
+
+// One entry per requested `seconds_ago`, in the order they were asked for
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+pub struct ObserveResult {
+    pub price_a_cumulatives: Vec<u128>,
+    pub price_b_cumulatives: Vec<u128>,
+    pub liquidity_cumulatives: Vec<u128>,
+}
@@ -0,0 +1,193 @@
+use crate::errors::error::TokenSwapError;
+use crate::state::curve_type::CurveType;
+use crate::state::swap_direction::SwapDirection;
+use crate::utils::amm::{integer_sqrt, ConstantProductCurve};
+use crate::utils::oracle::{accumulate, Cumulatives};
+use crate::utils::quote::{Curve, PoolState, SwapQuote};
+use crate::utils::stable_swap::{current_amp, StableCurve};
+use crate::utils::weighted::WeightedCurve;
+use anchor_lang::prelude::*;
+
+// Upper bound for the swap fee the admin can configure (10%)
+pub const MAX_FEE_BPS: u16 = 1_000;
+// Upper bound for the protocol share of the swap fee (50% of the fee)
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 5_000;
+// Lockup new pools start with, the admin can change it afterwards
+pub const DEFAULT_LOCKUP_DURATION: i64 = 100;
+// Bounds of the StableSwap amplification coefficient
+pub const MIN_AMP: u64 = 1;
+pub const MAX_AMP: u64 = 1_000_000;
+// A ramp can move the amplification by at most this factor
+pub const MAX_AMP_CHANGE: u64 = 10;
+// Shortest allowed ramp, so LPs can react before the curve changes
+pub const MIN_RAMP_DURATION: i64 = 86_400;
+// Pool weights are normalized to 1e18, each token keeps at least 1%
+pub const WEIGHT_ONE: u64 = 1_000_000_000_000_000_000;
+pub const MIN_WEIGHT: u64 = 10_000_000_000_000_000;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Pool {
+    pub admin: Pubkey,
+    // Admin proposed by `set_admin`, takes over once it accepts
+    pub pending_admin: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub lp_mint: Pubkey,
+    pub vault_a: Pubkey,
+    pub vault_b: Pubkey,
+
+    // Swap fee taken from the input amount, in basis points
+    pub fee_bps: u16,
+    // Part of the swap fee kept for the protocol, in basis points of the fee
+    pub protocol_fee_bps: u16,
+    // Owner of the token accounts protocol fees are collected to
+    pub treasury: Pubkey,
+    // Seconds a deposit must stay in the pool before it can be removed
+    pub lockup_duration: i64,
+
+    pub curve_type: CurveType,
+    // StableSwap amplification, ramped linearly from `initial_amp` at
+    // `ramp_start_time` to `target_amp` at `ramp_stop_time`. Unused on
+    // constant-product pools.
+    pub initial_amp: u64,
+    pub target_amp: u64,
+    pub ramp_start_time: i64,
+    pub ramp_stop_time: i64,
+    // Normalized weights adding up to `WEIGHT_ONE`, 50/50 unless the pool
+    // is weighted
+    pub weight_a: u64,
+    pub weight_b: u64,
+
+    pub lp_supply: u64,
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+    // Protocol fees held in the vaults but excluded from the reserves
+    pub protocol_fees_a: u64,
+    pub protocol_fees_b: u64,
+
+    // Running sums of the Q64.64 marginal price of A in B and of B in A,
+    // each times the seconds it held, as of `oracle_timestamp`. Like
+    // Uniswap v2's `price0CumulativeLast`, they wrap on overflow.
+    pub price_a_cumulative: u128,
+    pub price_b_cumulative: u128,
+    // Same for the liquidity, measured as the geometric mean of the reserves
+    pub liquidity_cumulative: u128,
+    pub oracle_timestamp: i64,
+
+    pub bump: u8,
+    pub vault_a_bump: u8,
+    pub vault_b_bump: u8,
+    pub lp_mint_bump: u8,
+    pub authority_bump: u8,
+    pub observations_bump: u8,
+}
+
+impl Pool {
+    // Amplification in effect at `current_time`
+    pub fn amp(&self, current_time: i64) -> u64 {
+        current_amp(
+            self.initial_amp,
+            self.target_amp,
+            self.ramp_start_time,
+            self.ramp_stop_time,
+            current_time,
+        )
+    }
+
+    // Snapshot of the fields the quote math needs at `current_time`
+    pub fn quote_state(&self, current_time: i64) -> PoolState {
+        let curve = match self.curve_type {
+            CurveType::ConstantProduct => Curve::ConstantProduct(ConstantProductCurve),
+            CurveType::Stable => Curve::Stable(StableCurve {
+                amp: self.amp(current_time),
+            }),
+            CurveType::Weighted => Curve::Weighted(WeightedCurve {
+                weight_a: self.weight_a,
+                weight_b: self.weight_b,
+            }),
+        };
+
+        PoolState {
+            reserve_a: self.reserve_a,
+            reserve_b: self.reserve_b,
+            lp_supply: self.lp_supply,
+            fee_bps: self.fee_bps,
+            protocol_fee_bps: self.protocol_fee_bps,
+            curve,
+        }
+    }
+
+    // Oracle sums as of `current_time`, counting the time since the last
+    // update at the current price and liquidity
+    pub fn cumulatives(&self, current_time: i64) -> Cumulatives {
+        let cumulatives = Cumulatives {
+            price_a: self.price_a_cumulative,
+            price_b: self.price_b_cumulative,
+            liquidity: self.liquidity_cumulative,
+        };
+
+        let elapsed = current_time.saturating_sub(self.oracle_timestamp);
+        if elapsed <= 0 || self.reserve_a == 0 || self.reserve_b == 0 {
+            return cumulatives;
+        }
+
+        // A price the curve cannot represent is left out rather than
+        // blocking the pool
+        let price_a = self
+            .quote_state(current_time)
+            .curve
+            .swap_curve()
+            .marginal_price(self.reserve_a, self.reserve_b);
+        let liquidity = integer_sqrt(self.reserve_a as u128 * self.reserve_b as u128);
+
+        match price_a {
+            Ok(price_a) => accumulate(cumulatives, price_a, liquidity, elapsed as u64),
+            Err(_) => cumulatives,
+        }
+    }
+
+    // Brings the oracle sums up to `current_time`. Called before any change
+    // to the reserves, so only the first action in a second moves the
+    // oracle and it always records the state trades left behind.
+    pub fn update_oracle(&mut self, current_time: i64) {
+        if current_time <= self.oracle_timestamp {
+            return;
+        }
+
+        let cumulatives = self.cumulatives(current_time);
+        self.price_a_cumulative = cumulatives.price_a;
+        self.price_b_cumulative = cumulatives.price_b;
+        self.liquidity_cumulative = cumulatives.liquidity;
+        self.oracle_timestamp = current_time;
+    }
+
+    // Books a settled swap: the input minus the protocol fee joins the input
+    // reserve, the protocol fee is accrued and the output leaves the other side
+    pub fn record_swap(&mut self, direction: SwapDirection, quote: &SwapQuote) -> Result<()> {
+        let (reserve_in, reserve_out, protocol_fees_in) = match direction {
+            SwapDirection::AToB => (
+                &mut self.reserve_a,
+                &mut self.reserve_b,
+                &mut self.protocol_fees_a,
+            ),
+            SwapDirection::BToA => (
+                &mut self.reserve_b,
+                &mut self.reserve_a,
+                &mut self.protocol_fees_b,
+            ),
+        };
+
+        *reserve_in = reserve_in
+            .checked_add(quote.amount_in - quote.protocol_fee)
+            .ok_or(error!(TokenSwapError::CalculationError))?;
+        *protocol_fees_in = protocol_fees_in
+            .checked_add(quote.protocol_fee)
+            .ok_or(error!(TokenSwapError::CalculationError))?;
+        *reserve_out = reserve_out
+            .checked_sub(quote.amount_out)
+            .ok_or(error!(TokenSwapError::CalculationError))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+// Liquidity an LP provides to a `ClmmPool` over the price range
+// [tick_lower, tick_upper), the concentrated counterpart of `LiquidityAccount`
+#[account]
+#[derive(InitSpace)]
+pub struct Position {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    // Fee growth inside the range when the position was last updated
+    pub fee_growth_inside_last_a: u128,
+    pub fee_growth_inside_last_b: u128,
+    // Fees earned but not yet paid out
+    pub tokens_owed_a: u64,
+    pub tokens_owed_b: u64,
+    pub bump: u8,
+}
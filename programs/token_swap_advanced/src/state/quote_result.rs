@@ -0,0 +1,39 @@
+use crate::utils::quote::SwapQuote;
+use anchor_lang::prelude::*;
+
+// Borsh-encoded quotes returned to callers through `set_return_data`. Unlike
+// the `utils::quote` results the amounts include Token-2022 transfer fees.
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct SwapQuoteResult {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub protocol_fee: u64,
+    pub price_impact_bps: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct DepositQuoteResult {
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub lp_tokens: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct WithdrawQuoteResult {
+    pub amount_a: u64,
+    pub amount_b: u64,
+}
+
+impl From<SwapQuote> for SwapQuoteResult {
+    fn from(quote: SwapQuote) -> Self {
+        SwapQuoteResult {
+            amount_in: quote.amount_in,
+            amount_out: quote.amount_out,
+            fee: quote.fee,
+            protocol_fee: quote.protocol_fee,
+            price_impact_bps: quote.price_impact_bps,
+        }
+    }
+}
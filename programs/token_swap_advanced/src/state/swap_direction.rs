@@ -0,0 +1,13 @@
+use anchor_lang::prelude::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapDirection {
+    AToB,
+    BToA,
+}
+
+impl SwapDirection {
+    pub fn is_a_to_b(&self) -> bool {
+        *self == SwapDirection::AToB
+    }
+}
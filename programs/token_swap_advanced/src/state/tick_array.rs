@@ -0,0 +1,140 @@
+use crate::errors::error::TokenSwapError;
+use crate::utils::clmm::add_liquidity_delta;
+use anchor_lang::prelude::*;
+
+pub const TICK_ARRAY_SIZE: usize = 32;
+
+// Liquidity and fee state kept at the boundary of one or more positions.
+// Packed so the u128 fields do not depend on the alignment of account data.
+#[zero_copy(unsafe)]
+#[repr(C, packed)]
+#[derive(Default, Debug)]
+pub struct Tick {
+    // Liquidity added when the price crosses this tick upwards, removed
+    // when it crosses downwards
+    pub liquidity_net: i128,
+    // Liquidity of every position using this tick, zero when uninitialized
+    pub liquidity_gross: u128,
+    // Fee growth on the other side of this tick from the current price
+    pub fee_growth_outside_a: u128,
+    pub fee_growth_outside_b: u128,
+}
+
+impl Tick {
+    pub fn is_initialized(&self) -> bool {
+        self.liquidity_gross != 0
+    }
+
+    // Adds `liquidity_delta` for a position starting (`is_upper == false`) or
+    // ending at this tick. Growth below a newly initialized tick is assumed to
+    // have happened below it, as in Uniswap v3.
+    pub fn update(
+        &mut self,
+        tick_index: i32,
+        tick_current: i32,
+        liquidity_delta: i128,
+        fee_growth_global_a: u128,
+        fee_growth_global_b: u128,
+        is_upper: bool,
+    ) -> Result<()> {
+        let liquidity_gross = add_liquidity_delta(self.liquidity_gross, liquidity_delta)
+            .map_err(TokenSwapError::from)?;
+
+        if !self.is_initialized() && tick_index <= tick_current {
+            self.fee_growth_outside_a = fee_growth_global_a;
+            self.fee_growth_outside_b = fee_growth_global_b;
+        }
+
+        let liquidity_net = if is_upper {
+            self.liquidity_net.checked_sub(liquidity_delta)
+        } else {
+            self.liquidity_net.checked_add(liquidity_delta)
+        };
+
+        self.liquidity_gross = liquidity_gross;
+        self.liquidity_net = liquidity_net.ok_or(error!(TokenSwapError::CalculationError))?;
+
+        Ok(())
+    }
+
+    // Flips the fee growth to the other side when the price moves across
+    // this tick and returns the liquidity to add when moving upwards
+    pub fn cross(&mut self, fee_growth_global_a: u128, fee_growth_global_b: u128) -> i128 {
+        self.fee_growth_outside_a = fee_growth_global_a.wrapping_sub(self.fee_growth_outside_a);
+        self.fee_growth_outside_b = fee_growth_global_b.wrapping_sub(self.fee_growth_outside_b);
+
+        self.liquidity_net
+    }
+
+    pub fn clear(&mut self) {
+        *self = Tick::default();
+    }
+}
+
+// Fixed-size run of ticks starting at `start_tick_index`, where slot `i`
+// holds tick `start_tick_index + i * tick_spacing`
+#[account(zero_copy(unsafe))]
+#[repr(C, packed)]
+pub struct TickArray {
+    pub ticks: [Tick; TICK_ARRAY_SIZE],
+    pub pool: Pubkey,
+    pub start_tick_index: i32,
+}
+
+impl TickArray {
+    // Slot of `tick_index`, which has to be a usable tick inside this array
+    pub fn tick_offset(&self, tick_index: i32, tick_spacing: u16) -> Result<usize> {
+        require!(
+            self.contains(tick_index, tick_spacing),
+            TokenSwapError::InvalidTickArray
+        );
+
+        let distance = tick_index - self.start_tick_index;
+        require!(
+            distance % tick_spacing as i32 == 0,
+            TokenSwapError::InvalidTickSpacing
+        );
+
+        Ok((distance / tick_spacing as i32) as usize)
+    }
+
+    pub fn tick_mut(&mut self, tick_index: i32, tick_spacing: u16) -> Result<&mut Tick> {
+        let offset = self.tick_offset(tick_index, tick_spacing)?;
+
+        Ok(&mut self.ticks[offset])
+    }
+
+    // Whether `tick_index` falls inside the range this array covers
+    pub fn contains(&self, tick_index: i32, tick_spacing: u16) -> bool {
+        let distance = tick_index as i64 - self.start_tick_index as i64;
+
+        distance >= 0 && distance < TICK_ARRAY_SIZE as i64 * tick_spacing as i64
+    }
+
+    // Nearest initialized tick in the swap direction: at or below
+    // `tick_current` when the price moves down, above it when it moves up
+    pub fn next_initialized_tick(
+        &self,
+        tick_current: i32,
+        tick_spacing: u16,
+        a_to_b: bool,
+    ) -> Option<i32> {
+        let start = self.start_tick_index;
+        let tick_at = |offset: usize| start + offset as i32 * tick_spacing as i32;
+
+        if a_to_b {
+            (0..TICK_ARRAY_SIZE)
+                .rev()
+                .find(|&offset| {
+                    tick_at(offset) <= tick_current && self.ticks[offset].is_initialized()
+                })
+                .map(tick_at)
+        } else {
+            (0..TICK_ARRAY_SIZE)
+                .find(|&offset| {
+                    tick_at(offset) > tick_current && self.ticks[offset].is_initialized()
+                })
+                .map(tick_at)
+        }
+    }
+}
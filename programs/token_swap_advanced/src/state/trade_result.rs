@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+
+// Return data of the swap and liquidity instructions, so routers and vault
+// strategies calling in through CPI can read what was actually settled.
+// Token amounts are what left or reached the user's token accounts, with
+// any Token-2022 transfer fee already taken.
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct SwapResult {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub protocol_fee: u64,
+    // Pool reserves after the trade
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+pub struct RouteSwapResult {
+    pub amount_in: u64,
+    // Output of the last hop, received by the user
+    pub amount_out: u64,
+    // One entry per pool, in route order
+    pub hops: Vec<RouteHopResult>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct RouteHopResult {
+    pub pool: Pubkey,
+    // Sent into the pool and received by the next hop or the user
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub protocol_fee: u64,
+    // Pool reserves after the trade
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct LiquidityResult {
+    pub amount_a: u64,
+    pub amount_b: u64,
+    // LP tokens minted on deposit or burned on withdrawal
+    pub lp_tokens: u64,
+    // Pool state after the change
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+    pub lp_supply: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct ClmmSwapResult {
+    // Input actually used, less than requested if the price limit was hit
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    // Pool price and liquidity after the trade
+    pub sqrt_price: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy)]
+pub struct PositionResult {
+    // Tokens deposited or withdrawn for the liquidity change
+    pub amount_a: u64,
+    pub amount_b: u64,
+    // Fees paid out along with a withdrawal
+    pub fees_a: u64,
+    pub fees_b: u64,
+    // Position liquidity after the change
+    pub liquidity: u128,
+}
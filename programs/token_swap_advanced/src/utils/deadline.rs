@@ -0,0 +1,11 @@
+use crate::errors::error::TokenSwapError;
+use anchor_lang::prelude::*;
+
+// Rejects transactions that land after the user supplied deadline
+pub fn check_deadline(deadline: Option<i64>, current_time: i64) -> Result<()> {
+    if let Some(deadline) = deadline {
+        require!(current_time <= deadline, TokenSwapError::DeadlineExceeded);
+    }
+
+    Ok(())
+}
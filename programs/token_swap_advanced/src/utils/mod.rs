@@ -1,2 +1,7 @@
 pub mod transfer;
-pub mod amm;
\ No newline at end of file
+pub mod deadline;
+// Pool math lives in the `token_swap_math` crate so clients can use it
+// without Anchor, re-exported here for the handlers
+pub use token_swap_math::{
+    amm, clmm, fixed_point, full_math, oracle, quote, stable_swap, tick_math, weighted,
+};
\ No newline at end of file
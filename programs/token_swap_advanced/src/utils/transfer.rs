@@ -1,110 +1,134 @@
+use crate::errors::error::TokenSwapError;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{
-    self, InitializeAccount, Mint, Token, TokenAccount, TokenAccount as SPLTokenAccount, Transfer,
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType,
+        StateWithExtensions,
+    },
 };
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
 
-// This function deposits the Token A from user to the token vault
-pub fn deposit_to_vault_token_a<'info>(
+// This function deposits tokens from the user to one of the pool vaults.
+// `token_program` is the program that owns `mint`, either SPL Token or
+// Token-2022.
+pub fn deposit_to_vault<'info>(
     user: &AccountInfo<'info>,
-    user_token_account_for_token_a: &Account<'info, TokenAccount>,
-    vault_token_a_account: &Account<'info, TokenAccount>,
-    token_program: &Program<'info, Token>,
+    user_token_account: &InterfaceAccount<'info, TokenAccount>,
+    vault_token_account: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
     amount: u64,
 ) -> Result<()> {
-    let cpi_accounts = Transfer {
-        from: user_token_account_for_token_a.to_account_info(),
-        to: vault_token_a_account.to_account_info(),
+    let cpi_accounts = TransferChecked {
+        from: user_token_account.to_account_info(),
+        mint: mint.to_account_info(),
+        to: vault_token_account.to_account_info(),
         authority: user.clone(),
     };
 
     let cpi_program = token_program.to_account_info();
 
     let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    token::transfer(cpi_ctx, amount)?;
+    token_interface::transfer_checked(cpi_ctx, amount, mint.decimals)?;
 
     Ok(())
 }
 
-// This function deposits the Token B from user to the token vault
-pub fn deposit_to_vault_token_b<'info>(
-    user: &AccountInfo<'info>,
-    user_token_account_for_token_b: &Account<'info, TokenAccount>,
-    vault_token_b_account: &Account<'info, TokenAccount>,
-    token_program: &Program<'info, Token>,
+// This function sends tokens from one of the pool vaults to a user.
+// `vault_seed` is the seed prefix of the vault PDA (b"vaultTokenA" or b"vaultTokenB"),
+// `pool` is the `Pool` or `ClmmPool` account the vault belongs to.
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_from_vault<'info>(
+    pool: &impl Key,
+    vault_seed: &[u8],
+    vault_auth: &AccountInfo<'info>,
+    vault_token_account: &InterfaceAccount<'info, TokenAccount>,
+    destination_token_account: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    vault_auth_bump: u8,
     amount: u64,
 ) -> Result<()> {
-    let cpi_accounts = Transfer {
-        from: user_token_account_for_token_b.to_account_info(),
-        to: vault_token_b_account.to_account_info(),
-        authority: user.to_account_info(),
-    };
-
-    let cpi_program = token_program.to_account_info();
-
-    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    token::transfer(cpi_ctx, amount)?;
-
-    Ok(())
-}
-
-// This function sends token A from Token Vault to User
-pub fn send_token_a_from_token_vault_to_user<'info>(
-    mint_a: &Account<'info, Mint>,
-    vault_auth_a: &AccountInfo<'info>,
-    vault_token_a_account: &Account<'info, TokenAccount>,
-    user_token_account_for_token_a: &Account<'info, TokenAccount>,
-    token_program: &Program<'info, Token>,
-    vault_auth_a_bump: u8,
-    tokenAmount: u64,
-) -> Result<()> {
-    let mint_a_key = mint_a.key();
+    let pool_key = pool.key();
 
-    let seeds = &[b"vaultTokenA", mint_a_key.as_ref(), &[vault_auth_a_bump]];
+    let seeds = &[vault_seed, pool_key.as_ref(), &[vault_auth_bump]];
 
     let signer = &[&seeds[..]];
 
-    let cpi_accounts = Transfer {
-        from: vault_token_a_account.to_account_info(),
-        to: user_token_account_for_token_a.to_account_info(),
-        authority: vault_auth_a.to_account_info(),
+    let cpi_accounts = TransferChecked {
+        from: vault_token_account.to_account_info(),
+        mint: mint.to_account_info(),
+        to: destination_token_account.to_account_info(),
+        authority: vault_auth.to_account_info(),
     };
 
     let cpi_program = token_program.to_account_info();
 
     let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
 
-    token::transfer(cpi_ctx, tokenAmount);
+    token_interface::transfer_checked(cpi_ctx, amount, mint.decimals)?;
 
     Ok(())
 }
 
-// This function sends token B from Token Vault to User
-pub fn send_token_b_from_token_vault_to_user<'info>(
-    mint_b: &Account<'info, Mint>,
-    vault_auth_b: &AccountInfo<'info>,
-    vault_token_b_account: &Account<'info, TokenAccount>,
-    user_token_account_for_token_b: &Account<'info, TokenAccount>,
-    token_program: &Program<'info, Token>,
-    vault_auth_b_bump: u8,
-    tokenAmount: u64,
-) -> Result<()> {
-    let mint_b_key = mint_b.key();
-
-    let seeds = &[b"vaultTokenB", mint_b_key.as_ref(), &[vault_auth_b_bump]];
+// Part of a transfer of `amount` the mint withholds in `epoch`. Only
+// Token-2022 mints with the transfer-fee extension charge one.
+pub fn transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64, epoch: u64) -> Result<u64> {
+    match transfer_fee_config(mint)? {
+        Some(config) => config
+            .calculate_epoch_fee(epoch, amount)
+            .ok_or(error!(TokenSwapError::CalculationError)),
+        None => Ok(0),
+    }
+}
 
-    let signer = &[&seeds[..]];
+// Fee to send on top of `amount` so that `amount` arrives
+pub fn inverse_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64, epoch: u64) -> Result<u64> {
+    match transfer_fee_config(mint)? {
+        Some(config) => config
+            .calculate_inverse_epoch_fee(epoch, amount)
+            .ok_or(error!(TokenSwapError::CalculationError)),
+        None => Ok(0),
+    }
+}
 
-    let cpi_accounts = Transfer {
-        from: vault_token_b_account.to_account_info(),
-        to: user_token_account_for_token_b.to_account_info(),
-        authority: vault_auth_b.to_account_info(),
-    };
+// Pools only hold mints whose transfers they can account for. A transfer
+// hook runs another program on every vault transfer, with accounts the
+// handlers don't pass, and a permanent delegate can move tokens out of the
+// vaults without the reserves knowing.
+pub fn check_supported_mint(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+
+    let extensions = mint_state.get_extension_types()?;
+    require!(
+        !extensions.iter().any(|extension| matches!(
+            extension,
+            ExtensionType::TransferHook | ExtensionType::PermanentDelegate
+        )),
+        TokenSwapError::UnsupportedMintExtension
+    );
 
-    let cpi_program = token_program.to_account_info();
+    Ok(())
+}
 
-    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+fn transfer_fee_config(mint: &InterfaceAccount<Mint>) -> Result<Option<TransferFeeConfig>> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(None);
+    }
 
-    token::transfer(cpi_ctx, tokenAmount);
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
 
-    Ok(())
-}
\ No newline at end of file
+    Ok(mint_state
+        .get_extension::<TransferFeeConfig>()
+        .ok()
+        .copied())
+}
@@ -1,85 +1,372 @@
 import * as anchor from "@coral-xyz/anchor";
 import * as web3 from "@solana/web3.js";
-import { CpiGuardLayout, createAssociatedTokenAccountInstruction, getAccount, getAssociatedTokenAddress } from "@solana/spl-token";
+import {
+  CpiGuardLayout,
+  ExtensionType,
+  TOKEN_2022_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createAssociatedTokenAccountInstruction,
+  createInitializeMintInstruction,
+  createInitializePermanentDelegateInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createInitializeTransferHookInstruction,
+  createMint,
+  getAccount,
+  getAssociatedTokenAddress,
+  getAssociatedTokenAddressSync,
+  getMintLen,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
 import { SimpleTokenSwap } from "../target/types/Simple_Token_Swap";
 import { BN } from "bn.js";
-import { bs58 } from "@coral-xyz/anchor/dist/cjs/utils/bytes";
 import { assert } from "chai";
 
-const base58PrivateKey = "";
-const privateKeySeed = bs58.decode(base58PrivateKey);
-
-const userKeyPair = web3.Keypair.fromSecretKey(privateKeySeed);
-
-const connection = new web3.Connection("https://api.devnet.solana.com", "confirmed");
-const userWallet = new anchor.Wallet(userKeyPair);
-const provider = new anchor.AnchorProvider(connection, userWallet, {
-  preflightCommitment: "confirmed",
-});
-anchor.setProvider(provider);
-// anchor.setProvider(anchor.AnchorProvider.env());
-
 describe("Test", () => {
+  // Configure the client to use the local cluster
+  anchor.setProvider(anchor.AnchorProvider.env());
 
   const program = anchor.workspace.SimpleTokenSwap as anchor.Program<SimpleTokenSwap>;
 
-  const tokenA_mint_address = new web3.PublicKey("GTha4aTjKC2odMHdbSPbNZYwnkaCbd1b5YBkUEPRMyyk");
-  const tokenB_mint_address = new web3.PublicKey("3kRHQT3z98KHDe5PHN2iMJgdEwKC6QgsWXjAHsbYjjmj");
+  // Pools keep their mints in ascending order
+  const [tokenA_mint_address, tokenB_mint_address] = [
+    new web3.PublicKey("GTha4aTjKC2odMHdbSPbNZYwnkaCbd1b5YBkUEPRMyyk"),
+    new web3.PublicKey("3kRHQT3z98KHDe5PHN2iMJgdEwKC6QgsWXjAHsbYjjmj"),
+  ].sort((x, y) => x.toBuffer().compare(y.toBuffer()));
 
-  const userPublicKey = new web3.PublicKey("HVw1Z2KFYfKjdL2UThi5RGBvSUpsF4zdsPrucV8TggQm");
+  const [pool] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("pool"), tokenA_mint_address.toBuffer(), tokenB_mint_address.toBuffer()],
+    program.programId
+  );
 
-  const [mint] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("mint")],
+  const [vault_token_account_a, bump1] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("vaultTokenA"), pool.toBuffer()],
     program.programId
   );
 
-  const [authorityPDA] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("authority")],
+  const [vault_token_account_b, bump2] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("vaultTokenB"), pool.toBuffer()],
     program.programId
   );
 
-  const [userPDALiquidity, bump] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("userliquidityPDA"), userPublicKey.toBuffer()],
+  const [vault_auth_a, bump3] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("vaultTokenA"), pool.toBuffer()],
     program.programId
   );
 
-  const [vault_token_account_a, bump1] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("vaultTokenA"), tokenA_mint_address.toBuffer()],
+  const [vault_auth_b, bump4] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("vaultTokenB"), pool.toBuffer()],
     program.programId
   );
 
-  const [vault_token_account_b, bump2] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("vaultTokenB"), tokenB_mint_address.toBuffer()],
+  const [observations] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("observations"), pool.toBuffer()],
     program.programId
   );
 
-  const [vault_auth_a, bump3] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("vaultTokenA"), tokenA_mint_address.toBuffer()],
+  const [userPDALiquidity, bump5] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("userliquidityPDA"), pool.toBuffer(), program.provider.publicKey.toBuffer()],
     program.programId
   );
 
-  const [vault_auth_b, bump4] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("vaultTokenB"), tokenB_mint_address.toBuffer()],
+  // Events the program emitted in a confirmed transaction, by name
+  const confirmedEvents = async (txHash: string) => {
+    const tx = await program.provider.connection.getTransaction(txHash, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    return [...parser.parseLogs(tx.meta.logMessages)];
+  };
+
+  const findEvent = (events: anchor.Event[], name: string) => {
+    const event = events.find((event) => event.name === name);
+    assert.isDefined(event, `${name} was not emitted`);
+    return event.data as any;
+  };
+
+  // Struct an instruction writes with set_return_data, read from a simulation
+  // of the instruction so the real transaction can be checked against it
+  const simulatedResult = async (builder: any, returnType: string) => {
+    const { raw } = await builder.simulate();
+    const prefix = `Program return: ${program.programId} `;
+    const log = raw.find((line: string) => line.startsWith(prefix));
+    assert.isDefined(log, "no return data");
+    return program.coder.types.decode(returnType, Buffer.from(log.slice(prefix.length), "base64"));
+  };
+
+  // Works for SPL Token and Token-2022 accounts alike
+  const tokenBalance = async (address: web3.PublicKey) =>
+    new BN((await program.provider.connection.getTokenAccountBalance(address)).value.amount);
+
+  // LP tokens the first deposit into a pool locks for good
+  const MINIMUM_LIQUIDITY = new BN(1_000);
+
+  const [config] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
     program.programId
   );
-  
+
+  it("initializes the program config", async () => {
+    const [programData] = web3.PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+
+    // The wallet deploying the program is its upgrade authority
+    const txHash = await program.methods
+      .initializeConfig(program.provider.publicKey)
+      .accounts({
+        config: config,
+        authority: program.provider.publicKey,
+        program: program.programId,
+        programData: programData,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const configAccount = await program.account.config.fetch(config);
+    assert.isTrue(configAccount.admin.equals(program.provider.publicKey));
+  });
+
+  it("keeps the program admin until a proposed one accepts", async () => {
+    const newAdmin = web3.Keypair.generate();
+
+    await program.methods
+      .setConfigAdmin(newAdmin.publicKey)
+      .accounts({ admin: program.provider.publicKey, config: config })
+      .rpc();
+
+    let configAccount = await program.account.config.fetch(config);
+    assert.isTrue(configAccount.admin.equals(program.provider.publicKey));
+    assert.isTrue(configAccount.pendingAdmin.equals(newAdmin.publicKey));
+
+    // Proposing again replaces the pending admin, here with the current one
+    await program.methods
+      .setConfigAdmin(program.provider.publicKey)
+      .accounts({ admin: program.provider.publicKey, config: config })
+      .rpc();
+
+    try {
+      await program.methods
+        .acceptConfigAdmin()
+        .accounts({ pendingAdmin: newAdmin.publicKey, config: config })
+        .signers([newAdmin])
+        .rpc();
+      assert.fail("accept_config_admin should fail for a replaced proposal");
+    } catch (err) {
+      assert.include(err.toString(), "UnauthorizedPendingAdmin");
+    }
+
+    const txHash = await program.methods
+      .acceptConfigAdmin()
+      .accounts({ pendingAdmin: program.provider.publicKey, config: config })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    configAccount = await program.account.config.fetch(config);
+    assert.isTrue(configAccount.admin.equals(program.provider.publicKey));
+    assert.isTrue(configAccount.pendingAdmin.equals(web3.PublicKey.default));
+  });
+
+  it("initializes a pool", async () => {
+    const fee_bps = 30;
+
+    const txHash = await program.methods
+      .initializePool(fee_bps, { constantProduct: {} }, new BN(0), new BN(0))
+      .accounts({
+        pool: pool,
+        observations: observations,
+        mintA: tokenA_mint_address,
+        mintB: tokenB_mint_address,
+        config: config,
+        admin: program.provider.publicKey,
+        payer: program.provider.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const created = findEvent(await confirmedEvents(txHash), "poolCreatedEvent");
+    assert.isTrue(created.pool.equals(pool));
+    assert.isTrue(created.mintA.equals(tokenA_mint_address));
+    assert.isTrue(created.mintB.equals(tokenB_mint_address));
+    assert.equal(created.feeBps, fee_bps);
+    assert.isTrue(created.admin.equals(program.provider.publicKey));
+  });
+
+  it("updates the pool swap fee", async () => {
+    const fee_bps = 25;
+
+    const txHash = await program.methods
+      .setFeeBps(fee_bps)
+      .accounts({
+        admin: program.provider.publicKey,
+        pool: pool,
+      })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const poolAccount = await program.account.pool.fetch(pool);
+    assert.equal(poolAccount.feeBps, fee_bps);
+  });
+
+  it("rejects an amplification ramp on a constant-product pool", async () => {
+    const ramp_stop_time = new BN(Math.floor(Date.now() / 1000) + 2 * 86_400);
+
+    try {
+      await program.methods
+        .rampAmp(new BN(200), ramp_stop_time)
+        .accounts({
+          admin: program.provider.publicKey,
+          pool: pool,
+        })
+        .rpc();
+      assert.fail("ramp_amp should fail on a constant-product pool");
+    } catch (err) {
+      assert.include(err.toString(), "UnsupportedCurve");
+    }
+  });
+
+  it("hands the pool admin over and back", async () => {
+    const newAdmin = web3.Keypair.generate();
+
+    await program.methods
+      .setAdmin(newAdmin.publicKey)
+      .accounts({
+        admin: program.provider.publicKey,
+        pool: pool,
+      })
+      .rpc();
+
+    // Until the proposed admin accepts, the current one stays in charge
+    let poolAccount = await program.account.pool.fetch(pool);
+    assert.isTrue(poolAccount.admin.equals(program.provider.publicKey));
+    assert.isTrue(poolAccount.pendingAdmin.equals(newAdmin.publicKey));
+
+    // Nobody else can accept in its place
+    try {
+      await program.methods
+        .acceptAdmin()
+        .accounts({
+          pendingAdmin: program.provider.publicKey,
+          pool: pool,
+        })
+        .rpc();
+      assert.fail("accept_admin should fail for anyone but the pending admin");
+    } catch (err) {
+      assert.include(err.toString(), "UnauthorizedPendingAdmin");
+    }
+
+    await program.methods
+      .acceptAdmin()
+      .accounts({
+        pendingAdmin: newAdmin.publicKey,
+        pool: pool,
+      })
+      .signers([newAdmin])
+      .rpc();
+
+    // The previous admin lost its rights over the pool
+    try {
+      await program.methods
+        .setFeeBps(30)
+        .accounts({
+          admin: program.provider.publicKey,
+          pool: pool,
+        })
+        .rpc();
+      assert.fail("set_fee_bps should fail for the previous admin");
+    } catch (err) {
+      assert.include(err.toString(), "Unauthorized");
+    }
+
+    await program.methods
+      .setAdmin(program.provider.publicKey)
+      .accounts({
+        admin: newAdmin.publicKey,
+        pool: pool,
+      })
+      .signers([newAdmin])
+      .rpc();
+
+    const txHash = await program.methods
+      .acceptAdmin()
+      .accounts({
+        pendingAdmin: program.provider.publicKey,
+        pool: pool,
+      })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    poolAccount = await program.account.pool.fetch(pool);
+    assert.isTrue(poolAccount.admin.equals(program.provider.publicKey));
+    assert.isTrue(poolAccount.pendingAdmin.equals(web3.PublicKey.default));
+  });
+
+  it("sets the protocol fee", async () => {
+    const protocol_fee_bps = 1_667;
+
+    const txHash = await program.methods
+      .setProtocolFee(protocol_fee_bps, program.provider.publicKey)
+      .accounts({
+        admin: program.provider.publicKey,
+        pool: pool,
+      })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const poolAccount = await program.account.pool.fetch(pool);
+    assert.equal(poolAccount.protocolFeeBps, protocol_fee_bps);
+  });
+
   it("initializes a Vault Account For Token A", async () => {
     const [vault_token_account, bump1] = await web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("vaultTokenA"), tokenA_mint_address.toBuffer()],
+      [Buffer.from("vaultTokenA"), pool.toBuffer()],
       program.programId
     );
 
     const [vaultPDA, bump2] = await web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("vaultTokenA"), tokenA_mint_address.toBuffer()],
+      [Buffer.from("vaultTokenA"), pool.toBuffer()],
       program.programId
     );
 
+    console.log("This is the Token vault_token_account for Token A: ", vault_token_account.toString());
+    console.log("This is the vaultPDA for Token A: ", vaultPDA.toString());
+
     // Send Transaction
     const txHash = await program.methods
       .initializeVaultTokenA()
       .accounts({
         vaultTokenAccount: vault_token_account,
         vault_auth: vaultPDA,
+        pool: pool,
+        admin: program.provider.publicKey,
         payer: program.provider.publicKey,
         mint: tokenA_mint_address,
         systemProgram: web3.SystemProgram.programId,
@@ -96,12 +383,12 @@ describe("Test", () => {
 
   it("initializes a Vault Account For Token B", async () => {
     const [vault_token_account, bump1] = await web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("vaultTokenB"), tokenB_mint_address.toBuffer()],
+      [Buffer.from("vaultTokenB"), pool.toBuffer()],
       program.programId
     )
 
     const [vaultPDA, bump2] = await web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("vaultTokenB"), tokenB_mint_address.toBuffer()],
+      [Buffer.from("vaultTokenB"), pool.toBuffer()],
       program.programId
     );
 
@@ -111,6 +398,8 @@ describe("Test", () => {
       .accounts({
         vaultTokenAccount: vault_token_account,
         vault_auth: vaultPDA,
+        pool: pool,
+        admin: program.provider.publicKey,
         payer: program.provider.publicKey,
         mint: tokenB_mint_address,
         systemProgram: web3.SystemProgram.programId,
@@ -128,6 +417,38 @@ describe("Test", () => {
     console.log("Vault Token B Account Balance: ", pda_token_value.amount.toString());
   });
 
+  it("initializes a liquidity account", async () => {
+
+    const [userPDALiquidity, bump] = await web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("userliquidityPDA"), pool.toBuffer(), program.provider.publicKey.toBuffer()],
+      program.programId
+    );
+
+    const accountInfo = await program.provider.connection.getAccountInfo(userPDALiquidity);
+
+    console.log("This is the user liquidity PDA: ", userPDALiquidity.toBase58());
+
+    if (accountInfo) {
+      console.log("User Liquidity account is already initialized");
+      return;
+    }
+
+    const txHash = await program.methods
+      .initializeUserLiquidityAccount()
+      .accounts({
+        user: program.provider.publicKey,
+        userPdaAccount: userPDALiquidity,
+        pool: pool,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+  });
+
   it("Creates a Token Mint", async () => {
     const METADATA_SEED = "metadata";
     const TOKEN_METADATA_PROGRAM_ID = new web3.PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
@@ -140,12 +461,12 @@ describe("Test", () => {
     }
 
     const [mint] = web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("mint")],
+      [Buffer.from("mint"), pool.toBuffer()],
       program.programId
-    );
+  );
 
     const [authorityPDA] = web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("authority")],
+      [Buffer.from("authority"), pool.toBuffer()],
       program.programId
     );
 
@@ -164,6 +485,8 @@ describe("Test", () => {
         metadata: metadataAddress,
         mint: mint,
         authority: authorityPDA,
+        pool: pool,
+        admin: program.provider.publicKey,
         payer: program.provider.publicKey,
         rent: web3.SYSVAR_RENT_PUBKEY,
         systemProgram: web3.SystemProgram.programId,
@@ -178,384 +501,1901 @@ describe("Test", () => {
     await program.provider.connection.confirmTransaction(txHash);
   });
 
-  it("initializes a liquidity account", async () => {
+  it("adds liquidity to the liquidity pool", async () => {
 
-    const [userPDALiquidity, bump] = await web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("userliquidityPDA"), userPublicKey.toBuffer()],
+    const [mint] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("mint"), pool.toBuffer()],
       program.programId
     );
 
-    const accountInfo = await program.provider.connection.getAccountInfo(userPDALiquidity);
-
-    if (accountInfo) {
-      console.log("User Liquidity account is already initialized");
-      return;
-    }
-
-    const txHash = await program.methods
-      .initializeUserLiquidityAccount()
-      .accounts({
-        user: userPublicKey,
-        userPdaAccount: userPDALiquidity,
-        systemProgram: web3.SystemProgram.programId,
-      })
-      .signers([userKeyPair])
-      .rpc();
-
-    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
-
-    // Confirm Transaction
-    await program.provider.connection.confirmTransaction(txHash);
-  });
+    const [authorityPDA] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("authority"), pool.toBuffer()],
+      program.programId
+    );
 
-  it("adds liquidity to the liquidity pool", async () => {
+    console.log("This is the Mint: ", mint.toBase58());
 
     // Deriving user Token ATA
     const user_token_a_ata = await getAssociatedTokenAddress(
       tokenA_mint_address,
-      userPublicKey
+      program.provider.publicKey
     );
 
     const user_token_b_ata = await getAssociatedTokenAddress(
       tokenB_mint_address,
-      userPublicKey
+      program.provider.publicKey
     );
 
     const destination = await anchor.utils.token.associatedAddress({
       mint: mint,
-      owner: userPublicKey
+      owner: program.provider.publicKey
     });
 
     const token_amount = new BN(5_000_000_000);
+    const min_lp_out = new BN(0);
 
-    const txHash = await program.methods
-      .addLiquidity(token_amount)
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
+
+    const builder = program.methods
+      .addLiquidity(token_amount, token_amount, min_lp_out, deadline)
       .accounts({
-        user: userPublicKey,
+        user: program.provider.publicKey,
         userPdaAccount: userPDALiquidity,
+        pool: pool,
         userTokenAccountForTokenA: user_token_a_ata,
         userTokenAccountForTokenB: user_token_b_ata,
         vaultTokenAAccount: vault_token_account_a,
         vaultTokenBAccount: vault_token_account_b,
         vaultAuthA: vault_auth_a,
         vaultAuthB: vault_auth_b,
+        observations: observations,
         mintA: tokenA_mint_address,
         mintB: tokenB_mint_address,
         mint: mint,
-        destination: destination,
-        destinationOwner: userPublicKey,
         authority: authorityPDA,
+        destination: destination,
+        destinationOwner: program.provider.publicKey,
+        payer: program.provider.publicKey,
         rent: web3.SYSVAR_RENT_PUBKEY,
         systemProgram: web3.SystemProgram.programId,
         tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
       })
-      .signers([userKeyPair])
-      .rpc();
+
+    const result = await simulatedResult(builder, "liquidityResult");
+    const lpBefore = (await program.provider.connection.getAccountInfo(destination))
+      ? await tokenBalance(destination)
+      : new BN(0);
+
+    const txHash = await builder.rpc();
 
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
     await program.provider.connection.confirmTransaction(txHash);
 
-    const userLiquidityInfo = await program.account.liquidityAccount.fetch(userPDALiquidity);
-    console.log("This is the userLiquidity info:  ", userLiquidityInfo.stakedTokenAmount.toNumber());
+    const added = findEvent(await confirmedEvents(txHash), "liquidityAddedEvent");
+    assert.isTrue(added.amountA.eq(result.amountA));
+    assert.isTrue(added.amountB.eq(result.amountB));
+    assert.isTrue(added.lpTokensMinted.eq(result.lpTokens));
+    assert.isTrue((await tokenBalance(destination)).sub(lpBefore).eq(result.lpTokens));
+
+    const poolAccount = await program.account.pool.fetch(pool);
+    assert.isTrue(poolAccount.reserveA.eq(added.reserveA));
+    assert.isTrue(poolAccount.lpSupply.eq(added.lpSupply));
 
-    // Assertions
-    assert.equal(userLiquidityInfo.stakedTokenAmount.toNumber(), 5000000000);
+    // The first deposit locks part of the supply that nobody holds
+    assert.isTrue(added.lpSupply.eq(result.lpTokens.add(MINIMUM_LIQUIDITY)));
   });
 
-  it("removes liquidity from the liquidity pool", async () => {
+  it("keeps a fresh deposit locked up until the admin lifts the lockup", async () => {
+    const [mint] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("mint"), pool.toBuffer()],
+      program.programId
+    );
+
+    const [authorityPDA] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("authority"), pool.toBuffer()],
+      program.programId
+    );
 
     const user_token_a_ata = await getAssociatedTokenAddress(
       tokenA_mint_address,
-      userPublicKey
+      program.provider.publicKey
     );
 
     const user_token_b_ata = await getAssociatedTokenAddress(
       tokenB_mint_address,
-      userPublicKey
+      program.provider.publicKey
     );
 
     const destination = await anchor.utils.token.associatedAddress({
       mint: mint,
-      owner: userPublicKey
+      owner: program.provider.publicKey
     });
 
-    const token_amount = new BN(1_000_000_000);
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
+
+    // The deposit above is younger than the default lockup
+    try {
+      await program.methods
+        .removeLiquidity(new BN(1), new BN(0), new BN(0), deadline)
+        .accounts({
+          user: program.provider.publicKey,
+          userPdaAccount: userPDALiquidity,
+          pool: pool,
+          userTokenAccountForTokenA: user_token_a_ata,
+          userTokenAccountForTokenB: user_token_b_ata,
+          vaultTokenAAccount: vault_token_account_a,
+          vaultTokenBAccount: vault_token_account_b,
+          vaultAuthA: vault_auth_a,
+          vaultAuthB: vault_auth_b,
+          observations: observations,
+          mintA: tokenA_mint_address,
+          mintB: tokenB_mint_address,
+          mint: mint,
+          authority: authorityPDA,
+          destination: destination,
+          destinationOwner: program.provider.publicKey,
+          payer: program.provider.publicKey,
+          rent: web3.SYSVAR_RENT_PUBKEY,
+          systemProgram: web3.SystemProgram.programId,
+          tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+          tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+          tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        })
+        .rpc();
+      assert.fail("removeLiquidity should fail during the lockup");
+    } catch (err) {
+      assert.include(err.toString(), "TimeConstraint");
+    }
+
+    try {
+      await program.methods
+        .setLockupDuration(new BN(-1))
+        .accounts({
+          admin: program.provider.publicKey,
+          pool: pool,
+        })
+        .rpc();
+      assert.fail("set_lockup_duration should reject a negative lockup");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidLockupDuration");
+    }
 
     const txHash = await program.methods
-      .removeLiquidity(token_amount)
+      .setLockupDuration(new BN(0))
+      .accounts({
+        admin: program.provider.publicKey,
+        pool: pool,
+      })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const poolAccount = await program.account.pool.fetch(pool);
+    assert.isTrue(poolAccount.lockupDuration.eqn(0));
+  });
+
+  it("removes liquidity from the liquidity pool", async () => {
+
+    const [mint] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("mint"), pool.toBuffer()],
+      program.programId
+    );
+
+    const [authorityPDA] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("authority"), pool.toBuffer()],
+      program.programId
+    );
+
+    console.log("This is the Mint: ", mint.toBase58());
+
+    const user_token_a_ata = await getAssociatedTokenAddress(
+      tokenA_mint_address,
+      program.provider.publicKey
+    );
+
+    const user_token_b_ata = await getAssociatedTokenAddress(
+      tokenB_mint_address,
+      program.provider.publicKey
+    );
+
+    const destination = await anchor.utils.token.associatedAddress({
+      mint: mint,
+      owner: program.provider.publicKey
+    });
+
+    // Only part of the LP supply, the swap tests below still need reserves
+    const token_amount = (await tokenBalance(destination)).divn(5);
+    const min_amount_a = new BN(0);
+    const min_amount_b = new BN(0);
+
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
+
+    const builder = program.methods
+      .removeLiquidity(token_amount, min_amount_a, min_amount_b, deadline)
       .accounts({
-        user: userPublicKey,
+        user: program.provider.publicKey,
         userPdaAccount: userPDALiquidity,
+        pool: pool,
         userTokenAccountForTokenA: user_token_a_ata,
         userTokenAccountForTokenB: user_token_b_ata,
         vaultTokenAAccount: vault_token_account_a,
         vaultTokenBAccount: vault_token_account_b,
         vaultAuthA: vault_auth_a,
         vaultAuthB: vault_auth_b,
+        observations: observations,
         mintA: tokenA_mint_address,
         mintB: tokenB_mint_address,
         mint: mint,
         authority: authorityPDA,
         destination: destination,
-        destinationOwner: userPublicKey,
+        destinationOwner: program.provider.publicKey,
+        payer: program.provider.publicKey,
         rent: web3.SYSVAR_RENT_PUBKEY,
         systemProgram: web3.SystemProgram.programId,
         tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
-      })
-      .signers([userKeyPair])
-      .rpc();
+      });
+
+    const result = await simulatedResult(builder, "liquidityResult");
+    const balanceABefore = await tokenBalance(user_token_a_ata);
+    const balanceBBefore = await tokenBalance(user_token_b_ata);
+
+    const txHash = await builder.rpc();
 
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
     await program.provider.connection.confirmTransaction(txHash);
 
-    const userLiquidityInfo = await program.account.liquidityAccount.fetch(userPDALiquidity);
-    console.log("This is the userLiquidityInfo is: ", userLiquidityInfo.stakedTokenAmount.toNumber());
+    const removed = findEvent(await confirmedEvents(txHash), "liquidityRemovedEvent");
+    assert.isTrue(removed.lpTokensBurned.eq(token_amount));
+    assert.isTrue(removed.amountA.eq(result.amountA));
+    assert.isTrue(removed.amountB.eq(result.amountB));
+    assert.isTrue((await tokenBalance(user_token_a_ata)).sub(balanceABefore).eq(result.amountA));
+    assert.isTrue((await tokenBalance(user_token_b_ata)).sub(balanceBBefore).eq(result.amountB));
 
-    // Assertions
-    assert.equal(userLiquidityInfo.stakedTokenAmount.toNumber(), 0);
+    const poolAccount = await program.account.pool.fetch(pool);
+    assert.isTrue(poolAccount.reserveA.gtn(0));
+    assert.isTrue(poolAccount.reserveB.gtn(0));
   })
 
-  it("Swap Token A for Token B for a user", async () => {
-    const Token_A = await getAccount(program.provider.connection, vault_token_account_a);
-    const Token_A_Quantity = parseInt(Token_A.amount.toString(), 10);
+  it("Swap Token B for Token A", async () => {
 
-    const Token_B = await getAccount(program.provider.connection, vault_token_account_b);
-    const Token_B_Quantity = parseInt(Token_B.amount.toString(), 10);
-
-    const Price_Of_Token_A = Token_B_Quantity / Token_A_Quantity;
-    const Price_of_Token_B = Token_A_Quantity / Token_B_Quantity;
-
-    console.log("This is the new price of Token A: ", Price_Of_Token_A);
-    console.log("This is the new price of Token B: ", Price_of_Token_B);
-
-    const userSlippageTolerancePercent = 1;
+    // Deriving user ATA for Token B
+    const destination = await getAssociatedTokenAddress(
+      tokenB_mint_address,
+      program.provider.publicKey
+    );
 
-    const userPublicKey = new web3.PublicKey("HVw1Z2KFYfKjdL2UThi5RGBvSUpsF4zdsPrucV8TggQm");
+    const userATAforTokenB = destination.toBase58();
 
     // Deriving user ATA for Token A
     const destination_token_a = await getAssociatedTokenAddress(
       tokenA_mint_address,
-      userPublicKey
+      program.provider.publicKey
     );
 
     const userATAforTokenA = destination_token_a.toBase58();
 
-    // Deriving user ATA for Token B
-    const destination = await getAssociatedTokenAddress(
-      tokenB_mint_address,
-      userPublicKey
-    );
+    const amount = new BN(10_000_000_000);
 
-    // Check if the Token_B ATA is initialized or not
-    const ataAccountInfo = await program.provider.connection.getAccountInfo(destination);
+    // Expected output and fee straight from the pool, 1% slippage on top
+    const quote = await program.methods
+      .quoteSwap(amount, { bToA: {} })
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
+    const minExpectedOutput = quote.amountOut.muln(99).divn(100);
 
-    if (ataAccountInfo && ataAccountInfo.data.length > 0) {
-      console.log("ATA is already initialized");
-    } else {
-      console.log("Initializing ATA");
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
 
-      // Create associated token account if it doesn't exist
-      const ataIx = createAssociatedTokenAccountInstruction(
-        program.provider.publicKey,     // payer
-        destination,               // ata to be created
-        userPublicKey,                  // token account owner
-        tokenB_mint_address             // mint
-      );
+    const builder = program.methods
+      .swap(amount, minExpectedOutput, { bToA: {} }, deadline)
+      .accounts({
+        user: program.provider.publicKey,
+        pool: pool,
+        userTokenAccountForTokenA: userATAforTokenA,
+        userTokenAccountForTokenB: userATAforTokenB,
+        vaultTokenAAccount: vault_token_account_a,
+        vaultTokenBAccount: vault_token_account_b,
+        vaultAuthA: vault_auth_a,
+        vaultAuthB: vault_auth_b,
+        observations: observations,
+        mintA: tokenA_mint_address,
+        mintB: tokenB_mint_address,
+        tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+      });
 
-      const tx = new web3.Transaction().add(ataIx);
+    const result = await simulatedResult(builder, "swapResult");
+    const balanceBefore = await tokenBalance(destination_token_a);
 
-      await program.provider.sendAndConfirm(tx);
-    }
+    const txHash = await builder.rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const swapped = findEvent(await confirmedEvents(txHash), "swapEvent");
+    assert.isTrue(swapped.amountIn.eq(result.amountIn));
+    assert.isTrue(swapped.amountOut.eq(result.amountOut));
+    assert.isTrue(swapped.fee.eq(result.fee));
+    assert.isTrue(swapped.reserveA.eq(result.reserveA));
+    assert.isTrue(swapped.reserveB.eq(result.reserveB));
+    assert.isTrue(result.amountOut.eq(quote.amountOut));
+    assert.isTrue(result.fee.eq(quote.fee));
+    assert.isTrue((await tokenBalance(destination_token_a)).sub(balanceBefore).eq(result.amountOut));
+  })
+
+  it("Swap Token A for Token B", async () => {
+
+    // Deriving user ATA for Token A
+    const destination_token_a = await getAssociatedTokenAddress(
+      tokenA_mint_address,
+      program.provider.publicKey
+    );
+
+    const userATAforTokenA = destination_token_a.toBase58();
+
+    // Deriving user ATA for Token B
+    const destination = await getAssociatedTokenAddress(
+      tokenB_mint_address,
+      program.provider.publicKey
+    );
 
     const userATAforTokenB = destination.toBase58();
 
-    const vaultTokenAVault = await getAccount(program.provider.connection, vault_token_account_a);
-    const vaultTokenBVault = await getAccount(program.provider.connection, vault_token_account_b);
+    const amount = new BN(10_000_000_000);
 
-    console.log("This is the value of vault_token_account_a: ", vaultTokenAVault.amount.toString());
-    console.log("This is the value of vault_token_account_b: ", vaultTokenBVault.amount.toString());
+    // Expected output and fee straight from the pool, 1% slippage on top
+    const quote = await program.methods
+      .quoteSwap(amount, { aToB: {} })
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
+    const minExpectedOutput = quote.amountOut.muln(99).divn(100);
 
-    const amount = new BN(1_000_000_000);
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
 
-    const expectedOutput = amount.mul(new BN(Price_of_Token_B * 1000)).divn(1000);
+    const builder = program.methods
+      .swap(amount, minExpectedOutput, { aToB: {} }, deadline)
+      .accounts({
+        user: program.provider.publicKey,
+        pool: pool,
+        userTokenAccountForTokenA: userATAforTokenA,
+        userTokenAccountForTokenB: userATAforTokenB,
+        vaultTokenAAccount: vault_token_account_a,
+        vaultTokenBAccount: vault_token_account_b,
+        vaultAuthA: vault_auth_a,
+        vaultAuthB: vault_auth_b,
+        observations: observations,
+        mintA: tokenA_mint_address,
+        mintB: tokenB_mint_address,
+        tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+      });
 
-    const swapFees = 0.003;
+    const result = await simulatedResult(builder, "swapResult");
+    const balanceBefore = await tokenBalance(destination);
 
-    const expectedOutput_With_Swap_Fees = expectedOutput.muln(1 - swapFees);
+    const txHash = await builder.rpc();
 
-    const slippageMultiplier = 1 - userSlippageTolerancePercent;
-    const minExpectedOutput = expectedOutput_With_Swap_Fees.muln(slippageMultiplier * 1000).divn(10000);
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
-    const txHash = await program.methods
-      .swapAForB(amount, minExpectedOutput)
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const swapped = findEvent(await confirmedEvents(txHash), "swapEvent");
+    assert.isTrue(swapped.amountIn.eq(result.amountIn));
+    assert.isTrue(swapped.amountOut.eq(result.amountOut));
+    assert.isTrue(swapped.fee.eq(result.fee));
+    assert.isTrue(swapped.reserveA.eq(result.reserveA));
+    assert.isTrue(swapped.reserveB.eq(result.reserveB));
+    assert.isTrue(result.amountOut.eq(quote.amountOut));
+    assert.isTrue(result.fee.eq(quote.fee));
+    assert.isTrue((await tokenBalance(destination)).sub(balanceBefore).eq(result.amountOut));
+  });
+
+  it("Swap Token A for an exact amount of Token B", async () => {
+    const userATAforTokenA = await getAssociatedTokenAddress(
+      tokenA_mint_address,
+      program.provider.publicKey
+    );
+
+    const userATAforTokenB = await getAssociatedTokenAddress(
+      tokenB_mint_address,
+      program.provider.publicKey
+    );
+
+    const amount_out = new BN(1_000_000_000);
+    const max_amount_in = new BN(2_000_000_000);
+
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
+
+    const builder = program.methods
+      .swapExactOut(amount_out, max_amount_in, { aToB: {} }, deadline)
       .accounts({
-        user: userPublicKey,
+        user: program.provider.publicKey,
+        pool: pool,
         userTokenAccountForTokenA: userATAforTokenA,
         userTokenAccountForTokenB: userATAforTokenB,
         vaultTokenAAccount: vault_token_account_a,
         vaultTokenBAccount: vault_token_account_b,
         vaultAuthA: vault_auth_a,
         vaultAuthB: vault_auth_b,
+        observations: observations,
         mintA: tokenA_mint_address,
         mintB: tokenB_mint_address,
-        tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+      });
+
+    const result = await simulatedResult(builder, "swapResult");
+    const balanceBefore = await tokenBalance(userATAforTokenB);
+
+    const txHash = await builder.rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const swapped = findEvent(await confirmedEvents(txHash), "swapEvent");
+    assert.isTrue(swapped.amountOut.eq(amount_out));
+    assert.isTrue(swapped.amountIn.eq(result.amountIn));
+    assert.isTrue(result.amountIn.lte(max_amount_in));
+    assert.isTrue((await tokenBalance(userATAforTokenB)).sub(balanceBefore).eq(amount_out));
+  });
+
+  it("Swap Token A for Token B through a route of pools", async () => {
+    const userATAforTokenA = await getAssociatedTokenAddress(
+      tokenA_mint_address,
+      program.provider.publicKey
+    );
+
+    const userATAforTokenB = await getAssociatedTokenAddress(
+      tokenB_mint_address,
+      program.provider.publicKey
+    );
+
+    const amount_in = new BN(1_000_000_000);
+    const min_amount_out = new BN(0);
+
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
+
+    // One [pool, vault_in, vault_out, observations, mint_out, token_program_out] group per hop
+    const builder = program.methods
+      .swapRoute(amount_in, min_amount_out, deadline)
+      .accounts({
+        user: program.provider.publicKey,
+        userSourceTokenAccount: userATAforTokenA,
+        userDestinationTokenAccount: userATAforTokenB,
+        sourceMint: tokenA_mint_address,
+        sourceTokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
       })
-      .signers([userKeyPair])
-      .rpc();
+      .remainingAccounts([
+        { pubkey: pool, isWritable: true, isSigner: false },
+        { pubkey: vault_token_account_a, isWritable: true, isSigner: false },
+        { pubkey: vault_token_account_b, isWritable: true, isSigner: false },
+        { pubkey: observations, isWritable: true, isSigner: false },
+        { pubkey: tokenB_mint_address, isWritable: false, isSigner: false },
+        { pubkey: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"), isWritable: false, isSigner: false },
+      ]);
+
+    const result = await simulatedResult(builder, "routeSwapResult");
+    const balanceBefore = await tokenBalance(userATAforTokenB);
+
+    const txHash = await builder.rpc();
 
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
     await program.provider.connection.confirmTransaction(txHash);
+
+    const result_hops = (await confirmedEvents(txHash)).filter((event) => event.name === "swapEvent");
+    assert.equal(result_hops.length, result.hops.length);
+    assert.isTrue((result_hops[0].data as any).amountIn.eq(amount_in));
+    assert.isTrue((await tokenBalance(userATAforTokenB)).sub(balanceBefore).eq(result.amountOut));
   });
 
-  it("Swap Token B for Token A for a user", async () => {
-    const Token_A = await getAccount(program.provider.connection, vault_token_account_a);
+  it("Swap Token A for a third token through two pools", async () => {
+    const tokenProgram = new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+    const payer = (program.provider as anchor.AnchorProvider).wallet.payer;
+    const connection = program.provider.connection;
+    const user = program.provider.publicKey;
+
+    // Token C only trades against Token B, so A reaches it through the A/B pool
+    const tokenC_mint_address = await createMint(connection, payer, user, null, 9);
+    const userATAforTokenC = (
+      await getOrCreateAssociatedTokenAccount(connection, payer, tokenC_mint_address, user)
+    ).address;
+    await mintTo(connection, payer, tokenC_mint_address, userATAforTokenC, payer, 10_000_000_000);
+
+    const [mintX, mintY] = [tokenB_mint_address, tokenC_mint_address].sort((x, y) =>
+      x.toBuffer().compare(y.toBuffer())
+    );
+    const pda = (seed: string, key: web3.PublicKey) =>
+      web3.PublicKey.findProgramAddressSync([Buffer.from(seed), key.toBuffer()], program.programId)[0];
 
-    const Token_B = await getAccount(program.provider.connection, vault_token_account_b);
+    const [poolBC] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("pool"), mintX.toBuffer(), mintY.toBuffer()],
+      program.programId
+    );
+    const vaultX = pda("vaultTokenA", poolBC);
+    const vaultY = pda("vaultTokenB", poolBC);
+    const observationsBC = pda("observations", poolBC);
+    const lpMint = pda("mint", poolBC);
+    const lpAuthority = pda("authority", poolBC);
+
+    await program.methods
+      .initializePool(30, { constantProduct: {} }, new BN(0), new BN(0))
+      .accounts({
+        pool: poolBC,
+        observations: observationsBC,
+        mintA: mintX,
+        mintB: mintY,
+        config: config,
+        admin: user,
+        payer: user,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
 
-    const Token_A_Quantity = parseInt(Token_A.amount.toString(), 10);
-    const Token_B_Quantity = parseInt(Token_B.amount.toString(), 10);
+    for (const [method, vault, vaultMint] of [
+      ["initializeVaultTokenA", vaultX, mintX],
+      ["initializeVaultTokenB", vaultY, mintY],
+    ] as const) {
+      await program.methods[method]()
+        .accounts({
+          vaultTokenAccount: vault,
+          vaultAuth: vault,
+          pool: poolBC,
+          admin: user,
+          payer: user,
+          mint: vaultMint,
+          systemProgram: web3.SystemProgram.programId,
+          tokenProgram: tokenProgram,
+          rent: web3.SYSVAR_RENT_PUBKEY,
+        })
+        .rpc();
+    }
 
-    const Price_Of_Token_A = Token_B_Quantity / Token_A_Quantity;
-    const Price_Of_Token_B = Token_A_Quantity / Token_B_Quantity;
+    const TOKEN_METADATA_PROGRAM_ID = new web3.PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+    const [metadataAddress] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), lpMint.toBuffer()],
+      TOKEN_METADATA_PROGRAM_ID
+    );
 
-    const userSlippageTolerancePercent = 1;
+    await program.methods
+      .createTokenMint({ name: "LP Token", symbol: "LP", uri: "https://jsonkeeper.com/b/7G05", decimals: 9 })
+      .accounts({
+        metadata: metadataAddress,
+        mint: lpMint,
+        authority: lpAuthority,
+        pool: poolBC,
+        admin: user,
+        payer: user,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+        systemProgram: web3.SystemProgram.programId,
+        tokenProgram: tokenProgram,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+      })
+      .rpc();
 
-    const userPublicKey = new web3.PublicKey("HVw1Z2KFYfKjdL2UThi5RGBvSUpsF4zdsPrucV8TggQm");
+    const [userLiquidityBC] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("userliquidityPDA"), poolBC.toBuffer(), user.toBuffer()],
+      program.programId
+    );
 
-    // Deriving user ATA for Token B
-    const destination = await getAssociatedTokenAddress(
-      tokenB_mint_address,
-      userPublicKey
+    await program.methods
+      .initializeUserLiquidityAccount()
+      .accounts({
+        user: user,
+        userPdaAccount: userLiquidityBC,
+        pool: poolBC,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
+
+    await program.methods
+      .addLiquidity(new BN(5_000_000_000), new BN(5_000_000_000), new BN(0), deadline)
+      .accounts({
+        user: user,
+        userPdaAccount: userLiquidityBC,
+        pool: poolBC,
+        userTokenAccountForTokenA: await getAssociatedTokenAddress(mintX, user),
+        userTokenAccountForTokenB: await getAssociatedTokenAddress(mintY, user),
+        vaultTokenAAccount: vaultX,
+        vaultTokenBAccount: vaultY,
+        vaultAuthA: vaultX,
+        vaultAuthB: vaultY,
+        observations: observationsBC,
+        mintA: mintX,
+        mintB: mintY,
+        mint: lpMint,
+        authority: lpAuthority,
+        destination: await getAssociatedTokenAddress(lpMint, user),
+        destinationOwner: user,
+        payer: user,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+        systemProgram: web3.SystemProgram.programId,
+        tokenProgram: tokenProgram,
+        tokenProgramA: tokenProgram,
+        tokenProgramB: tokenProgram,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      })
+      .rpc();
+
+    // Token B is the input of the second pool, whichever side it sorted to
+    const [vaultBInPoolBC, vaultCInPoolBC] = mintX.equals(tokenB_mint_address)
+      ? [vaultX, vaultY]
+      : [vaultY, vaultX];
+
+    const amount_in = new BN(1_000_000_000);
+
+    const builder = program.methods
+      .swapRoute(amount_in, new BN(0), deadline)
+      .accounts({
+        user: user,
+        userSourceTokenAccount: await getAssociatedTokenAddress(tokenA_mint_address, user),
+        userDestinationTokenAccount: userATAforTokenC,
+        sourceMint: tokenA_mint_address,
+        sourceTokenProgram: tokenProgram,
+      })
+      .remainingAccounts([
+        { pubkey: pool, isWritable: true, isSigner: false },
+        { pubkey: vault_token_account_a, isWritable: true, isSigner: false },
+        { pubkey: vault_token_account_b, isWritable: true, isSigner: false },
+        { pubkey: observations, isWritable: true, isSigner: false },
+        { pubkey: tokenB_mint_address, isWritable: false, isSigner: false },
+        { pubkey: tokenProgram, isWritable: false, isSigner: false },
+        { pubkey: poolBC, isWritable: true, isSigner: false },
+        { pubkey: vaultBInPoolBC, isWritable: true, isSigner: false },
+        { pubkey: vaultCInPoolBC, isWritable: true, isSigner: false },
+        { pubkey: observationsBC, isWritable: true, isSigner: false },
+        { pubkey: tokenC_mint_address, isWritable: false, isSigner: false },
+        { pubkey: tokenProgram, isWritable: false, isSigner: false },
+      ]);
+
+    const result = await simulatedResult(builder, "routeSwapResult");
+    const balanceBefore = await tokenBalance(userATAforTokenC);
+
+    const txHash = await builder.rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await connection.confirmTransaction(txHash);
+
+    const swapped = (await confirmedEvents(txHash))
+      .filter((event) => event.name === "swapEvent")
+      .map((event) => event.data as any);
+    assert.equal(swapped.length, 2);
+    assert.equal(result.hops.length, 2);
+
+    // Each hop reports what was sent into its pool, the second one gets
+    // exactly what the first paid out
+    assert.isTrue(swapped[0].amountIn.eq(amount_in));
+    assert.isTrue(swapped[1].amountIn.eq(swapped[0].amountOut));
+    assert.isTrue(swapped[1].amountOut.eq(result.amountOut));
+
+    for (const [index, hopPool] of [pool, poolBC].entries()) {
+      const hop = result.hops[index];
+      const poolAccount = await program.account.pool.fetch(hopPool);
+      assert.isTrue(hop.pool.equals(hopPool));
+      assert.isTrue(hop.amountIn.eq(swapped[index].amountIn));
+      assert.isTrue(hop.fee.eq(swapped[index].fee));
+      assert.isTrue(hop.protocolFee.eq(swapped[index].protocolFee));
+      assert.isTrue(hop.reserveA.eq(poolAccount.reserveA));
+      assert.isTrue(hop.reserveB.eq(poolAccount.reserveB));
+    }
+
+    assert.isTrue((await tokenBalance(userATAforTokenC)).sub(balanceBefore).eq(result.amountOut));
+  });
+
+  it("quotes a swap and liquidity changes without moving tokens", async () => {
+    const swapQuote = await program.methods
+      .quoteSwap(new BN(1_000_000_000), { aToB: {} })
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
+    assert.isTrue(swapQuote.amountOut.gtn(0));
+
+    // A simulated swap of the same size has to land on the quote
+    const userATAforTokenA = await getAssociatedTokenAddress(tokenA_mint_address, program.provider.publicKey);
+    const userATAforTokenB = await getAssociatedTokenAddress(tokenB_mint_address, program.provider.publicKey);
+    const swapResult = await simulatedResult(
+      program.methods
+        .swap(new BN(1_000_000_000), new BN(0), { aToB: {} }, new BN(Math.floor(Date.now() / 1000) + 60))
+        .accounts({
+          user: program.provider.publicKey,
+          pool: pool,
+          userTokenAccountForTokenA: userATAforTokenA,
+          userTokenAccountForTokenB: userATAforTokenB,
+          vaultTokenAAccount: vault_token_account_a,
+          vaultTokenBAccount: vault_token_account_b,
+          vaultAuthA: vault_auth_a,
+          vaultAuthB: vault_auth_b,
+          observations: observations,
+          mintA: tokenA_mint_address,
+          mintB: tokenB_mint_address,
+          tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+          tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        }),
+      "swapResult"
     );
+    assert.isTrue(swapResult.amountOut.eq(swapQuote.amountOut));
+    assert.isTrue(swapResult.fee.eq(swapQuote.fee));
+
+    const depositQuote = await program.methods
+      .quoteAddLiquidity(new BN(1_000_000_000), new BN(1_000_000_000))
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
+    assert.isTrue(depositQuote.lpTokens.gtn(0));
+
+    const withdrawQuote = await program.methods
+      .quoteRemoveLiquidity(depositQuote.lpTokens)
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
+    assert.isTrue(withdrawQuote.amountA.lte(depositQuote.amountA));
+    assert.isTrue(withdrawQuote.amountB.lte(depositQuote.amountB));
+  });
 
-    const userATAforTokenB = destination.toBase58();
+  it("reads a time-weighted average price from the oracle", async () => {
+    const observation = await program.methods
+      .observePrices()
+      .accounts({ pool: pool })
+      .view();
+
+    // The average needs at least a second between the two readings
+    await new Promise((resolve) => setTimeout(resolve, 2_000));
+
+    const twap = await program.methods
+      .quoteTwap(observation.priceACumulative, observation.priceBCumulative, observation.timestamp)
+      .accounts({ pool: pool })
+      .view();
+    assert.isTrue(twap.elapsed.gtn(0));
+    assert.isTrue(twap.priceA.gtn(0));
+  });
 
-    // Deriving user ATA for Token A
-    const destination_token_a = await getAssociatedTokenAddress(
-      tokenA_mint_address,
-      userPublicKey
+  it("grows the observation buffer and observes past prices", async () => {
+    const txHash = await program.methods
+      .growObservations(8)
+      .accounts({
+        pool: pool,
+        observations: observations,
+        payer: program.provider.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const buffer = await program.account.observationBuffer.fetch(observations);
+    assert.equal(buffer.cardinalityNext, 8);
+
+    // One call can add at most 182 slots of 56 bytes, 10 KiB of account data
+    try {
+      await program.methods
+        .growObservations(8 + 183)
+        .accounts({
+          pool: pool,
+          observations: observations,
+          payer: program.provider.publicKey,
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("grow_observations should fail past the per-call growth limit");
+    } catch (err) {
+      assert.include(err.toString(), "ObservationGrowthTooLarge");
+    }
+
+    // Sums now and one second ago, their difference is the current price
+    const observed = await program.methods
+      .observe([1, 0])
+      .accounts({ pool: pool, observations: observations })
+      .view();
+    const priceA = observed.priceACumulatives[1].sub(observed.priceACumulatives[0]);
+    assert.isTrue(priceA.gtn(0));
+    assert.isTrue(observed.liquidityCumulatives[1].gt(observed.liquidityCumulatives[0]));
+  });
+
+  // Concentrated liquidity pool over the same mints, 64 ticks between usable ticks
+  const tick_spacing = 64;
+  const tickBuffer = (tick: number) => {
+    const buffer = Buffer.alloc(4);
+    buffer.writeInt32LE(tick);
+    return buffer;
+  };
+  const tickSpacingBuffer = Buffer.alloc(2);
+  tickSpacingBuffer.writeUInt16LE(tick_spacing);
+
+  const [clmmPool] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("clmmPool"), tokenA_mint_address.toBuffer(), tokenB_mint_address.toBuffer(), tickSpacingBuffer],
+    program.programId
+  );
+
+  const [clmm_vault_a] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("vaultTokenA"), clmmPool.toBuffer()],
+    program.programId
+  );
+
+  const [clmm_vault_b] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("vaultTokenB"), clmmPool.toBuffer()],
+    program.programId
+  );
+
+  // Each tick array holds 32 ticks, so the arrays start every 2048 ticks
+  const [tickArrayLower] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("tickArray"), clmmPool.toBuffer(), tickBuffer(-2048)],
+    program.programId
+  );
+
+  const [tickArrayUpper] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("tickArray"), clmmPool.toBuffer(), tickBuffer(0)],
+    program.programId
+  );
+
+  const tick_lower = -128;
+  const tick_upper = 128;
+
+  const [position] = web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("position"),
+      clmmPool.toBuffer(),
+      program.provider.publicKey.toBuffer(),
+      tickBuffer(tick_lower),
+      tickBuffer(tick_upper),
+    ],
+    program.programId
+  );
+
+  it("initializes a concentrated liquidity pool", async () => {
+    // Price of 1, the sqrt price is Q64.64
+    const initial_sqrt_price = new BN("18446744073709551616");
+
+    const txHash = await program.methods
+      .initializeClmmPool(tick_spacing, 30, initial_sqrt_price)
+      .accounts({
+        pool: clmmPool,
+        vaultTokenAAccount: clmm_vault_a,
+        vaultTokenBAccount: clmm_vault_b,
+        vaultAuthA: clmm_vault_a,
+        vaultAuthB: clmm_vault_b,
+        mintA: tokenA_mint_address,
+        mintB: tokenB_mint_address,
+        config: config,
+        admin: program.provider.publicKey,
+        payer: program.provider.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+        tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const poolAccount = await program.account.clmmPool.fetch(clmmPool);
+    assert.equal(poolAccount.tickCurrent, 0);
+  });
+
+  it("rejects a tick array from anyone but the program admin", async () => {
+    const outsider = web3.Keypair.generate();
+    const [tickArray] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("tickArray"), clmmPool.toBuffer(), tickBuffer(2048)],
+      program.programId
     );
 
-    const userATAforTokenA = destination_token_a.toBase58();
+    try {
+      await program.methods
+        .initializeTickArray(2048)
+        .accounts({
+          tickArray: tickArray,
+          pool: clmmPool,
+          config: config,
+          admin: outsider.publicKey,
+          payer: program.provider.publicKey,
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .signers([outsider])
+        .rpc();
+      assert.fail("initialize_tick_array should fail for anyone but the program admin");
+    } catch (err) {
+      assert.include(err.toString(), "UnauthorizedProgramAdmin");
+    }
+  });
 
-    // Check if the Token_A ATA is initialized or not
-    const ataAccountInfo = await program.provider.connection.getAccountInfo(destination_token_a);
+  it("initializes the tick arrays around the current price", async () => {
+    for (const [tickArray, start_tick_index] of [[tickArrayLower, -2048], [tickArrayUpper, 0]] as const) {
+      const txHash = await program.methods
+        .initializeTickArray(start_tick_index)
+        .accounts({
+          tickArray: tickArray,
+          pool: clmmPool,
+          config: config,
+          admin: program.provider.publicKey,
+          payer: program.provider.publicKey,
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+      // Confirm Transaction
+      await program.provider.connection.confirmTransaction(txHash);
+    }
+  });
 
-    if (ataAccountInfo && ataAccountInfo.data.length > 0) {
-      console.log("ATA is already initialized");
-    } else {
-      console.log("Initializing ATA");
+  it("opens a position over a price range", async () => {
+    const txHash = await program.methods
+      .openPosition(tick_lower, tick_upper)
+      .accounts({
+        owner: program.provider.publicKey,
+        position: position,
+        pool: clmmPool,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
 
-      // Create associated token account if it doesn't exist
-      const ataIx = createAssociatedTokenAccountInstruction(
-        program.provider.publicKey,     // payer
-        destination_token_a,            // ata to be created
-        userPublicKey,                  // token account owner
-        tokenA_mint_address             // mint
-      );
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+  });
 
-      const tx = new web3.Transaction().add(ataIx);
+  it("adds liquidity to the position", async () => {
+    const userATAforTokenA = await getAssociatedTokenAddress(tokenA_mint_address, program.provider.publicKey);
+    const userATAforTokenB = await getAssociatedTokenAddress(tokenB_mint_address, program.provider.publicKey);
 
-      await program.provider.sendAndConfirm(tx);
+    const liquidity = new BN(1_000_000_000_000);
+
+    const builder = program.methods
+      .increaseLiquidity(liquidity, new BN(10_000_000_000), new BN(10_000_000_000))
+      .accounts({
+        owner: program.provider.publicKey,
+        pool: clmmPool,
+        position: position,
+        tickArrayLower: tickArrayLower,
+        tickArrayUpper: tickArrayUpper,
+        userTokenAccountForTokenA: userATAforTokenA,
+        userTokenAccountForTokenB: userATAforTokenB,
+        vaultTokenAAccount: clmm_vault_a,
+        vaultTokenBAccount: clmm_vault_b,
+        mintA: tokenA_mint_address,
+        mintB: tokenB_mint_address,
+        tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+      });
+
+    const result = await simulatedResult(builder, "positionResult");
+    const balanceABefore = await tokenBalance(userATAforTokenA);
+
+    const txHash = await builder.rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const added = findEvent(await confirmedEvents(txHash), "positionLiquidityEvent");
+    assert.equal(added.liquidityDelta.toString(), liquidity.toString());
+    assert.isTrue(added.amountA.eq(result.amountA));
+    assert.isTrue(added.amountB.eq(result.amountB));
+    assert.isTrue(result.liquidity.eq(liquidity));
+    assert.isTrue(balanceABefore.sub(await tokenBalance(userATAforTokenA)).eq(result.amountA));
+
+    const poolAccount = await program.account.clmmPool.fetch(clmmPool);
+    assert.isTrue(poolAccount.liquidity.eq(liquidity));
+  });
+
+  it("swaps Token A for Token B across the ticks of the pool", async () => {
+    const userATAforTokenA = await getAssociatedTokenAddress(tokenA_mint_address, program.provider.publicKey);
+    const userATAforTokenB = await getAssociatedTokenAddress(tokenB_mint_address, program.provider.publicKey);
+
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
+
+    // Enough to push the price below the lower tick of the position, the
+    // limit at tick -1024 stops it before it runs out of tick arrays
+    const sqrt_price_limit = new BN("17526086738831147013");
+
+    const builder = program.methods
+      .clmmSwap(new BN(10_000_000_000), new BN(0), { aToB: {} }, sqrt_price_limit, deadline)
+      .accounts({
+        user: program.provider.publicKey,
+        pool: clmmPool,
+        userTokenAccountForTokenA: userATAforTokenA,
+        userTokenAccountForTokenB: userATAforTokenB,
+        vaultTokenAAccount: clmm_vault_a,
+        vaultTokenBAccount: clmm_vault_b,
+        mintA: tokenA_mint_address,
+        mintB: tokenB_mint_address,
+        tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+      })
+      .remainingAccounts([
+        { pubkey: tickArrayUpper, isWritable: true, isSigner: false },
+        { pubkey: tickArrayLower, isWritable: true, isSigner: false },
+      ]);
+
+    const result = await simulatedResult(builder, "clmmSwapResult");
+    const balanceBefore = await tokenBalance(userATAforTokenB);
+
+    const txHash = await builder.rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const swapped = findEvent(await confirmedEvents(txHash), "clmmSwapEvent");
+    assert.isTrue(swapped.amountIn.eq(result.amountIn));
+    assert.isTrue(swapped.amountOut.eq(result.amountOut));
+    assert.isTrue(swapped.sqrtPrice.eq(result.sqrtPrice));
+    assert.equal(swapped.tickCurrent, result.tickCurrent);
+    assert.isTrue((await tokenBalance(userATAforTokenB)).sub(balanceBefore).eq(result.amountOut));
+
+    // Out of range, so none of the liquidity is active any more
+    const poolAccount = await program.account.clmmPool.fetch(clmmPool);
+    assert.equal(poolAccount.tickCurrent, -1024);
+    assert.isTrue(poolAccount.liquidity.eqn(0));
+  });
+
+  // Accounts of the A/B pools for the negative tests below, which only
+  // check that an instruction is rejected
+  const splTokenProgram = new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+  const userTokenAccountA = getAssociatedTokenAddressSync(tokenA_mint_address, program.provider.publicKey);
+  const userTokenAccountB = getAssociatedTokenAddressSync(tokenB_mint_address, program.provider.publicKey);
+  const poolLpMint = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("mint"), pool.toBuffer()],
+    program.programId
+  )[0];
+
+  const poolSwapAccounts = {
+    user: program.provider.publicKey,
+    pool: pool,
+    userTokenAccountForTokenA: userTokenAccountA,
+    userTokenAccountForTokenB: userTokenAccountB,
+    vaultTokenAAccount: vault_token_account_a,
+    vaultTokenBAccount: vault_token_account_b,
+    vaultAuthA: vault_auth_a,
+    vaultAuthB: vault_auth_b,
+    observations: observations,
+    mintA: tokenA_mint_address,
+    mintB: tokenB_mint_address,
+    tokenProgramA: splTokenProgram,
+    tokenProgramB: splTokenProgram,
+  };
+
+  const poolLiquidityAccounts = {
+    ...poolSwapAccounts,
+    userPdaAccount: userPDALiquidity,
+    mint: poolLpMint,
+    authority: web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("authority"), pool.toBuffer()],
+      program.programId
+    )[0],
+    destination: getAssociatedTokenAddressSync(poolLpMint, program.provider.publicKey),
+    destinationOwner: program.provider.publicKey,
+    payer: program.provider.publicKey,
+    rent: web3.SYSVAR_RENT_PUBKEY,
+    systemProgram: web3.SystemProgram.programId,
+    tokenProgram: splTokenProgram,
+    associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+  };
+
+  // Sends the transaction and checks the program rejects it with `errorName`
+  const expectError = async (builder: any, errorName: string) => {
+    try {
+      await builder.rpc();
+    } catch (err) {
+      assert.include(err.toString(), errorName);
+      return;
     }
+    assert.fail(`the transaction should fail with ${errorName}`);
+  };
+
+  // Well behind the validator clock, however far it lags the wall clock
+  const expiredDeadline = () => new BN(Math.floor(Date.now() / 1000) - 3_600);
+
+  it("rejects a swap past its deadline", async () => {
+    await expectError(
+      program.methods
+        .swap(new BN(1_000_000), new BN(0), { aToB: {} }, expiredDeadline())
+        .accounts(poolSwapAccounts),
+      "DeadlineExceeded"
+    );
+  });
 
-    const vaultTokenAVault = await getAccount(program.provider.connection, vault_token_account_a);
-    const vaultTokenBVault = await getAccount(program.provider.connection, vault_token_account_b);
+  it("rejects an exact-output swap past its deadline", async () => {
+    await expectError(
+      program.methods
+        .swapExactOut(new BN(1_000_000), new BN(2_000_000), { aToB: {} }, expiredDeadline())
+        .accounts(poolSwapAccounts),
+      "DeadlineExceeded"
+    );
+  });
+
+  it("rejects a route past its deadline", async () => {
+    await expectError(
+      program.methods
+        .swapRoute(new BN(1_000_000), new BN(0), expiredDeadline())
+        .accounts({
+          user: program.provider.publicKey,
+          userSourceTokenAccount: userTokenAccountA,
+          userDestinationTokenAccount: userTokenAccountB,
+          sourceMint: tokenA_mint_address,
+          sourceTokenProgram: splTokenProgram,
+        })
+        .remainingAccounts([
+          { pubkey: pool, isWritable: true, isSigner: false },
+          { pubkey: vault_token_account_a, isWritable: true, isSigner: false },
+          { pubkey: vault_token_account_b, isWritable: true, isSigner: false },
+          { pubkey: observations, isWritable: true, isSigner: false },
+          { pubkey: tokenB_mint_address, isWritable: false, isSigner: false },
+          { pubkey: splTokenProgram, isWritable: false, isSigner: false },
+        ]),
+      "DeadlineExceeded"
+    );
+  });
 
+  it("rejects liquidity changes past their deadline", async () => {
+    await expectError(
+      program.methods
+        .addLiquidity(new BN(1_000_000), new BN(1_000_000), new BN(0), expiredDeadline())
+        .accounts(poolLiquidityAccounts),
+      "DeadlineExceeded"
+    );
+
+    await expectError(
+      program.methods
+        .removeLiquidity(new BN(1_000), new BN(0), new BN(0), expiredDeadline())
+        .accounts(poolLiquidityAccounts),
+      "DeadlineExceeded"
+    );
+  });
+
+  it("rejects a concentrated liquidity swap past its deadline", async () => {
+    await expectError(
+      program.methods
+        .clmmSwap(new BN(1_000_000), new BN(0), { bToA: {} }, null, expiredDeadline())
+        .accounts({
+          user: program.provider.publicKey,
+          pool: clmmPool,
+          userTokenAccountForTokenA: userTokenAccountA,
+          userTokenAccountForTokenB: userTokenAccountB,
+          vaultTokenAAccount: clmm_vault_a,
+          vaultTokenBAccount: clmm_vault_b,
+          mintA: tokenA_mint_address,
+          mintB: tokenB_mint_address,
+          tokenProgramA: splTokenProgram,
+          tokenProgramB: splTokenProgram,
+        })
+        .remainingAccounts([{ pubkey: tickArrayLower, isWritable: true, isSigner: false }]),
+      "DeadlineExceeded"
+    );
+  });
+
+  it("rejects a deposit minting fewer LP tokens than min_lp_out", async () => {
     const amount = new BN(1_000_000_000);
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
+
+    const quote = await program.methods
+      .quoteAddLiquidity(amount, amount)
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
+
+    await expectError(
+      program.methods
+        .addLiquidity(amount, amount, quote.lpTokens.addn(1), deadline)
+        .accounts(poolLiquidityAccounts),
+      "SlippageExceeded"
+    );
+  });
+
+  it("rejects a withdrawal paying out less than min_amount_a or min_amount_b", async () => {
+    const lp_amount = (await tokenBalance(poolLiquidityAccounts.destination)).divn(10);
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
+
+    const quote = await program.methods
+      .quoteRemoveLiquidity(lp_amount)
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
+
+    for (const [min_amount_a, min_amount_b] of [
+      [quote.amountA.addn(1), quote.amountB],
+      [quote.amountA, quote.amountB.addn(1)],
+    ]) {
+      await expectError(
+        program.methods
+          .removeLiquidity(lp_amount, min_amount_a, min_amount_b, deadline)
+          .accounts(poolLiquidityAccounts),
+        "SlippageExceeded"
+      );
+    }
+  });
 
-    const expectedOutput = amount.mul(new BN(Price_Of_Token_A * 1000)).divn(1000);
+  it("rejects swaps one unit past their quoted output or input", async () => {
+    const deadline = new BN(Math.floor(Date.now() / 1000) + 60);
 
-    const swapFees = 0.003;
+    const amount_in = new BN(1_000_000_000);
+    const quote = await program.methods
+      .quoteSwap(amount_in, { aToB: {} })
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
 
-    const expectedOutput_With_Swap_Fees = expectedOutput.muln(1 - swapFees);
+    await expectError(
+      program.methods
+        .swap(amount_in, quote.amountOut.addn(1), { aToB: {} }, deadline)
+        .accounts(poolSwapAccounts),
+      "SlippageExceeded"
+    );
 
-    const slippageMultiplier = 1 - userSlippageTolerancePercent;
+    // The input an exact-output swap needs, from a simulation without a cap
+    const amount_out = new BN(1_000_000_000);
+    const result = await simulatedResult(
+      program.methods
+        .swapExactOut(amount_out, new BN("18446744073709551615"), { aToB: {} }, deadline)
+        .accounts(poolSwapAccounts),
+      "swapResult"
+    );
 
-    const minExpectedOutput = expectedOutput_With_Swap_Fees.muln(slippageMultiplier * 1000).divn(10000);
+    await expectError(
+      program.methods
+        .swapExactOut(amount_out, result.amountIn.subn(1), { aToB: {} }, deadline)
+        .accounts(poolSwapAccounts),
+      "SlippageExceeded"
+    );
+  });
 
-    const txHash = await program.methods
-      .swapBForA(amount, minExpectedOutput)
+  it("removes the liquidity and collects the fees of the position", async () => {
+    const userATAforTokenA = await getAssociatedTokenAddress(tokenA_mint_address, program.provider.publicKey);
+    const userATAforTokenB = await getAssociatedTokenAddress(tokenB_mint_address, program.provider.publicKey);
+
+    const positionAccount = await program.account.position.fetch(position);
+
+    const builder = program.methods
+      .decreaseLiquidity(positionAccount.liquidity, new BN(0), new BN(0))
       .accounts({
-        user: userPublicKey,
+        owner: program.provider.publicKey,
+        pool: clmmPool,
+        position: position,
+        tickArrayLower: tickArrayLower,
+        tickArrayUpper: tickArrayUpper,
         userTokenAccountForTokenA: userATAforTokenA,
         userTokenAccountForTokenB: userATAforTokenB,
+        vaultTokenAAccount: clmm_vault_a,
+        vaultTokenBAccount: clmm_vault_b,
+        mintA: tokenA_mint_address,
+        mintB: tokenB_mint_address,
+        tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+      });
+
+    const result = await simulatedResult(builder, "positionResult");
+    const balanceBBefore = await tokenBalance(userATAforTokenB);
+
+    const txHash = await builder.rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await program.provider.connection.confirmTransaction(txHash);
+
+    const removed = findEvent(await confirmedEvents(txHash), "positionLiquidityEvent");
+    assert.equal(removed.liquidityDelta.neg().toString(), positionAccount.liquidity.toString());
+    assert.isTrue(removed.amountB.eq(result.amountB));
+    assert.isTrue(removed.feesB.eq(result.feesB));
+    assert.isTrue(result.liquidity.eqn(0));
+    assert.isTrue((await tokenBalance(userATAforTokenB)).sub(balanceBBefore).eq(result.amountB.add(result.feesB)));
+
+    const updatedPosition = await program.account.position.fetch(position);
+    assert.isTrue(updatedPosition.liquidity.eqn(0));
+    assert.isTrue(updatedPosition.tokensOwedA.eqn(0));
+  });
+
+  it("collects protocol fees to the treasury", async () => {
+    const treasury_token_a_ata = await getAssociatedTokenAddress(
+      tokenA_mint_address,
+      program.provider.publicKey
+    );
+
+    const treasury_token_b_ata = await getAssociatedTokenAddress(
+      tokenB_mint_address,
+      program.provider.publicKey
+    );
+
+    const txHash = await program.methods
+      .collectProtocolFees()
+      .accounts({
+        admin: program.provider.publicKey,
+        pool: pool,
         vaultTokenAAccount: vault_token_account_a,
         vaultTokenBAccount: vault_token_account_b,
         vaultAuthA: vault_auth_a,
         vaultAuthB: vault_auth_b,
+        treasuryTokenAAccount: treasury_token_a_ata,
+        treasuryTokenBAccount: treasury_token_b_ata,
         mintA: tokenA_mint_address,
         mintB: tokenB_mint_address,
-        tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramA: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgramB: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
       })
-      .signers([userKeyPair])
       .rpc();
 
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
     await program.provider.connection.confirmTransaction(txHash);
+
+    const poolAccount = await program.account.pool.fetch(pool);
+    assert.equal(poolAccount.protocolFeesA.toNumber(), 0);
+    assert.equal(poolAccount.protocolFeesB.toNumber(), 0);
   });
 
   it("Price Impact Calculation when swapping Token A for Token B", async () => {
-    const Token_A = await getAccount(program.provider.connection, vault_token_account_a);
+    const poolAccount = await program.account.pool.fetch(pool);
 
-    const Token_B = await getAccount(program.provider.connection, vault_token_account_b);
+    const amount = new BN(1_000_000_000);
 
-    const Token_A_Quantity = parseInt(Token_A.amount.toString(), 10);
-    const Token_B_Quantity = parseInt(Token_B.amount.toString(), 10);
+    const quote = await program.methods
+      .quoteSwap(amount, { aToB: {} })
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
 
-    const amount = 1_000_000_000;
+    // Spot output at the current reserves, before the fee and any price movement
+    const spotOutput = amount.mul(poolAccount.reserveB).div(poolAccount.reserveA);
+    const priceImpactBps = spotOutput.sub(quote.amountOut).muln(10_000).div(spotOutput);
 
-    const k = Token_A_Quantity * Token_B_Quantity;
+    // The quoted impact is the slippage alone, the fee comes on top of it
+    assert.isTrue(quote.priceImpactBps.lte(priceImpactBps));
+    assert.isTrue(quote.amountOut.lt(spotOutput));
 
-    const newReserveA = Token_A_Quantity + amount;
-    const newReserveB = k / newReserveA;
-    const outputB = Token_B_Quantity - newReserveB;
+    console.log("This is the price impact: ", quote.priceImpactBps.toNumber() / 100);
+  });
+
+  it("Price Impact Calculation when swapping Token B for Token A", async () => {
+    const poolAccount = await program.account.pool.fetch(pool);
 
-    const swapFees = 0.003;
-    const outputBWithFees = outputB - (1 - swapFees);
+    const amount = new BN(1_000_000_000);
+
+    const quote = await program.methods
+      .quoteSwap(amount, { bToA: {} })
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
 
-    const spotPrice = Token_B_Quantity / Token_A_Quantity;
-    const swapPrice = outputBWithFees / amount;
+    // Spot output at the current reserves, before the fee and any price movement
+    const spotOutput = amount.mul(poolAccount.reserveA).div(poolAccount.reserveB);
+    const priceImpactBps = spotOutput.sub(quote.amountOut).muln(10_000).div(spotOutput);
 
-    const priceImpactPercent = ((spotPrice - swapPrice) / spotPrice) * 100;
+    // The quoted impact is the slippage alone, the fee comes on top of it
+    assert.isTrue(quote.priceImpactBps.lte(priceImpactBps));
+    assert.isTrue(quote.amountOut.lt(spotOutput));
 
-    console.log("This is the price impact: ", priceImpactPercent);
+    console.log("This is the price impact: ", quote.priceImpactBps.toNumber() / 100);
   });
 
-  it("Price Impact Calculation when swapping Token B for Token A", async () => {
-    const Token_A = await getAccount(program.provider.connection, vault_token_account_a);
+  describe("Token-2022 pools", () => {
+    const connection = program.provider.connection;
+    const payer = (program.provider as anchor.AnchorProvider).wallet.payer;
+    const user = program.provider.publicKey;
+    const TOKEN_METADATA_PROGRAM_ID = new web3.PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+    type Token = { mint: web3.PublicKey; program: web3.PublicKey };
+
+    // Mints a supply to the wallet, so every token here can be traded right away
+    const fundUser = async (token: Token) => {
+      const ata = await getOrCreateAssociatedTokenAccount(
+        connection, payer, token.mint, user, false, undefined, undefined, token.program
+      );
+      await mintTo(connection, payer, token.mint, ata.address, payer, 100_000_000_000, [], undefined, token.program);
+      return token;
+    };
+
+    const createSplToken = async (): Promise<Token> =>
+      fundUser({ mint: await createMint(connection, payer, user, null, 9), program: TOKEN_PROGRAM_ID });
+
+    // A Token-2022 mint whose extensions `initializeExtensions` sets up
+    // before the mint itself is initialized
+    const createToken2022 = async (
+      extensions: ExtensionType[] = [],
+      initializeExtensions: (mint: web3.PublicKey) => web3.TransactionInstruction[] = () => []
+    ): Promise<Token> => {
+      const mint = web3.Keypair.generate();
+      const space = getMintLen(extensions);
+
+      const tx = new web3.Transaction().add(
+        web3.SystemProgram.createAccount({
+          fromPubkey: user,
+          newAccountPubkey: mint.publicKey,
+          space: space,
+          lamports: await connection.getMinimumBalanceForRentExemption(space),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        ...initializeExtensions(mint.publicKey),
+        createInitializeMintInstruction(mint.publicKey, 9, user, null, TOKEN_2022_PROGRAM_ID)
+      );
+      await web3.sendAndConfirmTransaction(connection, tx, [payer, mint]);
+
+      return fundUser({ mint: mint.publicKey, program: TOKEN_2022_PROGRAM_ID });
+    };
+
+    const userAccount = (token: Token) =>
+      getAssociatedTokenAddressSync(token.mint, user, false, token.program);
+
+    const pda = (seed: string, key: web3.PublicKey) =>
+      web3.PublicKey.findProgramAddressSync([Buffer.from(seed), key.toBuffer()], program.programId)[0];
+
+    const poolAddresses = (first: Token, second: Token) => {
+      const [tokenA, tokenB] = [first, second].sort((x, y) => x.mint.toBuffer().compare(y.mint.toBuffer()));
+      const [poolAddress] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("pool"), tokenA.mint.toBuffer(), tokenB.mint.toBuffer()],
+        program.programId
+      );
+      const lpMint = pda("mint", poolAddress);
+
+      return {
+        pool: poolAddress,
+        tokenA: tokenA,
+        tokenB: tokenB,
+        vaultA: pda("vaultTokenA", poolAddress),
+        vaultB: pda("vaultTokenB", poolAddress),
+        observations: pda("observations", poolAddress),
+        lpMint: lpMint,
+        lpAuthority: pda("authority", poolAddress),
+        lpAccount: getAssociatedTokenAddressSync(lpMint, user),
+        userLiquidity: web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("userliquidityPDA"), poolAddress.toBuffer(), user.toBuffer()],
+          program.programId
+        )[0],
+      };
+    };
+
+    type TestPool = ReturnType<typeof poolAddresses>;
+
+    const initializePool = (p: TestPool) =>
+      program.methods
+        .initializePool(30, { constantProduct: {} }, new BN(0), new BN(0))
+        .accounts({
+          pool: p.pool,
+          observations: p.observations,
+          mintA: p.tokenA.mint,
+          mintB: p.tokenB.mint,
+          config: config,
+          admin: user,
+          payer: user,
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    // Creates a constant-product pool with its vaults and LP mint. The
+    // lockup is lifted so the tests can withdraw straight after depositing.
+    const createPool = async (first: Token, second: Token) => {
+      const p = poolAddresses(first, second);
+
+      await initializePool(p);
+
+      for (const [method, vault, token] of [
+        ["initializeVaultTokenA", p.vaultA, p.tokenA],
+        ["initializeVaultTokenB", p.vaultB, p.tokenB],
+      ] as const) {
+        await program.methods[method]()
+          .accounts({
+            vaultTokenAccount: vault,
+            vaultAuth: vault,
+            pool: p.pool,
+            admin: user,
+            payer: user,
+            mint: token.mint,
+            systemProgram: web3.SystemProgram.programId,
+            tokenProgram: token.program,
+            rent: web3.SYSVAR_RENT_PUBKEY,
+          })
+          .rpc();
+      }
+
+      const [metadataAddress] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), p.lpMint.toBuffer()],
+        TOKEN_METADATA_PROGRAM_ID
+      );
+
+      await program.methods
+        .createTokenMint({ name: "LP Token", symbol: "LP", uri: "https://jsonkeeper.com/b/7G05", decimals: 9 })
+        .accounts({
+          metadata: metadataAddress,
+          mint: p.lpMint,
+          authority: p.lpAuthority,
+          pool: p.pool,
+          admin: user,
+          payer: user,
+          rent: web3.SYSVAR_RENT_PUBKEY,
+          systemProgram: web3.SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        })
+        .rpc();
+
+      await program.methods
+        .initializeUserLiquidityAccount()
+        .accounts({
+          user: user,
+          userPdaAccount: p.userLiquidity,
+          pool: p.pool,
+          systemProgram: web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      await program.methods
+        .setLockupDuration(new BN(0))
+        .accounts({ admin: user, pool: p.pool })
+        .rpc();
+
+      return p;
+    };
+
+    const liquidityAccounts = (p: TestPool) => ({
+      user: user,
+      userPdaAccount: p.userLiquidity,
+      pool: p.pool,
+      userTokenAccountForTokenA: userAccount(p.tokenA),
+      userTokenAccountForTokenB: userAccount(p.tokenB),
+      vaultTokenAAccount: p.vaultA,
+      vaultTokenBAccount: p.vaultB,
+      vaultAuthA: p.vaultA,
+      vaultAuthB: p.vaultB,
+      observations: p.observations,
+      mintA: p.tokenA.mint,
+      mintB: p.tokenB.mint,
+      mint: p.lpMint,
+      authority: p.lpAuthority,
+      destination: p.lpAccount,
+      destinationOwner: user,
+      payer: user,
+      rent: web3.SYSVAR_RENT_PUBKEY,
+      systemProgram: web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      tokenProgramA: p.tokenA.program,
+      tokenProgramB: p.tokenB.program,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+    });
+
+    const swapAccounts = (p: TestPool) => ({
+      user: user,
+      pool: p.pool,
+      userTokenAccountForTokenA: userAccount(p.tokenA),
+      userTokenAccountForTokenB: userAccount(p.tokenB),
+      vaultTokenAAccount: p.vaultA,
+      vaultTokenBAccount: p.vaultB,
+      vaultAuthA: p.vaultA,
+      vaultAuthB: p.vaultB,
+      observations: p.observations,
+      mintA: p.tokenA.mint,
+      mintB: p.tokenB.mint,
+      tokenProgramA: p.tokenA.program,
+      tokenProgramB: p.tokenB.program,
+    });
+
+    const deadline = () => new BN(Math.floor(Date.now() / 1000) + 60);
+
+    it("rejects mints with a transfer hook or a permanent delegate", async () => {
+      const splToken = await createSplToken();
+      const hookToken = await createToken2022([ExtensionType.TransferHook], (mint) => [
+        createInitializeTransferHookInstruction(mint, user, program.programId, TOKEN_2022_PROGRAM_ID),
+      ]);
+      const delegateToken = await createToken2022([ExtensionType.PermanentDelegate], (mint) => [
+        createInitializePermanentDelegateInstruction(mint, user, TOKEN_2022_PROGRAM_ID),
+      ]);
+
+      for (const token of [hookToken, delegateToken]) {
+        try {
+          await initializePool(poolAddresses(splToken, token));
+          assert.fail("initialize_pool should reject the mint");
+        } catch (err) {
+          assert.include(err.toString(), "UnsupportedMintExtension");
+        }
+      }
+    });
+
+    let t22Pool: TestPool;
+
+    it("initializes a pool with a Token-2022 mint", async () => {
+      t22Pool = await createPool(await createToken2022(), await createSplToken());
+
+      const poolAccount = await program.account.pool.fetch(t22Pool.pool);
+      assert.isTrue(poolAccount.mintA.equals(t22Pool.tokenA.mint));
+      assert.isTrue(poolAccount.mintB.equals(t22Pool.tokenB.mint));
+      assert.isTrue(poolAccount.vaultA.equals(t22Pool.vaultA));
+      assert.isTrue(poolAccount.vaultB.equals(t22Pool.vaultB));
+    });
+
+    it("adds liquidity to the Token-2022 pool", async () => {
+      const amount = new BN(5_000_000_000);
 
-    const Token_B = await getAccount(program.provider.connection, vault_token_account_b);
+      const txHash = await program.methods
+        .addLiquidity(amount, amount, new BN(0), deadline())
+        .accounts(liquidityAccounts(t22Pool))
+        .rpc();
 
-    const Token_A_Quantity = parseInt(Token_A.amount.toString(), 10);
-    const Token_B_Quantity = parseInt(Token_B.amount.toString(), 10);
+      // Confirm Transaction
+      await connection.confirmTransaction(txHash);
 
-    const amount = 1_000_000_000;
+      const poolAccount = await program.account.pool.fetch(t22Pool.pool);
+      assert.isTrue(poolAccount.reserveA.eq(amount));
+      assert.isTrue(poolAccount.reserveB.eq(amount));
+      assert.isTrue((await tokenBalance(t22Pool.vaultA)).eq(amount));
+      assert.isTrue((await tokenBalance(t22Pool.vaultB)).eq(amount));
+      assert.isTrue((await tokenBalance(t22Pool.lpAccount)).eq(poolAccount.lpSupply.sub(MINIMUM_LIQUIDITY)));
+    });
+
+    it("swaps both ways through the Token-2022 pool", async () => {
+      for (const direction of [{ aToB: {} }, { bToA: {} }]) {
+        const tokenOut = "aToB" in direction ? t22Pool.tokenB : t22Pool.tokenA;
+        const amount = new BN(1_000_000_000);
+
+        const builder = program.methods
+          .swap(amount, new BN(0), direction, deadline())
+          .accounts(swapAccounts(t22Pool));
 
-    const k = Token_A_Quantity * Token_B_Quantity;
+        const result = await simulatedResult(builder, "swapResult");
+        const balanceBefore = await tokenBalance(userAccount(tokenOut));
 
-    const newReserveB = Token_B_Quantity + amount;
-    const newReserveA = k / newReserveB;
-    const outputA = Token_A_Quantity - newReserveA;
+        const txHash = await builder.rpc();
+
+        // Confirm Transaction
+        await connection.confirmTransaction(txHash);
+
+        assert.isTrue(result.amountIn.eq(amount));
+        assert.isTrue(result.amountOut.gtn(0));
+        assert.isTrue((await tokenBalance(userAccount(tokenOut))).sub(balanceBefore).eq(result.amountOut));
+      }
+    });
 
-    const swapFees = 0.003;
-    const outputAWithFees = outputA - (1 - swapFees);
+    it("removes liquidity from the Token-2022 pool", async () => {
+      const lp_amount = await tokenBalance(t22Pool.lpAccount);
+      const lpSupplyBefore = (await program.account.pool.fetch(t22Pool.pool)).lpSupply;
+
+      const builder = program.methods
+        .removeLiquidity(lp_amount, new BN(0), new BN(0), deadline())
+        .accounts(liquidityAccounts(t22Pool));
+
+      const result = await simulatedResult(builder, "liquidityResult");
+      const balanceABefore = await tokenBalance(userAccount(t22Pool.tokenA));
+
+      const txHash = await builder.rpc();
+
+      // Confirm Transaction
+      await connection.confirmTransaction(txHash);
+
+      assert.isTrue((await tokenBalance(t22Pool.lpAccount)).eqn(0));
+      assert.isTrue((await tokenBalance(userAccount(t22Pool.tokenA))).sub(balanceABefore).eq(result.amountA));
+
+      const poolAccount = await program.account.pool.fetch(t22Pool.pool);
+      assert.isTrue(poolAccount.lpSupply.eq(lpSupplyBefore.sub(lp_amount)));
+    });
+
+    // A mint withholding 1% of every transfer
+    const TRANSFER_FEE_BPS = 100;
+    const createTransferFeeToken = () =>
+      createToken2022([ExtensionType.TransferFeeConfig], (mint) => [
+        createInitializeTransferFeeConfigInstruction(
+          mint, user, user, TRANSFER_FEE_BPS, BigInt(1_000_000_000_000), TOKEN_2022_PROGRAM_ID
+        ),
+      ]);
+
+    // Whatever the vaults hold is either a reserve or an accrued protocol fee,
+    // the transfer fees withheld on the way in never count towards either
+    const assertVaultsMatchBooks = async (p: TestPool) => {
+      const poolAccount = await program.account.pool.fetch(p.pool);
+      assert.isTrue((await tokenBalance(p.vaultA)).eq(poolAccount.reserveA.add(poolAccount.protocolFeesA)));
+      assert.isTrue((await tokenBalance(p.vaultB)).eq(poolAccount.reserveB.add(poolAccount.protocolFeesB)));
+    };
+
+    let feeToken: Token;
+    let feePool: TestPool;
+
+    it("books deposits into a transfer-fee pool net of the fee", async () => {
+      feeToken = await createTransferFeeToken();
+      feePool = await createPool(feeToken, await createSplToken());
+
+      await program.methods
+        .setProtocolFee(1_667, user)
+        .accounts({ admin: user, pool: feePool.pool })
+        .rpc();
+
+      const amount = new BN(5_000_000_000);
+      const txHash = await program.methods
+        .addLiquidity(amount, amount, new BN(0), deadline())
+        .accounts(liquidityAccounts(feePool))
+        .rpc();
+
+      // Confirm Transaction
+      await connection.confirmTransaction(txHash);
+
+      await assertVaultsMatchBooks(feePool);
+    });
+
+    it("checks min_amount_out against what reaches the user after the transfer fee", async () => {
+      const direction = feePool.tokenA.mint.equals(feeToken.mint) ? { bToA: {} } : { aToB: {} };
+      const amount = new BN(1_000_000_000);
+
+      const quote = await program.methods
+        .quoteSwap(amount, direction)
+        .accounts({ pool: feePool.pool, mintA: feePool.tokenA.mint, mintB: feePool.tokenB.mint })
+        .view();
+
+      // The pool pays out more than the quote, the mint keeps the difference,
+      // so a minimum checked before the fee would let this through
+      try {
+        await program.methods
+          .swap(amount, quote.amountOut.addn(1), direction, deadline())
+          .accounts(swapAccounts(feePool))
+          .rpc();
+        assert.fail("swap should fail when the net output is under the minimum");
+      } catch (err) {
+        assert.include(err.toString(), "SlippageExceeded");
+      }
+
+      const balanceBefore = await tokenBalance(userAccount(feeToken));
+
+      const txHash = await program.methods
+        .swap(amount, quote.amountOut, direction, deadline())
+        .accounts(swapAccounts(feePool))
+        .rpc();
+
+      // Confirm Transaction
+      await connection.confirmTransaction(txHash);
+
+      const swapped = findEvent(await confirmedEvents(txHash), "swapEvent");
+      assert.isTrue(swapped.amountOut.eq(quote.amountOut));
+      assert.isTrue((await tokenBalance(userAccount(feeToken))).sub(balanceBefore).eq(quote.amountOut));
+
+      await assertVaultsMatchBooks(feePool);
+    });
 
-    const spotPrice = Token_A_Quantity / Token_B_Quantity;
-    const swapPrice = outputAWithFees / amount;
+    it("delivers the exact output net of the transfer fee", async () => {
+      const direction = feePool.tokenA.mint.equals(feeToken.mint) ? { bToA: {} } : { aToB: {} };
+      const amount_out = new BN(1_000_000_000);
 
-    const priceImpactPercent = ((spotPrice - swapPrice) / spotPrice) * 100;
+      const balanceBefore = await tokenBalance(userAccount(feeToken));
 
-    console.log("This is the price impact: ", priceImpactPercent);
+      const txHash = await program.methods
+        .swapExactOut(amount_out, new BN(2_000_000_000), direction, deadline())
+        .accounts(swapAccounts(feePool))
+        .rpc();
+
+      // Confirm Transaction
+      await connection.confirmTransaction(txHash);
+
+      assert.isTrue((await tokenBalance(userAccount(feeToken))).sub(balanceBefore).eq(amount_out));
+
+      await assertVaultsMatchBooks(feePool);
+    });
+
+    it("pays a transfer-fee token in as the exact-out input", async () => {
+      const direction = feePool.tokenA.mint.equals(feeToken.mint) ? { aToB: {} } : { bToA: {} };
+      const tokenOut = "aToB" in direction ? feePool.tokenB : feePool.tokenA;
+      const amount_out = new BN(500_000_000);
+
+      const builder = program.methods
+        .swapExactOut(amount_out, new BN(2_000_000_000), direction, deadline())
+        .accounts(swapAccounts(feePool));
+
+      const result = await simulatedResult(builder, "swapResult");
+      const feeBalanceBefore = await tokenBalance(userAccount(feeToken));
+      const outBalanceBefore = await tokenBalance(userAccount(tokenOut));
+
+      const txHash = await builder.rpc();
+
+      // Confirm Transaction
+      await connection.confirmTransaction(txHash);
+
+      // The user is charged the input and the fee the mint withholds from it
+      assert.isTrue(feeBalanceBefore.sub(await tokenBalance(userAccount(feeToken))).eq(result.amountIn));
+      assert.isTrue((await tokenBalance(userAccount(tokenOut))).sub(outBalanceBefore).eq(amount_out));
+
+      await assertVaultsMatchBooks(feePool);
+    });
+
+    it("routes through a transfer-fee token in the middle hop", async () => {
+      // Token X reaches Token Y through the fee token, which moves from the
+      // first pool's vault straight into the second pool's
+      const tokenX = await createSplToken();
+      const tokenY = await createSplToken();
+      const firstPool = await createPool(tokenX, feeToken);
+      const secondPool = await createPool(feeToken, tokenY);
+
+      for (const p of [firstPool, secondPool]) {
+        await program.methods
+          .addLiquidity(new BN(5_000_000_000), new BN(5_000_000_000), new BN(0), deadline())
+          .accounts(liquidityAccounts(p))
+          .rpc();
+      }
+
+      const vaults = (p: TestPool, tokenIn: Token) =>
+        p.tokenA.mint.equals(tokenIn.mint) ? [p.vaultA, p.vaultB] : [p.vaultB, p.vaultA];
+      const [firstVaultIn, firstVaultOut] = vaults(firstPool, tokenX);
+      const [secondVaultIn, secondVaultOut] = vaults(secondPool, feeToken);
+
+      const amount_in = new BN(1_000_000_000);
+
+      const builder = program.methods
+        .swapRoute(amount_in, new BN(0), deadline())
+        .accounts({
+          user: user,
+          userSourceTokenAccount: userAccount(tokenX),
+          userDestinationTokenAccount: userAccount(tokenY),
+          sourceMint: tokenX.mint,
+          sourceTokenProgram: tokenX.program,
+        })
+        .remainingAccounts([
+          { pubkey: firstPool.pool, isWritable: true, isSigner: false },
+          { pubkey: firstVaultIn, isWritable: true, isSigner: false },
+          { pubkey: firstVaultOut, isWritable: true, isSigner: false },
+          { pubkey: firstPool.observations, isWritable: true, isSigner: false },
+          { pubkey: feeToken.mint, isWritable: false, isSigner: false },
+          { pubkey: feeToken.program, isWritable: false, isSigner: false },
+          { pubkey: secondPool.pool, isWritable: true, isSigner: false },
+          { pubkey: secondVaultIn, isWritable: true, isSigner: false },
+          { pubkey: secondVaultOut, isWritable: true, isSigner: false },
+          { pubkey: secondPool.observations, isWritable: true, isSigner: false },
+          { pubkey: tokenY.mint, isWritable: false, isSigner: false },
+          { pubkey: tokenY.program, isWritable: false, isSigner: false },
+        ]);
+
+      const result = await simulatedResult(builder, "routeSwapResult");
+      const balanceBefore = await tokenBalance(userAccount(tokenY));
+
+      const txHash = await builder.rpc();
+
+      // Confirm Transaction
+      await connection.confirmTransaction(txHash);
+
+      // The second pool prices only what arrived after the first transfer fee
+      const [firstHop, secondHop] = result.hops;
+      assert.isTrue(firstHop.amountOut.lt(secondHop.amountIn));
+      assert.isTrue((await tokenBalance(userAccount(tokenY))).sub(balanceBefore).eq(result.amountOut));
+
+      await assertVaultsMatchBooks(firstPool);
+      await assertVaultsMatchBooks(secondPool);
+    });
+
+    it("withdraws from a transfer-fee pool net of the fee", async () => {
+      const lp_amount = (await tokenBalance(feePool.lpAccount)).divn(2);
+
+      const builder = program.methods
+        .removeLiquidity(lp_amount, new BN(0), new BN(0), deadline())
+        .accounts(liquidityAccounts(feePool));
+
+      const result = await simulatedResult(builder, "liquidityResult");
+      const [resultFee, feeBalanceBefore] = feePool.tokenA.mint.equals(feeToken.mint)
+        ? [result.amountA, await tokenBalance(userAccount(feeToken))]
+        : [result.amountB, await tokenBalance(userAccount(feeToken))];
+
+      const txHash = await builder.rpc();
+
+      // Confirm Transaction
+      await connection.confirmTransaction(txHash);
+
+      assert.isTrue((await tokenBalance(userAccount(feeToken))).sub(feeBalanceBefore).eq(resultFee));
+
+      await assertVaultsMatchBooks(feePool);
+    });
   });
 });
@@ -1,68 +1,203 @@
 import * as anchor from "@coral-xyz/anchor";
 import * as web3 from "@solana/web3.js";
-import { CpiGuardLayout, createAssociatedTokenAccountInstruction, getAccount, getAssociatedTokenAddress } from "@solana/spl-token";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
 import { SimpleTokenSwap } from "../target/types/Simple_Token_Swap";
 import { BN } from "bn.js";
-import { bs58 } from "@coral-xyz/anchor/dist/cjs/utils/bytes";
-
+import { assert } from "chai";
+
+// Walks a constant-product pool through its whole life on a local validator
+// with the Solana CLI wallet:
+//
+//     yarn run ts-mocha -p ./tsconfig.json -t 1000000 tests/anchorTest.ts
+//
+// It brings its own mints, so it does not depend on the devnet ones
+// anchor.ts trades, and it recomputes every swap output and price impact
+// on the client from the vault balances instead of asking the program.
 describe("Test", () => {
   // Configure the client to use the local cluster
   anchor.setProvider(anchor.AnchorProvider.env());
 
   const program = anchor.workspace.SimpleTokenSwap as anchor.Program<SimpleTokenSwap>;
-
-  const tokenA_mint_address = new web3.PublicKey("GTha4aTjKC2odMHdbSPbNZYwnkaCbd1b5YBkUEPRMyyk");
-  const tokenB_mint_address = new web3.PublicKey("3kRHQT3z98KHDe5PHN2iMJgdEwKC6QgsWXjAHsbYjjmj");
-
-  const [vault_token_account_a, bump1] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("vaultTokenA"), tokenA_mint_address.toBuffer()],
+  const connection = program.provider.connection;
+  const payer = (program.provider as anchor.AnchorProvider).wallet.payer;
+  const user = program.provider.publicKey;
+
+  const TOKEN_METADATA_PROGRAM_ID = new web3.PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+  const fee_bps = 30;
+
+  let tokenA_mint_address: web3.PublicKey;
+  let tokenB_mint_address: web3.PublicKey;
+  let pool: web3.PublicKey;
+  let vault_token_account_a: web3.PublicKey;
+  let vault_token_account_b: web3.PublicKey;
+  let observations: web3.PublicKey;
+  let mint: web3.PublicKey;
+  let authorityPDA: web3.PublicKey;
+  let userPDALiquidity: web3.PublicKey;
+
+  const [config] = web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
     program.programId
   );
 
-  const [vault_token_account_b, bump2] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("vaultTokenB"), tokenB_mint_address.toBuffer()],
-    program.programId
-  );
+  const pda = (seed: string, key: web3.PublicKey) =>
+    web3.PublicKey.findProgramAddressSync([Buffer.from(seed), key.toBuffer()], program.programId)[0];
+
+  const tokenBalance = async (address: web3.PublicKey) =>
+    new BN((await getAccount(connection, address)).amount.toString());
+
+  const deadline = () => new BN(Math.floor(Date.now() / 1000) + 60);
+
+  // x * y = k with the fee taken from the input, rounded down like the program
+  const expectedSwapOutput = (amount_in: BN, reserve_in: BN, reserve_out: BN) => {
+    const fee = amount_in.muln(fee_bps).divn(10_000);
+    const amount_in_after_fee = amount_in.sub(fee);
+    return {
+      fee: fee,
+      amountOut: amount_in_after_fee.mul(reserve_out).div(reserve_in.add(amount_in_after_fee)),
+      spotOutput: amount_in_after_fee.mul(reserve_out).div(reserve_in),
+    };
+  };
+
+  const swapAccounts = () => ({
+    user: user,
+    pool: pool,
+    userTokenAccountForTokenA: getAssociatedTokenAddressSync(tokenA_mint_address, user),
+    userTokenAccountForTokenB: getAssociatedTokenAddressSync(tokenB_mint_address, user),
+    vaultTokenAAccount: vault_token_account_a,
+    vaultTokenBAccount: vault_token_account_b,
+    vaultAuthA: vault_token_account_a,
+    vaultAuthB: vault_token_account_b,
+    observations: observations,
+    mintA: tokenA_mint_address,
+    mintB: tokenB_mint_address,
+    tokenProgramA: TOKEN_PROGRAM_ID,
+    tokenProgramB: TOKEN_PROGRAM_ID,
+  });
 
-  const [vault_auth_a, bump3] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("vaultTokenA"), tokenA_mint_address.toBuffer()],
-    program.programId
-  );
+  const liquidityAccounts = () => ({
+    ...swapAccounts(),
+    userPdaAccount: userPDALiquidity,
+    mint: mint,
+    authority: authorityPDA,
+    destination: getAssociatedTokenAddressSync(mint, user),
+    destinationOwner: user,
+    payer: user,
+    rent: web3.SYSVAR_RENT_PUBKEY,
+    systemProgram: web3.SystemProgram.programId,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+  });
 
-  const [vault_auth_b, bump4] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("vaultTokenB"), tokenB_mint_address.toBuffer()],
-    program.programId
-  );
+  before(async () => {
+    const mints: web3.PublicKey[] = [];
+    for (let index = 0; index < 2; index++) {
+      const tokenMint = await createMint(connection, payer, user, null, 9);
+      const ata = await getOrCreateAssociatedTokenAccount(connection, payer, tokenMint, user);
+      await mintTo(connection, payer, tokenMint, ata.address, payer, 100_000_000_000);
+      mints.push(tokenMint);
+    }
 
-  const [userPDALiquidity, bump5] = web3.PublicKey.findProgramAddressSync(
-    [Buffer.from("userliquidityPDA"), program.provider.publicKey.toBuffer()],
-    program.programId
-  );
+    // Pools keep their mints in ascending order
+    [tokenA_mint_address, tokenB_mint_address] = mints.sort((x, y) => x.toBuffer().compare(y.toBuffer()));
 
-  it("initializes a Vault Account For Token A", async () => {
-    const [vault_token_account, bump1] = await web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("vaultTokenA"), tokenA_mint_address.toBuffer()],
+    [pool] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("pool"), tokenA_mint_address.toBuffer(), tokenB_mint_address.toBuffer()],
       program.programId
     );
-
-    const [vaultPDA, bump2] = await web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("vaultTokenA"), tokenA_mint_address.toBuffer()],
+    vault_token_account_a = pda("vaultTokenA", pool);
+    vault_token_account_b = pda("vaultTokenB", pool);
+    observations = pda("observations", pool);
+    mint = pda("mint", pool);
+    authorityPDA = pda("authority", pool);
+    [userPDALiquidity] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("userliquidityPDA"), pool.toBuffer(), user.toBuffer()],
       program.programId
     );
+  });
+
+  it("initializes the program config", async () => {
+    // anchor.ts may already have run against this validator
+    if (await connection.getAccountInfo(config)) {
+      console.log("Program config is already initialized");
+      return;
+    }
+
+    const [programData] = web3.PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+
+    const txHash = await program.methods
+      .initializeConfig(user)
+      .accounts({
+        config: config,
+        authority: user,
+        program: program.programId,
+        programData: programData,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await connection.confirmTransaction(txHash);
+  });
+
+  it("initializes a pool", async () => {
+    const txHash = await program.methods
+      .initializePool(fee_bps, { constantProduct: {} }, new BN(0), new BN(0))
+      .accounts({
+        pool: pool,
+        observations: observations,
+        mintA: tokenA_mint_address,
+        mintB: tokenB_mint_address,
+        config: config,
+        admin: user,
+        payer: user,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
+
+    // Confirm Transaction
+    await connection.confirmTransaction(txHash);
+
+    // Deposits can be withdrawn straight away in this walk-through
+    await program.methods
+      .setLockupDuration(new BN(0))
+      .accounts({ admin: user, pool: pool })
+      .rpc();
+
+    const poolAccount = await program.account.pool.fetch(pool);
+    assert.equal(poolAccount.feeBps, fee_bps);
+    assert.isTrue(poolAccount.lockupDuration.eqn(0));
+  });
 
-    console.log("This is the Token vault_token_account for Token A: ", vault_token_account.toString());
-    console.log("This is the vaultPDA for Token A: ", vaultPDA.toString());
+  it("initializes a Vault Account For Token A", async () => {
+    console.log("This is the Token vault_token_account for Token A: ", vault_token_account_a.toString());
 
     // Send Transaction
     const txHash = await program.methods
       .initializeVaultTokenA()
       .accounts({
-        vaultTokenAccount: vault_token_account,
-        vault_auth: vaultPDA,
-        payer: program.provider.publicKey,
+        vaultTokenAccount: vault_token_account_a,
+        vaultAuth: vault_token_account_a,
+        pool: pool,
+        admin: user,
+        payer: user,
         mint: tokenA_mint_address,
         systemProgram: web3.SystemProgram.programId,
-        tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgram: TOKEN_PROGRAM_ID,
         rent: web3.SYSVAR_RENT_PUBKEY,
       })
       .rpc();
@@ -70,30 +205,26 @@ describe("Test", () => {
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
-    await program.provider.connection.confirmTransaction(txHash);
+    await connection.confirmTransaction(txHash);
+
+    console.log("Vault Token A Account Balance: ", (await tokenBalance(vault_token_account_a)).toString());
   });
 
   it("initializes a Vault Account For Token B", async () => {
-    const [vault_token_account, bump1] = await web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("vaultTokenB"), tokenB_mint_address.toBuffer()],
-      program.programId
-    )
-
-    const [vaultPDA, bump2] = await web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("vaultTokenB"), tokenB_mint_address.toBuffer()],
-      program.programId
-    );
+    console.log("This is the Token vault_token_account for Token B: ", vault_token_account_b.toString());
 
     // Send Transaction
     const txHash = await program.methods
       .initializeVaultTokenB()
       .accounts({
-        vaultTokenAccount: vault_token_account,
-        vault_auth: vaultPDA,
-        payer: program.provider.publicKey,
+        vaultTokenAccount: vault_token_account_b,
+        vaultAuth: vault_token_account_b,
+        pool: pool,
+        admin: user,
+        payer: user,
         mint: tokenB_mint_address,
         systemProgram: web3.SystemProgram.programId,
-        tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        tokenProgram: TOKEN_PROGRAM_ID,
         rent: web3.SYSVAR_RENT_PUBKEY,
       })
       .rpc();
@@ -101,24 +232,15 @@ describe("Test", () => {
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
-    await program.provider.connection.confirmTransaction(txHash);
+    await connection.confirmTransaction(txHash);
 
-    const pda_token_value = await getAccount(program.provider.connection, vault_token_account);
-    console.log("Vault Token B Account Balance: ", pda_token_value.amount.toString());
+    console.log("Vault Token B Account Balance: ", (await tokenBalance(vault_token_account_b)).toString());
   });
 
   it("initializes a liquidity account", async () => {
-
-    const [userPDALiquidity, bump] = await web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("userliquidityPDA"), program.provider.publicKey.toBuffer()],
-      program.programId
-    );
-
-    const accountInfo = await program.provider.connection.getAccountInfo(userPDALiquidity);
-
     console.log("This is the user liquidity PDA: ", userPDALiquidity.toBase58());
 
-    if (accountInfo) {
+    if (await connection.getAccountInfo(userPDALiquidity)) {
       console.log("User Liquidity account is already initialized");
       return;
     }
@@ -126,8 +248,9 @@ describe("Test", () => {
     const txHash = await program.methods
       .initializeUserLiquidityAccount()
       .accounts({
-        user: program.provider.publicKey,
+        user: user,
         userPdaAccount: userPDALiquidity,
+        pool: pool,
         systemProgram: web3.SystemProgram.programId,
       })
       .rpc();
@@ -135,363 +258,185 @@ describe("Test", () => {
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
-    await program.provider.connection.confirmTransaction(txHash);
+    await connection.confirmTransaction(txHash);
   });
 
   it("Creates a Token Mint", async () => {
-    const METADATA_SEED = "metadata";
-    const TOKEN_METADATA_PROGRAM_ID = new web3.PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
-
-    const metadata = {
-      name: "LP Token",
-      symbol: "LP",
-      uri: "https://jsonkeeper.com/b/7G05",
-      decimals: 9
-    }
-
-    const [mint] = web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("mint")],
-      program.programId
-  );
-
-    const [authorityPDA] = web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("authority")],
-      program.programId
-    );
-
     const [metadataAddress] = web3.PublicKey.findProgramAddressSync(
-      [
-        Buffer.from(METADATA_SEED),
-        TOKEN_METADATA_PROGRAM_ID.toBuffer(),
-        mint.toBuffer(),
-      ],
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
       TOKEN_METADATA_PROGRAM_ID
     );
 
     const txHash = await program.methods
-      .createTokenMint(metadata)
+      .createTokenMint({ name: "LP Token", symbol: "LP", uri: "https://jsonkeeper.com/b/7G05", decimals: 9 })
       .accounts({
         metadata: metadataAddress,
         mint: mint,
         authority: authorityPDA,
-        payer: program.provider.publicKey,
+        pool: pool,
+        admin: user,
+        payer: user,
         rent: web3.SYSVAR_RENT_PUBKEY,
         systemProgram: web3.SystemProgram.programId,
-        tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
-        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID
+        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
       })
       .rpc();
 
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
-    await program.provider.connection.confirmTransaction(txHash);
+    await connection.confirmTransaction(txHash);
   });
 
   it("adds liquidity to the liquidity pool", async () => {
-
-    const [mint] = web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("mint")],
-      program.programId
-    );
-
-    const [authorityPDA] = web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("authority")],
-      program.programId
-    );
-
     console.log("This is the Mint: ", mint.toBase58());
 
-    // Deriving user Token ATA
-    const user_token_a_ata = await getAssociatedTokenAddress(
-      tokenA_mint_address,
-      program.provider.publicKey
-    );
-
-    const user_token_b_ata = await getAssociatedTokenAddress(
-      tokenB_mint_address,
-      program.provider.publicKey
-    );
-
-    const destination = await anchor.utils.token.associatedAddress({
-      mint: mint,
-      owner: program.provider.publicKey
-    });
-
-    const token_amount = new BN(5_000_000_000);
-
-    const acc = await program.provider.connection.getAccountInfo(mint);
-    console.log("Mint account exists?", !!acc);
+    // Uneven on purpose, so A is worth twice as much as B afterwards
+    const amount_a = new BN(5_000_000_000);
+    const amount_b = new BN(10_000_000_000);
 
     const txHash = await program.methods
-      .addLiquidity(token_amount)
-      .accounts({
-        user: program.provider.publicKey,
-        userPdaAccount: userPDALiquidity,
-        userTokenAccountForTokenA: user_token_a_ata,
-        userTokenAccountForTokenB: user_token_b_ata,
-        vaultTokenAAccount: vault_token_account_a,
-        vaultTokenBAccount: vault_token_account_b,
-        vaultAuthA: vault_auth_a,
-        vaultAuthB: vault_auth_b,
-        mintA: tokenA_mint_address,
-        mintB: tokenB_mint_address,
-        mint: mint,
-        authority: authorityPDA,
-        destination: destination,
-        destinationOwner: program.provider.publicKey,
-        payer: program.provider.publicKey,
-        rent: web3.SYSVAR_RENT_PUBKEY,
-        systemProgram: web3.SystemProgram.programId,
-        tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
-        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
-      })
+      .addLiquidity(amount_a, amount_b, new BN(0), deadline())
+      .accounts(liquidityAccounts())
       .rpc();
 
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
-    await program.provider.connection.confirmTransaction(txHash);
+    await connection.confirmTransaction(txHash);
+
+    // The first deposit mints sqrt(a * b), less the part the pool locks
+    const poolAccount = await program.account.pool.fetch(pool);
+    assert.isTrue((await tokenBalance(vault_token_account_a)).eq(amount_a));
+    assert.isTrue((await tokenBalance(vault_token_account_b)).eq(amount_b));
+    assert.isTrue(poolAccount.lpSupply.eq(new BN("7071067811")));
+    assert.isTrue((await tokenBalance(liquidityAccounts().destination)).eq(poolAccount.lpSupply.subn(1_000)));
   });
 
   it("removes liquidity from the liquidity pool", async () => {
+    const destination = liquidityAccounts().destination;
+    const lp_amount = (await tokenBalance(destination)).divn(5);
 
-    const [mint] = web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("mint")],
-      program.programId
-    );
-
-    const [authorityPDA] = web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("authority")],
-      program.programId
-    );
+    const reserveA = await tokenBalance(vault_token_account_a);
+    const reserveB = await tokenBalance(vault_token_account_b);
+    const lpSupply = (await program.account.pool.fetch(pool)).lpSupply;
 
-    console.log("This is the Mint: ", mint.toBase58());
-
-    const user_token_a_ata = await getAssociatedTokenAddress(
-      tokenA_mint_address,
-      program.provider.publicKey
-    );
-
-    const user_token_b_ata = await getAssociatedTokenAddress(
-      tokenB_mint_address,
-      program.provider.publicKey
-    );
-
-    const destination = await anchor.utils.token.associatedAddress({
-      mint: mint,
-      owner: program.provider.publicKey
-    });
-
-    const token_amount = new BN(5_000_000_000);
+    const balanceABefore = await tokenBalance(swapAccounts().userTokenAccountForTokenA);
+    const balanceBBefore = await tokenBalance(swapAccounts().userTokenAccountForTokenB);
 
     const txHash = await program.methods
-      .removeLiquidity(token_amount)
-      .accounts({
-        user: program.provider.publicKey,
-        userPdaAccount: userPDALiquidity,
-        userTokenAccountForTokenA: user_token_a_ata,
-        userTokenAccountForTokenB: user_token_b_ata,
-        vaultTokenAAccount: vault_token_account_a,
-        vaultTokenBAccount: vault_token_account_b,
-        vaultAuthA: vault_auth_a,
-        vaultAuthB: vault_auth_b,
-        mintA: tokenA_mint_address,
-        mintB: tokenB_mint_address,
-        mint: mint,
-        authority: authorityPDA,
-        destination: destination,
-        destinationOwner: program.provider.publicKey,
-        payer: program.provider.publicKey,
-        rent: web3.SYSVAR_RENT_PUBKEY,
-        systemProgram: web3.SystemProgram.programId,
-        tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
-        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
-      })
+      .removeLiquidity(lp_amount, new BN(0), new BN(0), deadline())
+      .accounts(liquidityAccounts())
       .rpc();
 
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
-    await program.provider.connection.confirmTransaction(txHash);
-  })
-
-  it("Swap Token B for Token A", async () => {
+    await connection.confirmTransaction(txHash);
 
-    const Token_A = await getAccount(program.provider.connection, vault_token_account_a);
-    const Token_B = await getAccount(program.provider.connection, vault_token_account_b);
-
-    const Token_A_Quantity = parseInt(Token_A.amount.toString(), 10);
-    const Token_B_Quantity = parseInt(Token_B.amount.toString(), 10);
+    // A pro-rata share of each vault, rounded down
+    const received_a = (await tokenBalance(swapAccounts().userTokenAccountForTokenA)).sub(balanceABefore);
+    const received_b = (await tokenBalance(swapAccounts().userTokenAccountForTokenB)).sub(balanceBBefore);
+    assert.isTrue(received_a.eq(lp_amount.mul(reserveA).div(lpSupply)));
+    assert.isTrue(received_b.eq(lp_amount.mul(reserveB).div(lpSupply)));
+  });
 
-    const Price_Of_Token_A = Token_B_Quantity / Token_A_Quantity;
-    const Price_Of_Token_B = Token_A_Quantity / Token_B_Quantity;
+  it("Swap Token B for Token A", async () => {
+    const Token_A_Quantity = await tokenBalance(vault_token_account_a);
+    const Token_B_Quantity = await tokenBalance(vault_token_account_b);
 
     const userSlippageTolerancePercent = 1;
 
-    // Deriving user ATA for Token B
-    const destination = await getAssociatedTokenAddress(
-      tokenB_mint_address,
-      program.provider.publicKey
-    );
+    const amount = new BN(1_000_000_000);
 
-    const userATAforTokenB = destination.toBase58();
+    const expected = expectedSwapOutput(amount, Token_B_Quantity, Token_A_Quantity);
+    const minExpectedOutput = expected.amountOut.muln(100 - userSlippageTolerancePercent).divn(100);
 
-    // Deriving user ATA for Token A
-    const destination_token_a = await getAssociatedTokenAddress(
-      tokenA_mint_address,
-      program.provider.publicKey
-    );
-
-    const userATAforTokenA = destination_token_a.toBase58();
-
-    const amount = new BN(10_000_000_000);
-
-    const expectedOutput = amount.mul(new BN(Price_Of_Token_A));
-
-    const swapFees = 0.003;
-
-    const expectedOutput_With_Swap_Fees = expectedOutput.muln(1 - swapFees);
-
-    const slippageMultiplier = 1 - userSlippageTolerancePercent;
-
-    const minExpectedOutput = expectedOutput_With_Swap_Fees.muln(slippageMultiplier * 1000).divn(10000);
+    const destination = swapAccounts().userTokenAccountForTokenA;
+    const balanceBefore = await tokenBalance(destination);
 
     const txHash = await program.methods
-      .swapBForA(amount, minExpectedOutput)
-      .accounts({
-        user: program.provider.publicKey,
-        userTokenAccountForTokenA: userATAforTokenA,
-        userTokenAccountForTokenB: userATAforTokenB,
-        vaultTokenAAccount: vault_token_account_a,
-        vaultTokenBAccount: vault_token_account_b,
-        vaultAuthA: vault_auth_a,
-        vaultAuthB: vault_auth_b,
-        mintA: tokenA_mint_address,
-        mintB: tokenB_mint_address,
-        tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
-      })
+      .swap(amount, minExpectedOutput, { bToA: {} }, deadline())
+      .accounts(swapAccounts())
       .rpc();
 
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
-    await program.provider.connection.confirmTransaction(txHash);
-  })
-
-  it("Swap Token A for Token B", async () => {
-
-    const Token_A = await getAccount(program.provider.connection, vault_token_account_a);
-    const Token_B = await getAccount(program.provider.connection, vault_token_account_b);
+    await connection.confirmTransaction(txHash);
 
-    const Token_A_Quantity = parseInt(Token_A.amount.toString(), 10);
-    const Token_B_Quantity = parseInt(Token_B.amount.toString(), 10);
+    assert.isTrue((await tokenBalance(destination)).sub(balanceBefore).eq(expected.amountOut));
+    assert.isTrue((await tokenBalance(vault_token_account_b)).eq(Token_B_Quantity.add(amount)));
+  });
 
-    const Price_Of_Token_A = Token_B_Quantity / Token_A_Quantity;
-    const Price_Of_Token_B = Token_A_Quantity / Token_B_Quantity;
+  it("Swap Token A for Token B", async () => {
+    const Token_A_Quantity = await tokenBalance(vault_token_account_a);
+    const Token_B_Quantity = await tokenBalance(vault_token_account_b);
 
     const userSlippageTolerancePercent = 1;
 
-    // Deriving user ATA for Token A
-    const destination_token_a = await getAssociatedTokenAddress(
-      tokenA_mint_address,
-      program.provider.publicKey
-    );
-
-    const userATAforTokenA = destination_token_a.toBase58();
+    const amount = new BN(1_000_000_000);
 
-    // Deriving user ATA for Token B
-    const destination = await getAssociatedTokenAddress(
-      tokenB_mint_address,
-      program.provider.publicKey
-    );
-
-    const userATAforTokenB = destination.toBase58();
-
-    const amount = new BN(10_000_000_000);
-
-    const expectedOutput = amount.mul(new BN(Price_Of_Token_B));
-
-    const swapFees = 0.003;
+    const expected = expectedSwapOutput(amount, Token_A_Quantity, Token_B_Quantity);
+    const minExpectedOutput = expected.amountOut.muln(100 - userSlippageTolerancePercent).divn(100);
 
-    const expectedOutput_With_Swap_Fees = expectedOutput.muln(1 - swapFees);
-
-    const slippageMultiplier = 1 - userSlippageTolerancePercent;
-    const minExpectedOutput = expectedOutput_With_Swap_Fees.muln(slippageMultiplier * 1000).divn(10000);
+    const destination = swapAccounts().userTokenAccountForTokenB;
+    const balanceBefore = await tokenBalance(destination);
 
     const txHash = await program.methods
-      .swapAForB(amount, minExpectedOutput)
-      .accounts({
-        user: program.provider.publicKey,
-        userTokenAccountForTokenA: userATAforTokenA,
-        userTokenAccountForTokenB: userATAforTokenB,
-        vaultTokenAAccount: vault_token_account_a,
-        vaultTokenBAccount: vault_token_account_b,
-        vaultAuthA: vault_auth_a,
-        vaultAuthB: vault_auth_b,
-        mintA: tokenA_mint_address,
-        mintB: tokenB_mint_address,
-        tokenProgram: new web3.PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
-      })
+      .swap(amount, minExpectedOutput, { aToB: {} }, deadline())
+      .accounts(swapAccounts())
       .rpc();
 
     console.log(`Use 'solana confirm -v ${txHash}' to see the logs`);
 
     // Confirm Transaction
-    await program.provider.connection.confirmTransaction(txHash);
+    await connection.confirmTransaction(txHash);
+
+    assert.isTrue((await tokenBalance(destination)).sub(balanceBefore).eq(expected.amountOut));
+    assert.isTrue((await tokenBalance(vault_token_account_a)).eq(Token_A_Quantity.add(amount)));
   });
 
   it("Price Impact Calculation when swapping Token A for Token B", async () => {
-    const Token_A = await getAccount(program.provider.connection, vault_token_account_a);
-
-    const Token_B = await getAccount(program.provider.connection, vault_token_account_b);
-
-    const Token_A_Quantity = parseInt(Token_A.amount.toString(), 10);
-    const Token_B_Quantity = parseInt(Token_B.amount.toString(), 10);
-
-    const amount = 1_000_000_000;
-
-    const k = Token_A_Quantity * Token_B_Quantity;
+    const Token_A_Quantity = await tokenBalance(vault_token_account_a);
+    const Token_B_Quantity = await tokenBalance(vault_token_account_b);
 
-    const newReserveA = Token_A_Quantity + amount;
-    const newReserveB = k / newReserveA;
-    const outputB = Token_B_Quantity - newReserveB;
+    const amount = new BN(1_000_000_000);
 
-    const swapFees = 0.003;
-    const outputBWithFees = outputB - (1 - swapFees);
+    // How far the output falls short of the spot price, the fee excluded
+    const expected = expectedSwapOutput(amount, Token_A_Quantity, Token_B_Quantity);
+    const priceImpactBps = expected.spotOutput.sub(expected.amountOut).muln(10_000).div(expected.spotOutput);
 
-    const spotPrice = Token_B_Quantity / Token_A_Quantity;
-    const swapPrice = outputBWithFees / amount;
+    const quote = await program.methods
+      .quoteSwap(amount, { aToB: {} })
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
 
-    const priceImpactPercent = ((spotPrice - swapPrice) / spotPrice) * 100;
+    assert.isTrue(quote.amountOut.eq(expected.amountOut));
+    assert.isTrue(quote.priceImpactBps.eq(priceImpactBps));
 
-    console.log("This is the price impact: ", priceImpactPercent);
+    console.log("This is the price impact: ", priceImpactBps.toNumber() / 100);
   });
 
   it("Price Impact Calculation when swapping Token B for Token A", async () => {
-    const Token_A = await getAccount(program.provider.connection, vault_token_account_a);
-
-    const Token_B = await getAccount(program.provider.connection, vault_token_account_b);
-
-    const Token_A_Quantity = parseInt(Token_A.amount.toString(), 10);
-    const Token_B_Quantity = parseInt(Token_B.amount.toString(), 10);
-
-    const amount = 1_000_000_000;
-
-    const k = Token_A_Quantity * Token_B_Quantity;
+    const Token_A_Quantity = await tokenBalance(vault_token_account_a);
+    const Token_B_Quantity = await tokenBalance(vault_token_account_b);
 
-    const newReserveB = Token_B_Quantity + amount;
-    const newReserveA = k / newReserveB;
-    const outputA = Token_A_Quantity - newReserveA;
+    const amount = new BN(1_000_000_000);
 
-    const swapFees = 0.003;
-    const outputAWithFees = outputA - (1 - swapFees);
+    // How far the output falls short of the spot price, the fee excluded
+    const expected = expectedSwapOutput(amount, Token_B_Quantity, Token_A_Quantity);
+    const priceImpactBps = expected.spotOutput.sub(expected.amountOut).muln(10_000).div(expected.spotOutput);
 
-    const spotPrice = Token_A_Quantity / Token_B_Quantity;
-    const swapPrice = outputAWithFees / amount;
+    const quote = await program.methods
+      .quoteSwap(amount, { bToA: {} })
+      .accounts({ pool: pool, mintA: tokenA_mint_address, mintB: tokenB_mint_address })
+      .view();
 
-    const priceImpactPercent = ((spotPrice - swapPrice) / spotPrice) * 100;
+    assert.isTrue(quote.amountOut.eq(expected.amountOut));
+    assert.isTrue(quote.priceImpactBps.eq(priceImpactBps));
 
-    console.log("This is the price impact: ", priceImpactPercent);
+    console.log("This is the price impact: ", priceImpactBps.toNumber() / 100);
   });
-});
\ No newline at end of file
+});